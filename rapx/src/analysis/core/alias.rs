@@ -1,7 +1,10 @@
+pub mod closure;
+pub mod container;
 pub mod mop;
 
 use rustc_data_structures::fx::FxHashMap;
 use rustc_hir::def_id::DefId;
+use serde::{Deserialize, Serialize};
 use std::{collections::HashSet, fmt};
 
 //struct to cache the results for analyzed functions.
@@ -11,7 +14,7 @@ pub type FnMap = FxHashMap<DefId, FnRetAlias>;
  * To store the alias relationships among arguments and return values.
  * Each function may have multiple return instructions, leading to different RetAlias.
  */
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FnRetAlias {
     arg_size: usize,
     alias_set: HashSet<RetAlias>,
@@ -56,10 +59,14 @@ impl fmt::Display for FnRetAlias {
     }
 }
 
+/// The field standing for the field at its position and all the fields nested in it, which ends the field
+/// sequences of a summary reaching `limits.mop-field-depth`.
+pub const WILDCARD: usize = usize::MAX - 2;
+
 /*
  * To store the alias relationships among arguments and return values.
  */
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RetAlias {
     pub left_index: usize,
     pub left_field_seq: Vec<usize>,
@@ -100,7 +107,10 @@ impl RetAlias {
         }
         for num in fields.iter() {
             result.push('.');
-            result.push_str(&num.to_string());
+            match *num {
+                WILDCARD => result.push('*'),
+                _ => result.push_str(&num.to_string()),
+            }
         }
         result
     }
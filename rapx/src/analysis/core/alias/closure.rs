@@ -0,0 +1,136 @@
+use rustc_hir::def_id::DefId;
+use rustc_hir::LangItem;
+use rustc_middle::mir::{AggregateKind, Body, Operand, Place, PlaceRef};
+use rustc_middle::ty::{self, GenericArgsRef, TyCtxt};
+use rustc_span::source_map::Spanned;
+use rustc_target::abi::{FieldIdx, VariantIdx};
+
+/*
+ * Closures and coroutines are analyzed as functions of their own, and their environments as structs.
+ * The upvars captured by a closure or an `async` block are the fields of its environment, in the order of
+ * the operands of the aggregate creating it. The locals of a coroutine kept across a `yield` or an `.await` are
+ * stored in the variants of its state machine; a local kept across several suspension points is a field of
+ * each of their variants, so the fields of the variants are numbered by the saved locals instead.
+ */
+
+/// Whether the operands of the aggregate are assigned to its fields one by one, instead of aliasing it as a whole.
+///
+/// These are the environments of closures and coroutines, and the tuples, which pass the arguments of closures.
+pub fn by_field(kind: &AggregateKind<'_>) -> bool {
+    matches!(
+        kind,
+        AggregateKind::Tuple
+            | AggregateKind::Closure(..)
+            | AggregateKind::Coroutine(..)
+            | AggregateKind::CoroutineClosure(..)
+    )
+}
+
+/// The field of the aggregate holding its i-th operand.
+pub fn aggregate_field<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    body: &Body<'tcx>,
+    aggregate: Place<'tcx>,
+    i: usize,
+    operand: &Operand<'tcx>,
+) -> Place<'tcx> {
+    tcx.mk_place_field(
+        aggregate,
+        FieldIdx::from_usize(i),
+        operand.ty(&body.local_decls, tcx),
+    )
+}
+
+/// The body that a call runs, with the places passed to its arguments in the order of its locals.
+///
+/// `Fn::call`, `FnMut::call_mut` and `FnOnce::call_once` run the closure they are called on, with the
+/// arguments untupled; `Future::poll` on a coroutine resumes its body. Other callees are returned as they are,
+/// and constant arguments are `None`.
+pub fn resolve_call<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    body: &Body<'tcx>,
+    callee: DefId,
+    generic_args: GenericArgsRef<'tcx>,
+    args: &[Spanned<Operand<'tcx>>],
+) -> (DefId, Vec<Option<Place<'tcx>>>) {
+    let places = args.iter().map(|arg| arg.node.place()).collect::<Vec<_>>();
+    let Some(self_ty) = generic_args.types().next() else {
+        return (callee, places);
+    };
+    match self_ty.kind() {
+        ty::Closure(closure, _) if is_fn_call(tcx, callee) && args.len() == 2 => {
+            let mut untupled = vec![places[0]];
+            let tuple_ty = args[1].node.ty(&body.local_decls, tcx);
+            for (i, ty) in tuple_ty.tuple_fields().iter().enumerate() {
+                untupled.push(
+                    places[1].map(|tuple| tcx.mk_place_field(tuple, FieldIdx::from_usize(i), ty)),
+                );
+            }
+            (*closure, untupled)
+        }
+        ty::Coroutine(coroutine, _) if tcx.is_lang_item(callee, LangItem::FuturePoll) => {
+            (*coroutine, places)
+        }
+        _ => (callee, places),
+    }
+}
+
+fn is_fn_call(tcx: TyCtxt<'_>, callee: DefId) -> bool {
+    tcx.trait_of_item(callee)
+        .is_some_and(|trait_id| tcx.fn_trait_kind_from_def_id(trait_id).is_some())
+}
+
+/// The fields of the state machine of a coroutine, numbered by its upvars and then by its saved locals.
+#[derive(Debug, Clone)]
+pub struct CoroutineFields {
+    def_id: DefId,
+    upvars: usize,
+    // the saved local stored in each field of each variant.
+    variants: Vec<Vec<usize>>,
+}
+
+impl CoroutineFields {
+    /// The fields of the coroutine whose body it is, if it is the body of a coroutine.
+    pub fn new<'tcx>(tcx: TyCtxt<'tcx>, body: &Body<'tcx>) -> Option<Self> {
+        let layout = body.coroutine_layout_raw()?;
+        let def_id = body.source.def_id();
+        let ty::Coroutine(_, args) = tcx.type_of(def_id).instantiate_identity().kind() else {
+            return None;
+        };
+        Some(CoroutineFields {
+            def_id,
+            upvars: args.as_coroutine().upvar_tys().len(),
+            variants: layout
+                .variant_fields
+                .iter()
+                .map(|fields| fields.iter().map(|local| local.as_usize()).collect())
+                .collect(),
+        })
+    }
+
+    /// The variant of the state machine that the place is downcast to, if it is the state machine.
+    pub fn downcast<'tcx>(
+        &self,
+        tcx: TyCtxt<'tcx>,
+        body: &Body<'tcx>,
+        base: PlaceRef<'tcx>,
+        variant: VariantIdx,
+    ) -> Option<usize> {
+        match base.ty(body, tcx).ty.kind() {
+            ty::Coroutine(def_id, _) if *def_id == self.def_id => Some(variant.as_usize()),
+            _ => None,
+        }
+    }
+
+    /// The number of the field of a variant, which is the same in all the variants storing the saved local.
+    pub fn field(&self, variant: usize, field: usize) -> usize {
+        match self
+            .variants
+            .get(variant)
+            .and_then(|fields| fields.get(field))
+        {
+            Some(local) => self.upvars + local,
+            None => field,
+        }
+    }
+}
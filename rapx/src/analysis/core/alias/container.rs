@@ -0,0 +1,235 @@
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::{self, GenericArgsRef, TyCtxt};
+use std::sync::OnceLock;
+
+use crate::analysis::utils::def_path::def_path_def_ids;
+use crate::rap_debug;
+
+/// The virtual field of a container that stands for all its elements.
+///
+/// There is only one such node per container, so the elements are not told apart: removing one
+/// element detaches all of them from the container, and clearing the container drops all of them.
+///
+/// Element nodes are not owned by the container in the sense of SafeDrop: a dropped element does
+/// not make the container dangling, and dropping the container does not free an element twice.
+pub const ELEMENT: usize = usize::MAX - 1;
+
+/*
+ * Built-in summaries of std collections, `Option`, `Box`, `Rc`, `Arc` and `RefCell`.
+ * Their MIR is available, but analyzing it loses track of the elements behind the raw buffers,
+ * so the calls are modeled by how they move the ownership of the elements instead.
+ * The container is the first argument, or the return value for `New`. As for aggregates, `Some(x)` is modeled as an
+ * alias of `x` rather than a field holding it.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContainerOp {
+    /// The argument is moved into the container, e.g., `Vec::push(&mut self, value)` with 2 for
+    /// `value`; 1, 2, 3, ... are the arguments as in `RetAlias`.
+    Insert(usize),
+    /// An element is moved out of the container and returned, e.g., `Vec::pop`.
+    /// As all elements share the `ELEMENT` node, the container no longer holds any of them afterwards.
+    Remove,
+    /// The return value points to the elements, e.g., `Vec::as_ptr`.
+    Borrow,
+    /// The elements are dropped, e.g., `Vec::clear`.
+    Clear,
+    /// The return value aliases the first argument, e.g., `Option::unwrap`.
+    Alias,
+    /// The return value is a new container holding the argument, e.g., `Rc::new(value)` with 1.
+    New(usize),
+    /// The return value shares the elements of the first argument without aliasing it, e.g.,
+    /// `Rc::clone`. The elements of `Rc` and `Arc` are dropped with the last pointer sharing them.
+    Share,
+    /// The elements are returned as a pointer and no longer owned by the container, e.g., `Rc::into_raw`.
+    Release,
+    /// The return value owns the elements pointed to by the first argument, e.g., `Rc::from_raw`.
+    Adopt,
+}
+
+use ContainerOp::*;
+
+const SUMMARIES: &[(&str, &[ContainerOp])] = &[
+    ("alloc::vec::Vec::push", &[Insert(2)]),
+    ("alloc::vec::Vec::insert", &[Insert(3)]),
+    ("alloc::vec::Vec::pop", &[Remove]),
+    ("alloc::vec::Vec::remove", &[Remove]),
+    ("alloc::vec::Vec::swap_remove", &[Remove]),
+    ("alloc::vec::Vec::clear", &[Clear]),
+    ("alloc::vec::Vec::truncate", &[Clear]),
+    ("alloc::vec::Vec::as_ptr", &[Borrow]),
+    ("alloc::vec::Vec::as_mut_ptr", &[Borrow]),
+    ("alloc::vec::Vec::as_slice", &[Borrow]),
+    ("alloc::vec::Vec::as_mut_slice", &[Borrow]),
+    ("alloc::collections::VecDeque::push_back", &[Insert(2)]),
+    ("alloc::collections::VecDeque::push_front", &[Insert(2)]),
+    ("alloc::collections::VecDeque::insert", &[Insert(3)]),
+    ("alloc::collections::VecDeque::pop_back", &[Remove]),
+    ("alloc::collections::VecDeque::pop_front", &[Remove]),
+    ("alloc::collections::VecDeque::remove", &[Remove]),
+    ("alloc::collections::VecDeque::clear", &[Clear]),
+    ("alloc::collections::VecDeque::truncate", &[Clear]),
+    ("alloc::collections::VecDeque::get", &[Borrow]),
+    ("alloc::collections::VecDeque::get_mut", &[Borrow]),
+    ("alloc::collections::VecDeque::front", &[Borrow]),
+    ("alloc::collections::VecDeque::back", &[Borrow]),
+    ("alloc::collections::VecDeque::front_mut", &[Borrow]),
+    ("alloc::collections::VecDeque::back_mut", &[Borrow]),
+    // The replaced value is returned before the new one is inserted.
+    (
+        "std::collections::HashMap::insert",
+        &[Remove, Insert(2), Insert(3)],
+    ),
+    ("std::collections::HashMap::remove", &[Remove]),
+    ("std::collections::HashMap::clear", &[Clear]),
+    ("std::collections::HashMap::get", &[Borrow]),
+    ("std::collections::HashMap::get_mut", &[Borrow]),
+    (
+        "alloc::collections::BTreeMap::insert",
+        &[Remove, Insert(2), Insert(3)],
+    ),
+    ("alloc::collections::BTreeMap::remove", &[Remove]),
+    ("alloc::collections::BTreeMap::pop_first", &[Remove]),
+    ("alloc::collections::BTreeMap::pop_last", &[Remove]),
+    ("alloc::collections::BTreeMap::clear", &[Clear]),
+    ("alloc::collections::BTreeMap::get", &[Borrow]),
+    ("alloc::collections::BTreeMap::get_mut", &[Borrow]),
+    // A slice points to the elements of the container it is borrowed from.
+    ("slice::get", &[Alias]),
+    ("slice::get_mut", &[Alias]),
+    ("slice::first", &[Alias]),
+    ("slice::first_mut", &[Alias]),
+    ("slice::last", &[Alias]),
+    ("slice::last_mut", &[Alias]),
+    ("slice::get_unchecked", &[Alias]),
+    ("slice::get_unchecked_mut", &[Alias]),
+    ("slice::as_ptr", &[Alias]),
+    ("slice::as_mut_ptr", &[Alias]),
+    ("core::option::Option::unwrap", &[Alias]),
+    ("core::option::Option::expect", &[Alias]),
+    ("core::option::Option::unwrap_unchecked", &[Alias]),
+    // The payload is moved out of the option, which no longer owns it.
+    ("core::option::Option::take", &[Remove]),
+    ("core::option::Option::replace", &[Remove, Insert(2)]),
+    ("core::option::Option::as_ref", &[Alias]),
+    ("core::option::Option::as_mut", &[Alias]),
+    ("alloc::boxed::Box::into_raw", &[Alias]),
+    ("alloc::boxed::Box::from_raw", &[Alias]),
+    ("alloc::boxed::Box::leak", &[Alias]),
+    // The payload of `Rc` and `Arc` is modeled as their elements.
+    ("alloc::rc::Rc::new", &[New(1)]),
+    ("alloc::rc::Rc::into_raw", &[Release]),
+    ("alloc::rc::Rc::from_raw", &[Adopt]),
+    ("alloc::rc::Rc::as_ptr", &[Borrow]),
+    ("alloc::rc::Rc::get_mut", &[Borrow]),
+    ("alloc::rc::Rc::downgrade", &[Share]),
+    ("alloc::rc::Weak::as_ptr", &[Borrow]),
+    ("alloc::rc::Weak::upgrade", &[Share]),
+    ("alloc::sync::Arc::new", &[New(1)]),
+    ("alloc::sync::Arc::into_raw", &[Release]),
+    ("alloc::sync::Arc::from_raw", &[Adopt]),
+    ("alloc::sync::Arc::as_ptr", &[Borrow]),
+    ("alloc::sync::Arc::get_mut", &[Borrow]),
+    ("alloc::sync::Arc::downgrade", &[Share]),
+    ("alloc::sync::Weak::as_ptr", &[Borrow]),
+    ("alloc::sync::Weak::upgrade", &[Share]),
+    // A guard of `RefCell` points to the value in the cell.
+    ("core::cell::RefCell::borrow", &[Alias]),
+    ("core::cell::RefCell::borrow_mut", &[Alias]),
+    ("core::cell::RefCell::as_ptr", &[Alias]),
+    ("core::cell::RefCell::get_mut", &[Alias]),
+];
+
+/// Trait methods that are summarized only if `self` is one of `CONTAINERS`.
+const TRAIT_SUMMARIES: &[(&str, &[ContainerOp])] = &[
+    ("core::ops::Index::index", &[Borrow]),
+    ("core::ops::IndexMut::index_mut", &[Borrow]),
+    ("core::ops::Deref::deref", &[Borrow]),
+    ("core::ops::DerefMut::deref_mut", &[Borrow]),
+];
+
+/// Trait methods that are summarized only if `self` is one of `COUNTED`.
+const COUNTED_TRAIT_SUMMARIES: &[(&str, &[ContainerOp])] = &[
+    ("core::clone::Clone::clone", &[Share]),
+    ("core::ops::Deref::deref", &[Borrow]),
+];
+
+const COUNTED: &[&str] = &[
+    "alloc::rc::Rc",
+    "alloc::rc::Weak",
+    "alloc::sync::Arc",
+    "alloc::sync::Weak",
+];
+
+const CONTAINERS: &[&str] = &[
+    "alloc::vec::Vec",
+    "alloc::collections::VecDeque",
+    "std::collections::HashMap",
+    "alloc::collections::BTreeMap",
+];
+
+struct Summaries {
+    functions: FxHashMap<DefId, &'static [ContainerOp]>,
+    trait_methods: FxHashMap<DefId, &'static [ContainerOp]>,
+    containers: FxHashSet<DefId>,
+    counted_methods: FxHashMap<DefId, &'static [ContainerOp]>,
+    counted: FxHashSet<DefId>,
+}
+
+static RESOLVED: OnceLock<Summaries> = OnceLock::new();
+
+fn resolve(tcx: TyCtxt<'_>, path: &str) -> Vec<DefId> {
+    let segments: Vec<&str> = path.split("::").collect();
+    let def_ids: Vec<DefId> = def_path_def_ids(&tcx, &segments).collect();
+    if def_ids.is_empty() {
+        // e.g., `std` is not available in `no_std` crates
+        rap_debug!(
+            "Failed to resolve the container summary of path `{}`.",
+            path
+        );
+    }
+    def_ids
+}
+
+/// Resolve the functions of the built-in summaries in the current compilation session.
+pub fn init(tcx: TyCtxt<'_>) {
+    RESOLVED.get_or_init(|| {
+        let resolve_all = |summaries: &[(&str, &'static [ContainerOp])]| {
+            let mut map = FxHashMap::default();
+            for (path, ops) in summaries {
+                for def_id in resolve(tcx, path) {
+                    map.insert(def_id, *ops);
+                }
+            }
+            map
+        };
+        Summaries {
+            functions: resolve_all(SUMMARIES),
+            trait_methods: resolve_all(TRAIT_SUMMARIES),
+            containers: CONTAINERS
+                .iter()
+                .flat_map(|path| resolve(tcx, path))
+                .collect(),
+            counted_methods: resolve_all(COUNTED_TRAIT_SUMMARIES),
+            counted: COUNTED.iter().flat_map(|path| resolve(tcx, path)).collect(),
+        }
+    });
+}
+
+/// The built-in summary of the callee, if any.
+pub fn container_ops(def_id: DefId, args: GenericArgsRef<'_>) -> Option<&'static [ContainerOp]> {
+    let summaries = RESOLVED.get()?;
+    if let Some(ops) = summaries.functions.get(&def_id) {
+        return Some(ops);
+    }
+    let ty::Adt(adt_def, _) = args.types().next()?.peel_refs().kind() else {
+        return None;
+    };
+    if summaries.containers.contains(&adt_def.did()) {
+        return summaries.trait_methods.get(&def_id).copied();
+    }
+    if summaries.counted.contains(&adt_def.did()) {
+        return summaries.counted_methods.get(&def_id).copied();
+    }
+    None
+}
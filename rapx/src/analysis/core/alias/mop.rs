@@ -1,25 +1,38 @@
 pub mod alias;
 pub mod graph;
 pub mod mop;
+pub mod query;
 pub mod types;
 
-use crate::analysis::core::alias::{FnMap, RetAlias};
-use crate::analysis::utils::intrinsic_id::{
-    COPY_FROM, COPY_FROM_NONOVERLAPPING, COPY_TO, COPY_TO_NONOVERLAPPING,
-};
+use crate::analysis::core::alias::{FnMap, RetAlias, WILDCARD};
+use crate::analysis::utils::intrinsic_id::{is_intrinsic, Intrinsic};
+use crate::utils::cache;
+use crate::utils::config::config;
 use crate::utils::source::*;
+use crate::utils::summary;
 use crate::{rap_debug, rap_trace};
 use graph::MopGraph;
+use query::FnAliasFacts;
 use rustc_data_structures::fx::FxHashMap;
 use rustc_data_structures::fx::FxHashSet;
 use rustc_middle::ty::TyCtxt;
 use rustc_span::def_id::DefId;
 
-pub const VISIT_LIMIT: usize = 100;
+/// The maximum number of visited paths of a function, set by `limits.mop-visit` in the configuration.
+pub fn visit_limit() -> usize {
+    config().limits.mop_visit
+}
+
+/// The maximum depth of the distinguished fields, set by `limits.mop-field-depth` in the configuration.
+pub fn field_depth() -> usize {
+    config().limits.mop_field_depth
+}
 
 pub struct MopAlias<'tcx> {
     pub tcx: TyCtxt<'tcx>,
     pub fn_map: FnMap,
+    // the aliases found within the functions, see `query`.
+    pub facts: FxHashMap<DefId, FnAliasFacts<'tcx>>,
 }
 
 impl<'tcx> MopAlias<'tcx> {
@@ -27,11 +40,18 @@ impl<'tcx> MopAlias<'tcx> {
         Self {
             tcx,
             fn_map: FxHashMap::default(),
+            facts: FxHashMap::default(),
         }
     }
 
     pub fn start(&mut self) -> &FnMap {
         rap_debug!("Start alias analysis via MoP.");
+        if let Some(fn_map) = cache::fn_map(self.tcx) {
+            self.fn_map = fn_map;
+            return &self.fn_map;
+        }
+        // Functions of dependencies are summarized when cargo-rapx compiles them.
+        self.fn_map = summary::fn_map(self.tcx);
         for local_def_id in self.tcx.iter_local_def_id() {
             let hir_map = self.tcx.hir();
             if hir_map.maybe_body_owned_by(local_def_id).is_some() {
@@ -46,19 +66,17 @@ impl<'tcx> MopAlias<'tcx> {
             }
         }
         self.handle_conor_cases();
+        cache::store_fn_map(self.tcx, &self.fn_map);
         &self.fn_map
     }
 
     pub fn handle_conor_cases(&mut self) {
-        let cases = [
-            COPY_FROM_NONOVERLAPPING,
-            COPY_TO_NONOVERLAPPING,
-            COPY_TO,
-            COPY_FROM,
-        ];
-        let alias = RetAlias::new(1, true, true, 2, true, true);
+        // a copy makes the pointee of the destination and its fields alias those of the source.
+        let mut alias = RetAlias::new(1, true, true, 2, true, true);
+        alias.left_field_seq.push(WILDCARD);
+        alias.right_field_seq.push(WILDCARD);
         for (key, value) in self.fn_map.iter_mut() {
-            if cases.contains(&key.index.as_usize()) {
+            if is_intrinsic(*key, Intrinsic::Copy) {
                 value.alias_set.clear();
                 value.alias_set.insert(alias.clone());
             }
@@ -78,9 +96,10 @@ impl<'tcx> MopAlias<'tcx> {
             mop_graph.solve_scc();
             let mut recursion_set = FxHashSet::default();
             mop_graph.check(0, &mut self.fn_map, &mut recursion_set);
-            if mop_graph.visit_times > VISIT_LIMIT {
+            if mop_graph.visit_times > visit_limit() {
                 rap_trace!("Over visited: {:?}", def_id);
             }
+            self.fn_map.insert(def_id, mop_graph.ret_alias);
         } else {
             rap_trace!("mir is not available at {}", self.tcx.def_path_str(def_id));
         }
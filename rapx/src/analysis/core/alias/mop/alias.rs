@@ -1,7 +1,10 @@
+use super::field_depth;
 use super::graph::*;
 use super::types::*;
-use crate::analysis::core::alias::{FnMap, RetAlias};
-use crate::analysis::utils::intrinsic_id::*;
+use crate::analysis::core::alias::closure::resolve_call;
+use crate::analysis::core::alias::container::{container_ops, ContainerOp, ELEMENT};
+use crate::analysis::core::alias::{FnMap, RetAlias, WILDCARD};
+use crate::analysis::utils::intrinsic_id::{intrinsic, is_allocator, is_intrinsic, Intrinsic};
 use crate::{rap_debug, rap_error};
 use rustc_data_structures::fx::FxHashSet;
 use rustc_hir::def_id::DefId;
@@ -11,8 +14,9 @@ use rustc_middle::ty;
 impl<'tcx> MopGraph<'tcx> {
     /* alias analysis for a single block */
     pub fn alias_bb(&mut self, bb_index: usize) {
-        for stmt in self.blocks[bb_index].const_value.clone() {
-            self.constant.insert(stmt.0, stmt.1);
+        for (place, value) in self.blocks[bb_index].const_value.clone() {
+            let node = self.projection(false, place);
+            self.constant.insert(node, value);
         }
         let cur_block = self.blocks[bb_index].clone();
         for assign in cur_block.assignments {
@@ -61,34 +65,46 @@ impl<'tcx> MopGraph<'tcx> {
                 if self.values[lv].may_drop {
                     may_drop_flag += 1;
                 }
-                for arg in args {
-                    match arg.node {
-                        Operand::Copy(ref p) => {
-                            let rv = self.projection(true, *p);
-                            merge_vec.push(rv);
-                            if self.values[rv].may_drop {
-                                may_drop_flag += 1;
+                if let ty::FnDef(ref target_id, generic_args) = constant.const_.ty().kind() {
+                    // calls of closures and coroutines run their bodies.
+                    let body = self.tcx.optimized_mir(self.def_id);
+                    let (callee, places) =
+                        resolve_call(self.tcx, body, *target_id, generic_args, args);
+                    for place in places {
+                        match place {
+                            Some(p) => {
+                                let rv = self.projection(true, p);
+                                merge_vec.push(rv);
+                                if self.values[rv].may_drop {
+                                    may_drop_flag += 1;
+                                }
                             }
-                        }
-                        Operand::Move(ref p) => {
-                            let rv = self.projection(true, *p);
-                            merge_vec.push(rv);
-                            if self.values[rv].may_drop {
-                                may_drop_flag += 1;
+                            None => {
+                                merge_vec.push(0);
                             }
                         }
-                        Operand::Constant(_) => {
-                            merge_vec.push(0);
-                        }
                     }
-                }
-                if let ty::FnDef(ref target_id, _) = constant.const_.ty().kind() {
+                    // the pointer returned by an allocator is a new allocation
+                    if is_allocator(*target_id) {
+                        continue;
+                    }
+                    if let Some(ops) = container_ops(*target_id, generic_args) {
+                        self.container_call(ops, &merge_vec);
+                        continue;
+                    }
+                    // copying values that own no heap item aliases nothing.
+                    if is_intrinsic(*target_id, Intrinsic::Copy)
+                        && is_not_drop(self.tcx, generic_args.type_at(0))
+                    {
+                        continue;
+                    }
                     //if may_drop_flag > 1 || Self::should_check(target_id.clone()) == false {
                     if may_drop_flag > 0 {
-                        if self.tcx.is_mir_available(*target_id) {
-                            rap_debug!("target_id {:?}", target_id);
-                            if fn_map.contains_key(target_id) {
-                                let assignments = fn_map.get(target_id).unwrap();
+                        // Callees in dependencies may only have summaries.
+                        if self.tcx.is_mir_available(callee) || fn_map.contains_key(&callee) {
+                            rap_debug!("target_id {:?}", callee);
+                            if fn_map.contains_key(&callee) {
+                                let assignments = fn_map.get(&callee).unwrap();
                                 for assign in assignments.aliases().iter() {
                                     if !assign.valuable() {
                                         continue;
@@ -97,11 +113,11 @@ impl<'tcx> MopGraph<'tcx> {
                                 }
                             } else {
                                 /* Fixed-point iteration: this is not perfect */
-                                if recursion_set.contains(target_id) {
+                                if recursion_set.contains(&callee) {
                                     continue;
                                 }
-                                recursion_set.insert(*target_id);
-                                let mut mop_graph = MopGraph::new(self.tcx, *target_id);
+                                recursion_set.insert(callee);
+                                let mut mop_graph = MopGraph::new(self.tcx, callee);
                                 mop_graph.solve_scc();
                                 mop_graph.check(0, fn_map, recursion_set);
                                 let ret_alias = mop_graph.ret_alias.clone();
@@ -111,13 +127,14 @@ impl<'tcx> MopGraph<'tcx> {
                                     }
                                     self.merge(assign, &merge_vec);
                                 }
-                                fn_map.insert(*target_id, ret_alias);
-                                recursion_set.remove(target_id);
+                                fn_map.insert(callee, ret_alias);
+                                recursion_set.remove(&callee);
                             }
                         } else if self.values[lv].may_drop {
-                            if target_id.index.as_usize() == CALL_MUT
-                                || target_id.index.as_usize() == NEXT
-                            {
+                            if matches!(
+                                intrinsic(callee),
+                                Some(Intrinsic::CallMut | Intrinsic::Next)
+                            ) {
                                 continue;
                             }
 
@@ -140,6 +157,132 @@ impl<'tcx> MopGraph<'tcx> {
         }
     }
 
+    // apply the built-in summary of a std collection, `Option` or `Box`.
+    pub fn container_call(&mut self, ops: &[ContainerOp], merge_vec: &[usize]) {
+        if merge_vec.len() < 2 {
+            return;
+        }
+        let (ret, container) = (merge_vec[0], merge_vec[1]);
+        for op in ops {
+            let (lv, rv) = match *op {
+                ContainerOp::Insert(arg) => match merge_vec.get(arg) {
+                    Some(&value) if self.values[value].may_drop => (self.element(container), value),
+                    _ => continue,
+                },
+                ContainerOp::Remove => {
+                    if !self.values[ret].may_drop {
+                        continue;
+                    }
+                    let element = self.element(container);
+                    self.detach_element(container);
+                    (ret, element)
+                }
+                ContainerOp::Borrow => {
+                    if !self.values[ret].may_drop {
+                        continue;
+                    }
+                    (ret, self.element(container))
+                }
+                ContainerOp::Clear => {
+                    self.detach_element(container);
+                    continue;
+                }
+                ContainerOp::Alias => {
+                    if !self.values[ret].may_drop || !self.values[container].may_drop {
+                        continue;
+                    }
+                    (ret, container)
+                }
+                ContainerOp::New(arg) => match merge_vec.get(arg) {
+                    Some(&value) if self.values[value].may_drop => (self.element(ret), value),
+                    _ => continue,
+                },
+                ContainerOp::Share => {
+                    let element = self.element(container);
+                    self.link_element(ret, element);
+                    continue;
+                }
+                ContainerOp::Release => {
+                    if !self.values[ret].may_drop {
+                        continue;
+                    }
+                    let element = self.element(container);
+                    self.detach_element(container);
+                    (ret, element)
+                }
+                ContainerOp::Adopt => {
+                    if !self.values[container].may_drop {
+                        continue;
+                    }
+                    self.link_element(ret, container);
+                    continue;
+                }
+            };
+            self.merge_alias(lv, rv);
+        }
+    }
+
+    /*
+     * The node of the elements of a container, which is shared by all the aliases of the container.
+     * It is named after the smallest local among the aliases, so that the elements of an argument
+     * are summarized as its fields.
+     */
+    pub fn element(&mut self, container: usize) -> usize {
+        let mut aliases = Vec::new();
+        for i in 0..self.alias_set.len() {
+            if self.union_is_same(i, container) {
+                aliases.push(i);
+            }
+        }
+        let element = match aliases
+            .iter()
+            .find_map(|alias| self.values[*alias].fields.get(&ELEMENT).copied())
+        {
+            Some(element) => element,
+            None => {
+                let father = aliases
+                    .iter()
+                    .copied()
+                    .min_by_key(|alias| self.values[*alias].local)
+                    .unwrap_or(container);
+                let local = self.values[father].local;
+                let mut node = ValueNode::new(self.values.len(), local, true, true);
+                node.father = father;
+                node.field_id = ELEMENT;
+                self.alias_set.push(self.values.len());
+                self.values.push(node);
+                self.values.len() - 1
+            }
+        };
+        self.link_element(container, element);
+        element
+    }
+
+    // make the node the elements of all the aliases of the container.
+    pub fn link_element(&mut self, container: usize, element: usize) {
+        for alias in 0..self.alias_set.len() {
+            if !self.union_is_same(alias, container) {
+                continue;
+            }
+            match self.values[alias].fields.get(&ELEMENT).copied() {
+                Some(other) if other != element => self.merge_alias(element, other),
+                Some(_) => {}
+                None => {
+                    self.values[alias].fields.insert(ELEMENT, element);
+                }
+            }
+        }
+    }
+
+    // the elements moved out of a container are no longer the elements of the container.
+    pub fn detach_element(&mut self, container: usize) {
+        for i in 0..self.alias_set.len() {
+            if self.union_is_same(i, container) {
+                self.values[i].fields.remove(&ELEMENT);
+            }
+        }
+    }
+
     /*
      * This is the function for field sensitivity
      * If the projection is a deref, we directly return its head alias or alias[0].
@@ -147,9 +290,11 @@ impl<'tcx> MopGraph<'tcx> {
      *
      */
     pub fn projection(&mut self, is_right: bool, place: Place<'tcx>) -> usize {
+        let place = self.deref_copy(place);
         let mut local = place.local.as_usize();
         let mut proj_id: usize = local;
-        for proj in place.projection {
+        let mut variant = None;
+        for (base, proj) in place.iter_projections() {
             let new_id = self.values.len();
             match proj {
                 ProjectionElem::Deref => {
@@ -163,7 +308,16 @@ impl<'tcx> MopGraph<'tcx> {
                         proj_id = self.values[proj_id].index;
                         local = self.values[proj_id].local;
                     }
-                    let field_idx = field.as_usize();
+                    let field_idx = match (variant.take(), &self.coroutine) {
+                        (Some(variant), Some(coroutine)) => {
+                            coroutine.field(variant, field.as_usize())
+                        }
+                        _ => field.as_usize(),
+                    };
+                    // the fields beyond the depth limit are merged into their ancestor at the limit.
+                    if self.depth(proj_id) >= field_depth() {
+                        continue;
+                    }
                     if let std::collections::hash_map::Entry::Vacant(e) =
                         self.values[proj_id].fields.entry(field_idx)
                     {
@@ -173,6 +327,7 @@ impl<'tcx> MopGraph<'tcx> {
                         let mut node =
                             ValueNode::new(new_id, local, need_drop, need_drop || may_drop);
                         node.kind = kind(ty);
+                        node.father = proj_id;
                         node.field_id = field_idx;
                         e.insert(node.index);
                         self.alias_set.push(self.values.len());
@@ -180,12 +335,27 @@ impl<'tcx> MopGraph<'tcx> {
                     }
                     proj_id = *self.values[proj_id].fields.get(&field_idx).unwrap();
                 }
+                // the fields of the variants of a coroutine are numbered by its saved locals.
+                ProjectionElem::Downcast(_, index) => {
+                    if let Some(coroutine) = &self.coroutine {
+                        let body = self.tcx.optimized_mir(self.def_id);
+                        variant = coroutine.downcast(self.tcx, body, base, index);
+                    }
+                }
                 _ => {}
             }
         }
         proj_id
     }
 
+    // the place behind the pointer copied into a `deref_copy` temporary.
+    pub fn deref_copy(&self, mut place: Place<'tcx>) -> Place<'tcx> {
+        while let Some(base) = self.deref_copies.get(&place.local.as_usize()) {
+            place = base.project_deeper(place.projection, self.tcx);
+        }
+        place
+    }
+
     //assign alias for a variable.
     //TO FIX
     pub fn merge_alias(&mut self, lv: usize, rv: usize) {
@@ -209,6 +379,7 @@ impl<'tcx> MopGraph<'tcx> {
                     self.values[field.1].may_drop,
                 );
                 node.kind = self.values[field.1].kind;
+                node.father = lv;
                 node.field_id = field.0;
                 self.values[lv].fields.insert(field.0, node.index);
                 self.alias_set.push(self.values.len());
@@ -226,16 +397,27 @@ impl<'tcx> MopGraph<'tcx> {
             rap_error!("Vector error!");
             return;
         }
+        // constant arguments are passed as the node 0, which is the return value of the caller.
+        if (ret_alias.left_index > 0 && arg_vec[ret_alias.left_index] == 0)
+            || (ret_alias.right_index > 0 && arg_vec[ret_alias.right_index] == 0)
+        {
+            return;
+        }
         let left_init = arg_vec[ret_alias.left_index];
         let mut right_init = arg_vec[ret_alias.right_index];
         let mut lv = left_init;
         let mut rv = right_init;
         for index in ret_alias.left_field_seq.iter() {
+            // a wildcard stands for the field reached so far and all the fields nested in it.
+            if *index == WILDCARD {
+                break;
+            }
             if !self.values[lv].fields.contains_key(index) {
                 let need_drop = ret_alias.left_need_drop;
                 let may_drop = ret_alias.left_may_drop;
                 let mut node = ValueNode::new(self.values.len(), left_init, need_drop, may_drop);
                 node.kind = TyKind::RawPtr;
+                node.father = lv;
                 node.field_id = *index;
                 self.values[lv].fields.insert(*index, node.index);
                 self.alias_set.push(self.values.len());
@@ -244,6 +426,9 @@ impl<'tcx> MopGraph<'tcx> {
             lv = *self.values[lv].fields.get(index).unwrap();
         }
         for index in ret_alias.right_field_seq.iter() {
+            if *index == WILDCARD {
+                break;
+            }
             if self.union_is_same(rv, self.alias_set[rv]) {
                 right_init = self.values[rv].local;
             }
@@ -252,6 +437,7 @@ impl<'tcx> MopGraph<'tcx> {
                 let may_drop = ret_alias.right_may_drop;
                 let mut node = ValueNode::new(self.values.len(), right_init, need_drop, may_drop);
                 node.kind = TyKind::RawPtr;
+                node.father = rv;
                 node.field_id = *index;
                 self.values[rv].fields.insert(*index, node.index);
                 self.alias_set.push(self.values.len());
@@ -300,16 +486,34 @@ impl<'tcx> MopGraph<'tcx> {
         }
     }
 
+    // the numbers of the fields from the local to the node, ended by a wildcard at the depth limit.
     pub fn get_field_seq(&self, value: &ValueNode) -> Vec<usize> {
         let mut field_id_seq = vec![];
         let mut node_ref = value;
         while node_ref.field_id != usize::MAX {
             field_id_seq.push(node_ref.field_id);
-            node_ref = &self.values[value.father];
+            node_ref = &self.values[node_ref.father];
+        }
+        field_id_seq.reverse();
+        let depth = field_depth();
+        if field_id_seq.len() >= depth {
+            field_id_seq.truncate(depth);
+            field_id_seq.push(WILDCARD);
         }
         field_id_seq
     }
 
+    // the number of fields from the local to the node.
+    pub fn depth(&self, node: usize) -> usize {
+        let mut depth = 0;
+        let mut node_ref = &self.values[node];
+        while node_ref.field_id != usize::MAX {
+            depth += 1;
+            node_ref = &self.values[node_ref.father];
+        }
+        depth
+    }
+
     #[inline]
     pub fn union_find(&mut self, e: usize) -> usize {
         let mut r = e;
@@ -1,11 +1,13 @@
+use super::query::FnAliasFacts;
 use super::types::*;
+use crate::analysis::core::alias::closure::{aggregate_field, by_field, CoroutineFields};
 use crate::analysis::core::alias::FnRetAlias;
 use crate::rap_debug;
 use crate::utils::source::*;
 use rustc_data_structures::fx::{FxHashMap, FxHashSet};
 use rustc_middle::mir::{
-    BasicBlock, Const, Operand, Place, Rvalue, StatementKind, Terminator, TerminatorKind,
-    UnwindAction,
+    AggregateKind, BasicBlock, Const, Operand, Place, Rvalue, StatementKind, Terminator,
+    TerminatorKind, UnwindAction,
 };
 use rustc_middle::ty::TyCtxt;
 use rustc_span::def_id::DefId;
@@ -59,8 +61,8 @@ pub struct BlockNode<'tcx> {
     pub calls: Vec<Terminator<'tcx>>,
     //store the index of the basic blocks as a SCC node.
     pub scc_sub_blocks: Vec<usize>,
-    //store const values defined in this block, i.e., which id has what value;
-    pub const_value: Vec<(usize, usize)>,
+    //store const values defined in this block, i.e., which place has what value;
+    pub const_value: Vec<(Place<'tcx>, usize)>,
     //store switch stmts in current block for the path filtering in path-sensitive analysis.
     pub switch_stmts: Vec<Terminator<'tcx>>,
 }
@@ -73,7 +75,7 @@ impl<'tcx> BlockNode<'tcx> {
             assignments: Vec::<Assignment<'tcx>>::new(),
             calls: Vec::<Terminator<'tcx>>::new(),
             scc_sub_blocks: Vec::<usize>::new(),
-            const_value: Vec::<(usize, usize)>::new(),
+            const_value: Vec::<(Place<'tcx>, usize)>::new(),
             switch_stmts: Vec::<Terminator<'tcx>>::new(),
         }
     }
@@ -144,6 +146,12 @@ pub struct MopGraph<'tcx> {
     // a threhold to avoid path explosion.
     pub visit_times: usize,
     pub alias_set: Vec<usize>,
+    // the fields of the state machine if the function is the body of a coroutine.
+    pub coroutine: Option<CoroutineFields>,
+    // the pointers copied into temporaries only to be dereferenced, e.g., to the state machine of a coroutine.
+    pub deref_copies: FxHashMap<usize, Place<'tcx>>,
+    // the alias classes at the entry and at the exit of each block on the visited paths, if they are recorded.
+    pub facts: Option<FnAliasFacts<'tcx>>,
 }
 
 impl<'tcx> MopGraph<'tcx> {
@@ -174,6 +182,7 @@ impl<'tcx> MopGraph<'tcx> {
         let basicblocks = &body.basic_blocks;
         let mut blocks = Vec::<BlockNode<'tcx>>::new();
         let mut scc_indices = Vec::<usize>::new();
+        let mut deref_copies = FxHashMap::default();
 
         // handle each basicblock
         for i in 0..basicblocks.len() {
@@ -219,14 +228,14 @@ impl<'tcx> MopGraph<'tcx> {
                                                 const_value.try_eval_scalar_int(tcx, param_env)
                                             {
                                                 let val = scalar.to_uint(scalar.size());
-                                                cur_bb.const_value.push((lv_local, val as usize));
+                                                cur_bb.const_value.push((lv, val as usize));
                                             }
                                         }
                                         Const::Unevaluated(_const_value, _ty) => {}
                                         Const::Val(const_value, _ty) => {
                                             if let Some(scalar) = const_value.try_to_scalar_int() {
                                                 let val = scalar.to_uint(scalar.size());
-                                                cur_bb.const_value.push((lv_local, val as usize));
+                                                cur_bb.const_value.push((lv, val as usize));
                                             }
                                         }
                                     }
@@ -286,13 +295,29 @@ impl<'tcx> MopGraph<'tcx> {
                             }
                             Operand::Constant(_) => {}
                         },
-                        Rvalue::Aggregate(_, ref x) => {
-                            for each_x in x {
+                        Rvalue::Aggregate(ref kind, ref x) => {
+                            for (i, each_x) in x.iter().enumerate() {
                                 match each_x {
                                     Operand::Copy(ref p) | Operand::Move(ref p) => {
                                         let rv_local = p.local.as_usize();
                                         if values[lv_local].may_drop && values[rv_local].may_drop {
                                             let rv = *p;
+                                            // the operands of a struct are its fields, e.g., the upvars of a
+                                            // closure; as in the summaries of `Option`, a variant of an enum
+                                            // aliases its operands as a whole.
+                                            let lv = match **kind {
+                                                AggregateKind::Adt(adt, _, _, _, active)
+                                                    if !tcx.adt_def(adt).is_enum() =>
+                                                {
+                                                    let i =
+                                                        active.map_or(i, |field| field.as_usize());
+                                                    aggregate_field(tcx, body, lv, i, each_x)
+                                                }
+                                                _ if by_field(kind) => {
+                                                    aggregate_field(tcx, body, lv, i, each_x)
+                                                }
+                                                _ => lv,
+                                            };
                                             let assign =
                                                 Assignment::new(lv, rv, AssignType::Copy, span);
                                             cur_bb.assignments.push(assign);
@@ -302,6 +327,12 @@ impl<'tcx> MopGraph<'tcx> {
                                 }
                             }
                         }
+                        Rvalue::CopyForDeref(ref p) => {
+                            let ty = p.ty(body, tcx).ty;
+                            if ty.is_ref() || ty.is_unsafe_ptr() {
+                                deref_copies.insert(lv_local, *p);
+                            }
+                        }
                         Rvalue::Discriminant(ref p) => {
                             let rv = *p;
                             let assign = Assignment::new(lv, rv, AssignType::Variant, span);
@@ -416,10 +447,12 @@ impl<'tcx> MopGraph<'tcx> {
             blocks.push(cur_bb);
         }
 
+        let coroutine = CoroutineFields::new(tcx, body);
         MopGraph {
             def_id,
             tcx,
             span: body.span,
+            facts: None,
             blocks,
             values,
             arg_size,
@@ -428,6 +461,8 @@ impl<'tcx> MopGraph<'tcx> {
             constant: FxHashMap::default(),
             ret_alias: FnRetAlias::new(arg_size),
             visit_times: 0,
+            coroutine,
+            deref_copies,
         }
     }
 
@@ -51,10 +51,15 @@ impl<'tcx> MopGraph<'tcx> {
         recursion_set: &mut FxHashSet<DefId>,
     ) {
         self.visit_times += 1;
-        if self.visit_times > VISIT_LIMIT {
+        if self.visit_times > visit_limit() {
             return;
         }
         let cur_block = self.blocks[self.scc_indices[bb_index]].clone();
+        // the blocks of an SCC are analyzed at once, so they share the aliases at its entry and exit.
+        let scc_blocks = std::iter::once(cur_block.index).chain(cur_block.scc_sub_blocks.clone());
+        for block in scc_blocks.clone() {
+            self.record_facts(block, false);
+        }
         self.alias_bb(self.scc_indices[bb_index]);
         self.alias_bbcall(self.scc_indices[bb_index], fn_map, recursion_set);
 
@@ -65,6 +70,9 @@ impl<'tcx> MopGraph<'tcx> {
                 self.alias_bbcall(i, fn_map, recursion_set);
             }
         }
+        for block in scc_blocks {
+            self.record_facts(block, true);
+        }
 
         /* Reach a leaf node, check bugs */
         match cur_block.next.len() {
@@ -149,7 +157,7 @@ impl<'tcx> MopGraph<'tcx> {
             // Other cases in switchInt terminators
             if let Some(targets) = sw_targets {
                 for iter in targets.iter() {
-                    if self.visit_times > VISIT_LIMIT {
+                    if self.visit_times > visit_limit() {
                         continue;
                     }
                     let next_index = iter.1.as_usize();
@@ -174,7 +182,7 @@ impl<'tcx> MopGraph<'tcx> {
                 );
             } else {
                 for i in cur_block.next {
-                    if self.visit_times > VISIT_LIMIT {
+                    if self.visit_times > visit_limit() {
                         continue;
                     }
                     let next_index = i;
@@ -0,0 +1,312 @@
+use super::field_depth;
+use super::graph::MopGraph;
+use super::MopAlias;
+use crate::analysis::core::alias::closure::CoroutineFields;
+use crate::rap_info;
+use crate::utils::source::get_fn_name;
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir::{Local, Location, Place, ProjectionElem, TerminatorKind, RETURN_PLACE};
+use std::collections::hash_map::Entry;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/*
+ * The aliases found by MoP within a function, which can be queried by other analyses.
+ * The alias classes are recorded at the entry and at the exit of each basic block on every visited path, and the
+ * classes of all the paths are kept apart, so two places may alias at a location only if they are in the same class
+ * on some path. MoP never removes an alias within a block, so the classes at the exit of a block cover those at any
+ * of its statements. Nothing is known about the blocks that are not visited before `limits.mop-visit` is reached.
+ * The classes are only recorded for the functions queried through `MopAlias::fn_facts`.
+ */
+
+/// A place tracked by MoP: a local followed by the numbers of its fields, e.g., `1.0` for `(*_1).0`.
+///
+/// Dereferences are not part of the place, as a pointer is an alias of its pointee; the elements of a container
+/// are its field `ELEMENT`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AliasPlace {
+    pub local: usize,
+    pub field_seq: Vec<usize>,
+}
+
+impl AliasPlace {
+    pub fn new(local: usize, field_seq: Vec<usize>) -> Self {
+        Self { local, field_seq }
+    }
+}
+
+impl fmt::Display for AliasPlace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.local)?;
+        for field in &self.field_seq {
+            write!(f, ".{}", field)?;
+        }
+        Ok(())
+    }
+}
+
+/// The places that are aliases of each other.
+pub type AliasClass = BTreeSet<AliasPlace>;
+
+/// The alias classes of a function at the entry and at the exit of its basic blocks.
+#[derive(Debug, Clone)]
+pub struct FnAliasFacts<'tcx> {
+    entry: Vec<Option<FxHashSet<AliasClass>>>,
+    exit: Vec<Option<FxHashSet<AliasClass>>>,
+    deref_copies: FxHashMap<usize, Place<'tcx>>,
+    coroutine: Option<CoroutineFields>,
+}
+
+impl<'tcx> FnAliasFacts<'tcx> {
+    pub fn new(
+        blocks: usize,
+        deref_copies: FxHashMap<usize, Place<'tcx>>,
+        coroutine: Option<CoroutineFields>,
+    ) -> Self {
+        Self {
+            entry: vec![None; blocks],
+            exit: vec![None; blocks],
+            deref_copies,
+            coroutine,
+        }
+    }
+
+    /// The classes of the visited paths at the location: at the entry of the block for its first statement, and
+    /// at the exit of the block otherwise; `None` if the block is not visited.
+    pub fn classes(&self, location: Location) -> Option<&FxHashSet<AliasClass>> {
+        let facts = match location.statement_index {
+            0 => &self.entry,
+            _ => &self.exit,
+        };
+        facts.get(location.block.as_usize())?.as_ref()
+    }
+
+    /// Whether the two places may be aliases at the location; `None` if the block is not visited.
+    pub fn may_alias(&self, a: &AliasPlace, b: &AliasPlace, location: Location) -> Option<bool> {
+        if a == b {
+            return Some(true);
+        }
+        let classes = self.classes(location)?;
+        Some(
+            classes
+                .iter()
+                .any(|class| class.contains(a) && class.contains(b)),
+        )
+    }
+
+    /// The places that the local may be an alias of at the location, excluding itself; `None` if the block is
+    /// not visited.
+    pub fn points_to_set(&self, local: Local, location: Location) -> Option<BTreeSet<AliasPlace>> {
+        let place = AliasPlace::new(local.as_usize(), Vec::new());
+        let classes = self.classes(location)?;
+        Some(
+            classes
+                .iter()
+                .filter(|class| class.contains(&place))
+                .flatten()
+                .filter(|alias| **alias != place)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    pub fn record(&mut self, block: usize, exit: bool, classes: Vec<AliasClass>) {
+        let facts = match exit {
+            true => &mut self.exit,
+            false => &mut self.entry,
+        };
+        facts[block]
+            .get_or_insert_with(FxHashSet::default)
+            .extend(classes);
+    }
+}
+
+impl MopGraph<'_> {
+    // start recording the alias classes of the visited blocks, which is costly and only done for queries.
+    pub fn record_aliases(&mut self) {
+        self.facts = Some(FnAliasFacts::new(
+            self.blocks.len(),
+            self.deref_copies.clone(),
+            self.coroutine.clone(),
+        ));
+    }
+
+    // record the alias classes at the entry or at the exit of the block.
+    pub fn record_facts(&mut self, block: usize, exit: bool) {
+        if self.facts.is_none() {
+            return;
+        }
+        let classes = self.alias_classes();
+        if let Some(facts) = &mut self.facts {
+            facts.record(block, exit, classes);
+        }
+    }
+
+    // the places of the nodes reachable from the locals, grouped by their alias sets.
+    fn alias_classes(&mut self) -> Vec<AliasClass> {
+        let mut classes = FxHashMap::<usize, AliasClass>::default();
+        // the nodes of the locals come first in the values.
+        let locals = self.tcx.optimized_mir(self.def_id).local_decls.len();
+        for local in 0..locals {
+            let mut stack = vec![local];
+            let mut field_seq = Vec::new();
+            self.collect_places(local, local, &mut field_seq, &mut stack, &mut classes);
+        }
+        classes
+            .into_values()
+            .filter(|class| class.len() > 1)
+            .collect()
+    }
+
+    fn collect_places(
+        &mut self,
+        local: usize,
+        node: usize,
+        field_seq: &mut Vec<usize>,
+        stack: &mut Vec<usize>,
+        classes: &mut FxHashMap<usize, AliasClass>,
+    ) {
+        let root = self.union_find(node);
+        classes
+            .entry(root)
+            .or_default()
+            .insert(AliasPlace::new(local, field_seq.clone()));
+        let mut fields = self.values[node]
+            .fields
+            .iter()
+            .map(|(field, node)| (*field, *node))
+            .collect::<Vec<_>>();
+        fields.sort_unstable();
+        for (field, field_node) in fields {
+            // the elements of a container may be the container itself.
+            if stack.contains(&field_node) {
+                continue;
+            }
+            stack.push(field_node);
+            field_seq.push(field);
+            self.collect_places(local, field_node, field_seq, stack, classes);
+            field_seq.pop();
+            stack.pop();
+        }
+    }
+}
+
+impl<'tcx> MopAlias<'tcx> {
+    /// The aliases found within the function, which are computed on demand if the function has not been analyzed.
+    pub fn fn_facts(&mut self, def_id: DefId) -> Option<&FnAliasFacts<'tcx>> {
+        if let Entry::Vacant(e) = self.facts.entry(def_id) {
+            if !self.tcx.is_mir_available(def_id) || !self.tcx.def_kind(def_id).is_fn_like() {
+                return None;
+            }
+            let mut mop_graph = MopGraph::new(self.tcx, def_id);
+            mop_graph.record_aliases();
+            mop_graph.solve_scc();
+            let mut recursion_set = FxHashSet::default();
+            mop_graph.check(0, &mut self.fn_map, &mut recursion_set);
+            e.insert(mop_graph.facts?);
+        }
+        self.facts.get(&def_id)
+    }
+
+    /// The place of MoP that the MIR place of the function is projected to.
+    pub fn alias_place(&mut self, def_id: DefId, place: Place<'tcx>) -> AliasPlace {
+        let tcx = self.tcx;
+        let Some(facts) = self.fn_facts(def_id) else {
+            return AliasPlace::new(place.local.as_usize(), Vec::new());
+        };
+        let body = tcx.optimized_mir(def_id);
+        let mut place = place;
+        while let Some(base) = facts.deref_copies.get(&place.local.as_usize()) {
+            place = base.project_deeper(place.projection, tcx);
+        }
+        let mut field_seq = Vec::new();
+        let mut variant = None;
+        for (base, proj) in place.iter_projections() {
+            match proj {
+                ProjectionElem::Field(field, _) => {
+                    let field = match (variant.take(), &facts.coroutine) {
+                        (Some(variant), Some(coroutine)) => {
+                            coroutine.field(variant, field.as_usize())
+                        }
+                        _ => field.as_usize(),
+                    };
+                    field_seq.push(field);
+                }
+                ProjectionElem::Downcast(_, index) => {
+                    if let Some(coroutine) = &facts.coroutine {
+                        variant = coroutine.downcast(tcx, body, base, index);
+                    }
+                }
+                _ => {}
+            }
+        }
+        // the fields beyond the depth limit are merged into their ancestor at the limit.
+        field_seq.truncate(field_depth());
+        AliasPlace::new(place.local.as_usize(), field_seq)
+    }
+
+    /// Whether the two places of the function may be aliases at the location; `None` if it is unknown, i.e., the
+    /// function has no MIR or the location is not reached within `limits.mop-visit`.
+    pub fn may_alias(
+        &mut self,
+        def_id: DefId,
+        a: Place<'tcx>,
+        b: Place<'tcx>,
+        location: Location,
+    ) -> Option<bool> {
+        let a = self.alias_place(def_id, a);
+        let b = self.alias_place(def_id, b);
+        if a == b {
+            return Some(true);
+        }
+        self.fn_facts(def_id)?.may_alias(&a, &b, location)
+    }
+
+    /// The places that the local of the function may be an alias of at the location, excluding itself; `None` if
+    /// it is unknown, as for `may_alias`.
+    pub fn points_to_set(
+        &mut self,
+        def_id: DefId,
+        local: Local,
+        location: Location,
+    ) -> Option<BTreeSet<AliasPlace>> {
+        self.fn_facts(def_id)?.points_to_set(local, location)
+    }
+
+    /// Display the places that the return value of each local function may be an alias of when it returns.
+    pub fn show_return_aliases(&mut self) {
+        let tcx = self.tcx;
+        for local_def_id in tcx.hir().body_owners() {
+            let def_id = local_def_id.to_def_id();
+            if !tcx.def_kind(def_id).is_fn_like() {
+                continue;
+            }
+            let body = tcx.optimized_mir(def_id);
+            for (bb, data) in body.basic_blocks.iter_enumerated() {
+                if !matches!(data.terminator().kind, TerminatorKind::Return) {
+                    continue;
+                }
+                let fn_name = get_fn_name(tcx, def_id).unwrap_or_default();
+                let location = body.terminator_loc(bb);
+                match self.points_to_set(def_id, RETURN_PLACE, location) {
+                    Some(places) if !places.is_empty() => {
+                        let places: Vec<String> = places.iter().map(|p| p.to_string()).collect();
+                        rap_info!(
+                            "Return value of {} at {:?} may alias: {}",
+                            fn_name,
+                            bb,
+                            places.join(", ")
+                        );
+                    }
+                    Some(_) => {}
+                    None => rap_info!(
+                        "Return value of {} at {:?} is not reached by MoP.",
+                        fn_name,
+                        bb
+                    ),
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+pub mod export;
 pub mod ownership;
 pub mod type_visitor;
 
@@ -6,8 +7,10 @@ use rustc_middle::ty::{self, Ty, TyCtxt, TyKind};
 use rustc_span::def_id::DefId;
 use rustc_target::abi::VariantIdx;
 
+use crate::utils::cache;
+use crate::utils::config::config;
+use crate::utils::summary;
 use std::collections::{HashMap, HashSet};
-use std::env;
 //use stopwatch::Stopwatch;
 use crate::analysis::core::heap_item::ownership::OwnershipLayoutResult;
 use crate::analysis::rcanary::{rCanary, RcxMut};
@@ -82,7 +85,17 @@ impl<'tcx, 'a> TypeAnalysis<'tcx, 'a> {
         // Get the analysis result from rap phase llvm
         // self.connect();
         // Get related adt types through visiting mir local
+        let tcx = self.tcx();
+        if let Some(adt_owner) = cache::adt_owner(tcx) {
+            *self.adt_owner_mut() = adt_owner;
+            return;
+        }
         self.visitor();
+        // ADTs of dependencies not reached by the local MIR are summarized when cargo-rapx compiles them.
+        for (did, units) in summary::adt_owner(tcx) {
+            self.adt_owner_mut().entry(did).or_insert(units);
+        }
+        cache::store_adt_owner(tcx, self.adt_owner());
 
         //rap_info!("AdtDef Sum:{:?}", self.adt_owner().len());
         //rap_info!("Tymap Sum:{:?}", self.ty_map().len());
@@ -882,10 +895,7 @@ impl<'tcx> FindPtr<'tcx> {
 }
 
 pub fn is_display_verbose() -> bool {
-    match env::var_os("ADT_DISPLAY") {
-        Some(_) => true,
-        _ => false,
-    }
+    config().display.adt
 }
 pub fn mir_body(tcx: TyCtxt<'_>, def_id: DefId) -> &rustc_middle::mir::Body<'_> {
     //let def = ty::InstanceDef::Item(def_id);
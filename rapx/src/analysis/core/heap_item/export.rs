@@ -0,0 +1,196 @@
+use rustc_middle::ty::{EarlyBinder, Ty, TyCtxt, TyKind, TypeVisitable};
+use rustc_span::def_id::{DefId, LOCAL_CRATE};
+use serde::Serialize;
+
+use super::*;
+use crate::analysis::rcanary::rCanary;
+use crate::rap_info;
+use crate::utils::report;
+
+/// The extension of the files of the heap-item table, i.e., `rapx.heap_items.json`.
+pub const EXTENSION: &str = "heap_items";
+
+/// The ownership layout of an ADT, i.e., one entry of `AdtOwner`, explained for auditing.
+#[derive(Debug, Clone, Serialize)]
+pub struct AdtEntry {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub path: String,
+    /// `struct`, `enum` or `union`.
+    pub kind: &'static str,
+    /// Whether any variant owns heap memory by itself.
+    pub owning: bool,
+    /// One entry for a struct or a union, and one for each variant of an enum.
+    pub variants: Vec<VariantEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VariantEntry {
+    /// The name of the variant, which is empty for a struct or a union.
+    pub name: String,
+    pub owning: bool,
+    /// The generic parameters whose values are held directly, i.e., not behind a pointer, so that the type owns
+    /// heap memory if their arguments do.
+    pub raw_generics: Vec<String>,
+    pub reason: String,
+}
+
+/// The `-heap-item` mode: the ownership layouts of the ADTs used by the crate, printed per type and written into
+/// `rapx.heap_items.json`.
+pub struct HeapItemTable<'tcx> {
+    tcx: TyCtxt<'tcx>,
+}
+
+impl<'tcx> HeapItemTable<'tcx> {
+    pub fn new(tcx: TyCtxt<'tcx>) -> Self {
+        Self { tcx }
+    }
+
+    pub fn start(&mut self) {
+        let mut rcx = rCanary::new(self.tcx);
+        TypeAnalysis::new(&mut rcx).start();
+        let mut entries: Vec<AdtEntry> = rcx
+            .adt_owner()
+            .iter()
+            .map(|(did, units)| self.entry(&rcx, *did, units))
+            .collect();
+        entries.sort_by(|a, b| (&a.krate, &a.path).cmp(&(&b.krate, &b.path)));
+
+        let local = self.tcx.crate_name(LOCAL_CRATE).to_string();
+        for entry in entries.iter().filter(|entry| entry.krate == local) {
+            for line in entry.render() {
+                rap_info!("{}", line);
+            }
+        }
+        rap_info!(
+            "{} ADT(s) of {} and {} of its dependencies.",
+            entries.iter().filter(|entry| entry.krate == local).count(),
+            local,
+            entries.iter().filter(|entry| entry.krate != local).count()
+        );
+        let content = serde_json::to_string_pretty(&entries).unwrap();
+        let path = report::write_structured(self.tcx, EXTENSION, content);
+        rap_info!("Ownership of the ADTs written into {}.", path.display());
+    }
+
+    fn entry(&self, rcx: &rCanary<'tcx>, did: DefId, units: &[OwnerUnit]) -> AdtEntry {
+        let tcx = self.tcx;
+        let ty = EarlyBinder::skip_binder(tcx.type_of(did));
+        let TyKind::Adt(adt_def, substs) = ty.kind() else {
+            unreachable!()
+        };
+        let generics = tcx.generics_of(did);
+        let variants: Vec<VariantEntry> = adt_def
+            .variants()
+            .iter()
+            .zip(units)
+            .map(|(variant, (owner, raw))| {
+                let fields: Vec<(String, Ty<'tcx>)> = variant
+                    .fields
+                    .iter()
+                    .map(|field| (field.name.to_string(), field.ty(tcx, substs)))
+                    .collect();
+                let raw_generics: Vec<String> = raw
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, raw)| **raw)
+                    .map(|(index, _)| generics.param_at(index, tcx).name.to_string())
+                    .collect();
+                let reason = match owner.is_owned() {
+                    true => self.owning_reason(rcx, adt_def.is_struct(), &fields),
+                    false => unowned_reason(&raw_generics),
+                };
+                VariantEntry {
+                    name: match adt_def.is_enum() {
+                        true => variant.name.to_string(),
+                        false => String::new(),
+                    },
+                    owning: owner.is_owned(),
+                    raw_generics,
+                    reason,
+                }
+            })
+            .collect();
+        AdtEntry {
+            krate: tcx.crate_name(did.krate).to_string(),
+            path: tcx.def_path_str(did),
+            kind: match () {
+                _ if adt_def.is_enum() => "enum",
+                _ if adt_def.is_union() => "union",
+                _ => "struct",
+            },
+            owning: variants.iter().any(|variant| variant.owning),
+            variants,
+        }
+    }
+
+    // why the struct or the variant owns heap memory, following the steps of `TypeAnalysis`.
+    fn owning_reason(
+        &self,
+        rcx: &rCanary<'tcx>,
+        is_struct: bool,
+        fields: &[(String, Ty<'tcx>)],
+    ) -> String {
+        let tcx = self.tcx;
+        if is_struct {
+            let marker = fields.iter().find(|(_, ty)| match ty.kind() {
+                TyKind::Adt(adt_def, substs) if adt_def.is_phantom_data() => {
+                    let mut params = IsolatedParamFieldSubst::new();
+                    substs.types().for_each(|ty| {
+                        ty.visit_with(&mut params);
+                    });
+                    params.contains_param()
+                }
+                _ => false,
+            });
+            let pointer = fields.iter().find(|(_, ty)| {
+                let mut find_ptr = FindPtr::new(tcx);
+                ty.visit_with(&mut find_ptr);
+                find_ptr.has_ptr()
+            });
+            if let (Some((marker, marker_ty)), Some((pointer, _))) = (marker, pointer) {
+                return format!(
+                    "field `{marker}: {marker_ty}` marks the values behind the pointer in field `{pointer}` as owned."
+                );
+            }
+        }
+        for (name, ty) in fields {
+            let mut owner_prop = OwnerPropagation::new(tcx, RawTypeOwner::Unowned, rcx.adt_owner());
+            ty.visit_with(&mut owner_prop);
+            if owner_prop.ownership().is_owned() {
+                return format!("field `{name}: {ty}` owns heap memory.");
+            }
+        }
+        "owns heap memory according to the summary of its crate.".to_string()
+    }
+}
+
+fn unowned_reason(raw_generics: &[String]) -> String {
+    if raw_generics.is_empty() {
+        return "no field owns heap memory.".to_string();
+    }
+    let generics: Vec<String> = raw_generics.iter().map(|g| format!("`{g}`")).collect();
+    format!(
+        "no field owns heap memory, but the values of {} are held directly, so it owns heap memory if they do.",
+        generics.join(", ")
+    )
+}
+
+impl AdtEntry {
+    /// The readable lines of the entry, e.g., `struct alloc::vec::Vec<T, A>: owning`.
+    pub fn render(&self) -> Vec<String> {
+        let owning = match self.owning {
+            true => "owning",
+            false => "not owning",
+        };
+        let mut lines = vec![format!("{} {}: {}", self.kind, self.path, owning)];
+        for variant in &self.variants {
+            let name = match variant.name.is_empty() {
+                true => String::new(),
+                false => format!("variant {}: ", variant.name),
+            };
+            lines.push(format!("    {}{}", name, variant.reason));
+        }
+        lines
+    }
+}
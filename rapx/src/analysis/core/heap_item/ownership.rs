@@ -1,10 +1,11 @@
 use rustc_middle::ty::Ty;
+use serde::{Deserialize, Serialize};
 
 use std::fmt::Debug;
 
 use super::{DefaultOwnership, OwnershipLayout};
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum RawTypeOwner {
     Owned,
     Unowned,
@@ -4,6 +4,7 @@ pub mod memory_cloning;
 use rustc_middle::ty::TyCtxt;
 
 use super::core::dataflow::{graph::Graph, DataFlow};
+use crate::utils::report;
 use checking::bounds_checking::BoundsCheck;
 use memory_cloning::used_as_immutable::UsedAsImmutableCheck;
 
@@ -25,6 +26,9 @@ impl<'tcx> Opt<'tcx> {
     pub fn start(&mut self) {
         let mut dataflow = DataFlow::new(self.tcx, false);
         dataflow.build_graphs();
+        dataflow
+            .graphs
+            .retain(|def_id, _| report::in_scope(self.tcx, *def_id));
         let bounds_checks: Vec<BoundsCheck> = dataflow
             .graphs
             .iter()
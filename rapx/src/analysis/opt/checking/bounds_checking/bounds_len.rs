@@ -7,10 +7,7 @@ use crate::analysis::core::dataflow::graph::{
     AggKind, DFSStatus, Direction, Graph, GraphNode, NodeOp,
 };
 use crate::analysis::utils::def_path::DefPath;
-use crate::utils::log::{
-    relative_pos_range, span_to_filename, span_to_line_number, span_to_source_code,
-};
-use annotate_snippets::{Level, Renderer, Snippet};
+use crate::utils::report::{self, Finding, FindingKind};
 
 static DEFPATHS: OnceCell<DefPaths> = OnceCell::new();
 
@@ -145,28 +142,12 @@ fn find_downside_index_node(graph: &Graph, node_idx: Local) -> Vec<Local> {
 
 fn report_upperbound_bug(graph: &Graph, upperbound_node_idx: Local, index_record: &Vec<Local>) {
     let upperbound_span = graph.nodes[upperbound_node_idx].span;
-    let code_source = span_to_source_code(graph.span);
-    let filename = span_to_filename(upperbound_span);
-    let mut snippet = Snippet::source(&code_source)
-        .line_start(span_to_line_number(graph.span))
-        .origin(&filename)
-        .fold(true)
-        .annotation(
-            Level::Info
-                .span(unsafe { relative_pos_range(graph.span, upperbound_span) })
-                .label("Index is upperbounded."),
-        );
+    let mut finding = Finding::new(FindingKind::BoundsChecking, graph.def_id, graph.span)
+        .message("Unnecessary bounds checkings detected")
+        .related(upperbound_span, "Index is upperbounded.");
     for node_idx in index_record {
         let index_span = graph.nodes[*node_idx].span;
-        snippet = snippet.annotation(
-            Level::Error
-                .span(unsafe { relative_pos_range(graph.span, index_span) })
-                .label("Checked here."),
-        );
+        finding = finding.primary(index_span, "Checked here.");
     }
-    let message = Level::Warning
-        .title("Unnecessary bounds checkings detected")
-        .snippet(snippet);
-    let renderer = Renderer::styled();
-    println!("{}", renderer.render(message));
+    report::emit(finding);
 }
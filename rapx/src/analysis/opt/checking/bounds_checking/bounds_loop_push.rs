@@ -1,5 +1,6 @@
 use once_cell::sync::OnceCell;
 
+use rustc_hir::def_id::DefId;
 use rustc_hir::{intravisit, Expr, ExprKind};
 use rustc_middle::ty::TyCtxt;
 use rustc_middle::ty::TypeckResults;
@@ -7,11 +8,8 @@ use rustc_span::Span;
 
 use crate::analysis::core::dataflow::graph::Graph;
 use crate::analysis::utils::def_path::DefPath;
-use crate::utils::log::{
-    relative_pos_range, span_to_filename, span_to_first_line, span_to_line_number,
-    span_to_source_code, span_to_trimmed_span,
-};
-use annotate_snippets::{Level, Renderer, Snippet};
+use crate::utils::log::{span_to_first_line, span_to_trimmed_span};
+use crate::utils::report::{self, Finding, FindingKind};
 
 static DEFPATHS: OnceCell<DefPaths> = OnceCell::new();
 
@@ -93,40 +91,22 @@ impl OptCheck for BoundsLoopPushCheck {
         self.record = loop_finder.record;
     }
 
-    fn report(&self, _: &Graph) {
+    fn report(&self, graph: &Graph) {
         for (loop_span, push_record) in self.record.iter() {
-            report_loop_push_bug(*loop_span, push_record);
+            report_loop_push_bug(graph.def_id, *loop_span, push_record);
         }
     }
 }
 
-fn report_loop_push_bug(loop_span: Span, push_record: &Vec<Span>) {
-    let code_source = span_to_source_code(loop_span);
-    let filename = span_to_filename(loop_span);
-    let mut snippet = Snippet::source(&code_source)
-        .line_start(span_to_line_number(loop_span))
-        .origin(&filename)
-        .fold(true)
-        .annotation(
-            Level::Info
-                .span(unsafe {
-                    relative_pos_range(
-                        loop_span,
-                        span_to_trimmed_span(span_to_first_line(loop_span)),
-                    )
-                })
-                .label("A loop operation."),
+fn report_loop_push_bug(def_id: DefId, loop_span: Span, push_record: &Vec<Span>) {
+    let mut finding = Finding::new(FindingKind::BoundsChecking, def_id, loop_span)
+        .message("Unnecessary bounds checkings detected")
+        .related(
+            span_to_trimmed_span(span_to_first_line(loop_span)),
+            "A loop operation.",
         );
     for push_span in push_record {
-        snippet = snippet.annotation(
-            Level::Error
-                .span(unsafe { relative_pos_range(loop_span, *push_span) })
-                .label("Push happens here."),
-        );
+        finding = finding.primary(*push_span, "Push happens here.");
     }
-    let message = Level::Warning
-        .title("Unnecessary bounds checkings detected")
-        .snippet(snippet);
-    let renderer = Renderer::styled();
-    println!("{}", renderer.render(message));
+    report::emit(finding);
 }
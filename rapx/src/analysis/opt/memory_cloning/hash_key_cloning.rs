@@ -1,6 +1,3 @@
-use annotate_snippets::Level;
-use annotate_snippets::Renderer;
-use annotate_snippets::Snippet;
 use once_cell::sync::OnceCell;
 
 use crate::analysis::core::dataflow::graph::DFSStatus;
@@ -16,9 +13,7 @@ use crate::analysis::core::dataflow::graph::Graph;
 use crate::analysis::core::dataflow::graph::GraphNode;
 use crate::analysis::core::dataflow::graph::NodeOp;
 use crate::analysis::utils::def_path::DefPath;
-use crate::utils::log::{
-    relative_pos_range, span_to_filename, span_to_line_number, span_to_source_code,
-};
+use crate::utils::report::{self, Finding, FindingKind};
 
 struct DefPaths {
     hashset_insert: DefPath,
@@ -121,28 +116,12 @@ fn find_hashset_new_node(graph: &Graph, node: &GraphNode) -> Option<Local> {
 }
 
 fn report_hash_key_cloning(graph: &Graph, clone_span: Span, insert_span: Span) {
-    let code_source = span_to_source_code(graph.span);
-    let filename = span_to_filename(clone_span);
-    let snippet = Snippet::source(&code_source)
-        .line_start(span_to_line_number(graph.span))
-        .origin(&filename)
-        .fold(true)
-        .annotation(
-            Level::Error
-                .span(unsafe { relative_pos_range(graph.span, clone_span) })
-                .label("Cloning happens here."),
-        )
-        .annotation(
-            Level::Error
-                .span(unsafe { relative_pos_range(graph.span, insert_span) })
-                .label("Used here."),
-        );
-    let message = Level::Warning
-        .title("Unnecessary memory cloning detected")
-        .snippet(snippet)
-        .footer(Level::Help.title("Use borrowings as keys."));
-    let renderer = Renderer::styled();
-    println!("{}", renderer.render(message));
+    let finding = Finding::new(FindingKind::MemoryCloning, graph.def_id, graph.span)
+        .message("Unnecessary memory cloning detected")
+        .primary(clone_span, "Cloning happens here.")
+        .related(insert_span, "Used here.")
+        .help("Use borrowings as keys.");
+    report::emit(finding);
 }
 
 pub fn check(graph: &Graph, tcx: &TyCtxt) {
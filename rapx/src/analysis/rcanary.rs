@@ -6,7 +6,9 @@ use crate::Elapsed;
 use std::collections::HashMap;
 
 use crate::analysis::core::heap_item::{AdtOwner, TypeAnalysis};
+use ranalyzer::ownership::FnOwnership;
 use ranalyzer::{FlowAnalysis, IcxSliceFroBlock, IntraFlowContext, MirGraph};
+use rustc_span::def_id::DefId;
 
 #[allow(non_camel_case_types)]
 #[derive(Clone)]
@@ -14,6 +16,7 @@ pub struct rCanary<'tcx> {
     tcx: TyCtxt<'tcx>,
     adt_owner: AdtOwner,
     mir_graph: MirGraph,
+    fn_ownership: HashMap<DefId, FnOwnership>,
     elapsed: Elapsed,
 }
 
@@ -23,6 +26,7 @@ impl<'tcx> rCanary<'tcx> {
             tcx,
             adt_owner: HashMap::default(),
             mir_graph: HashMap::default(),
+            fn_ownership: HashMap::default(),
             elapsed: (0, 0),
         }
     }
@@ -54,6 +58,14 @@ impl<'tcx> rCanary<'tcx> {
         &mut self.mir_graph
     }
 
+    pub fn fn_ownership(&self) -> &HashMap<DefId, FnOwnership> {
+        &self.fn_ownership
+    }
+
+    pub fn fn_ownership_mut(&mut self) -> &mut HashMap<DefId, FnOwnership> {
+        &mut self.fn_ownership
+    }
+
     pub fn get_time_build(&self) -> i64 {
         self.elapsed.0
     }
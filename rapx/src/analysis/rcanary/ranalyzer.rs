@@ -3,7 +3,7 @@ pub mod intra_visitor;
 pub mod order;
 pub mod ownership;
 
-use rustc_middle::mir::{Body, Terminator};
+use rustc_middle::mir::{Body, Location, Terminator};
 use rustc_middle::ty::TyCtxt;
 use rustc_span::def_id::DefId;
 
@@ -12,21 +12,29 @@ use crate::analysis::core::heap_item::{
     mir_body, type_visitor::TyWithIndex, AdtOwner, OwnershipLayout, Unique,
 };
 use crate::Elapsed;
-use ownership::{IntraVar, Taint};
+use inter_visitor::arg_copies;
+use ownership::{FnOwnership, IntraVar, Taint};
 
+use crate::utils::config::config;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
-use std::env;
 use std::fmt::{Debug, Formatter};
 
 pub type MirGraph = HashMap<DefId, Graph>;
 pub type ToPo = Vec<usize>;
 pub type Edges = Vec<Vec<usize>>;
 
+/// The acyclic graph of the basic blocks visited by rCanary.
+///
+/// The loops are unrolled: the nodes after the basic blocks are the copies of the blocks in loops for later
+/// iterations, and `block` maps every node to its basic block.
 #[derive(Debug, Clone)]
 pub struct Graph {
     e: Edges,
     pre: Edges,
     topo: ToPo,
+    block: Vec<usize>,
+    loops: Vec<usize>,
 }
 
 impl Default for Graph {
@@ -35,6 +43,8 @@ impl Default for Graph {
             e: Vec::default(),
             pre: Vec::default(),
             topo: Vec::default(),
+            block: Vec::default(),
+            loops: Vec::default(),
         }
     }
 }
@@ -45,9 +55,15 @@ impl Graph {
             e: vec![Vec::new(); len],
             pre: vec![Vec::new(); len],
             topo: Vec::new(),
+            block: (0..len).collect(),
+            loops: Vec::new(),
         }
     }
 
+    pub fn node_count(&self) -> usize {
+        self.block.len()
+    }
+
     pub fn get_edges(&self) -> &Edges {
         &self.e
     }
@@ -71,6 +87,16 @@ impl Graph {
     pub fn get_topo_mut(&mut self) -> &mut ToPo {
         &mut self.topo
     }
+
+    /// The basic block of the node.
+    pub fn get_block(&self, node: usize) -> usize {
+        self.block[node]
+    }
+
+    /// The heads of the loops that are unrolled, whose later iterations are approximated.
+    pub fn get_loops(&self) -> &Vec<usize> {
+        &self.loops
+    }
 }
 
 pub struct FlowAnalysis<'tcx, 'a> {
@@ -106,8 +132,10 @@ impl<'tcx, 'a> FlowAnalysis<'tcx, 'a> {
         // this phase determines the final order of all basic blocks for us to visit
         // Note: we will not visit the clean-up blocks (unwinding)
         self.order();
+        // this phase computes the ownership summaries of the functions bottom-up over the call graph
+        self.inter_run();
         // this phase will generate the Intra procedural visitor for us to visit the block
-        // note that the inter procedural part is inside in this function but cod in module inter_visitor
+        // note that the summaries of the callees are applied at the call sites
         self.intra_run();
 
         // rap_info!("@@@@@@@@@@@@@Build Analysis:{:?}", self.rcx().get_time_build());
@@ -163,6 +191,122 @@ impl<'tcx> NodeOrder<'tcx> {
     }
 }
 
+/// The outcome of checking a function by the z3 solver, from the most to the least precise.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SolverOutcome {
+    /// The ownership constraints are satisfiable, so no heap item is leaked.
+    Sat,
+    /// The ownership constraints conflict and a heap item escapes to a raw pointer, so a leak is reported.
+    Leak,
+    /// The ownership constraints conflict without a heap item escaping to a raw pointer, so no leak is reported
+    /// but the ownership is not modeled consistently.
+    Unsat,
+    /// Some variables holding ownership have types or places that rCanary does not model, so the function is
+    /// only checked partially.
+    Unsupported,
+    /// The solver gives up without an answer.
+    Unknown,
+    /// The solver reaches the time limit of `limits.rcanary-timeout` or `limits.rcanary-timeouts`.
+    Timeout,
+    /// The solver is not run, e.g., the function never returns.
+    Skipped,
+}
+
+impl SolverOutcome {
+    pub const ALL: [SolverOutcome; 7] = [
+        SolverOutcome::Sat,
+        SolverOutcome::Leak,
+        SolverOutcome::Unsat,
+        SolverOutcome::Unsupported,
+        SolverOutcome::Unknown,
+        SolverOutcome::Timeout,
+        SolverOutcome::Skipped,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SolverOutcome::Sat => "sat",
+            SolverOutcome::Leak => "leak",
+            SolverOutcome::Unsat => "unsat",
+            SolverOutcome::Unsupported => "unsupported",
+            SolverOutcome::Unknown => "unknown",
+            SolverOutcome::Timeout => "timeout",
+            SolverOutcome::Skipped => "skipped",
+        }
+    }
+
+    /// Whether the function is fully verified, i.e., the solver answers on the whole function and either finds
+    /// no leak or reports one.
+    pub fn is_verified(&self) -> bool {
+        matches!(self, SolverOutcome::Sat | SolverOutcome::Leak)
+    }
+
+    /// The outcome of a function whose returns are checked separately: a leak found at any of them is kept,
+    /// and otherwise the least precise outcome is taken.
+    pub fn join(self, other: SolverOutcome) -> SolverOutcome {
+        match (self, other) {
+            (SolverOutcome::Skipped, outcome) | (outcome, SolverOutcome::Skipped) => outcome,
+            (SolverOutcome::Leak, _) | (_, SolverOutcome::Leak) => SolverOutcome::Leak,
+            _ => self.max(other),
+        }
+    }
+}
+
+/// The kinds of the constraints asserted by rCanary.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ConstraintKind {
+    /// The initial ownership of an argument.
+    Arg,
+    /// The ownership of a variable where the branches join.
+    Phi,
+    /// The transfer of ownership by an assignment.
+    Assign,
+    /// A variable that is assigned again must not hold ownership.
+    Overwrite,
+    /// The ownership of an argument or the return value of a call.
+    Call,
+    /// The ownership of a pointer to a heap item that is leaked, e.g., by `Box::into_raw`.
+    Source,
+    /// A pointer passed to a call must not hold ownership.
+    PassPtr,
+    /// The ownership released by a drop.
+    Drop,
+    /// A variable must not hold ownership when the function returns.
+    Return,
+    /// An owning argument of a call must not hold ownership if the callee leaks it.
+    Leaked,
+}
+
+impl ConstraintKind {
+    /// Whether the constraint requires a variable to hold no ownership, where the ownership of a leaked heap item is lost.
+    pub fn is_loss(&self) -> bool {
+        matches!(
+            self,
+            ConstraintKind::Overwrite | ConstraintKind::PassPtr | ConstraintKind::Return
+        )
+    }
+}
+
+/// A constraint asserted by rCanary, which is tracked so that the unsat core can be mapped back to the MIR.
+#[derive(Debug, Copy, Clone)]
+pub struct TrackedConstraint {
+    pub kind: ConstraintKind,
+    /// The local whose ownership is constrained.
+    pub local: usize,
+    pub location: Location,
+}
+
+impl TrackedConstraint {
+    pub fn new(kind: ConstraintKind, local: usize, location: Location) -> Self {
+        Self {
+            kind,
+            local,
+            location,
+        }
+    }
+}
+
 struct IntraFlowAnalysis<'tcx, 'ctx, 'a> {
     rcx: &'a rCanary<'tcx>,
     icx: IntraFlowContext<'tcx, 'ctx>,
@@ -173,6 +317,20 @@ struct IntraFlowAnalysis<'tcx, 'ctx, 'a> {
     elasped: Elapsed,
     taint_flag: bool,
     taint_source: Vec<Terminator<'tcx>>,
+    // the ownership summary of the function, which is only computed without checking for leaks if summary_only is set.
+    ownership: FnOwnership,
+    summary_only: bool,
+    // the locals holding the pointer arguments, mapped to the indexes of the arguments.
+    arg_copies: HashMap<usize, usize>,
+    // the locals holding the other arguments, and the owning arguments turned into the heap items of each source.
+    owning_arg_copies: HashMap<usize, usize>,
+    source_args: HashMap<Location, Vec<usize>>,
+    // the location of the statement or terminator being visited, and the constraints named after their trackers.
+    location: Location,
+    constraints: HashMap<String, TrackedConstraint>,
+    // the outcome of the solver, and whether a variable holding ownership is not modeled.
+    outcome: SolverOutcome,
+    unsupported: bool,
 }
 
 impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
@@ -183,8 +341,8 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
     ) -> Self {
         let body = mir_body(rcx.tcx(), did);
         let v_len = body.local_decls.len();
-        let b_len = body.basic_blocks.len();
         let graph = rcx.mir_graph().get(&did).unwrap();
+        let b_len = graph.node_count();
 
         Self {
             rcx,
@@ -196,6 +354,15 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
             elasped: (0, 0),
             taint_flag: false,
             taint_source: Vec::default(),
+            ownership: FnOwnership::default(),
+            summary_only: false,
+            arg_copies: arg_copies(body, |ty| ty.is_unsafe_ptr()),
+            owning_arg_copies: arg_copies(body, |ty| !ty.is_any_ptr()),
+            source_args: HashMap::default(),
+            location: Location::START,
+            constraints: HashMap::default(),
+            outcome: SolverOutcome::Skipped,
+            unsupported: false,
         }
     }
 
@@ -227,6 +394,14 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
     pub fn add_taint(&mut self, terminator: Terminator<'tcx>) {
         self.taint_source.push(terminator);
     }
+
+    pub fn ownership(&self) -> &FnOwnership {
+        &self.ownership
+    }
+
+    pub fn outcome(&self) -> SolverOutcome {
+        self.outcome
+    }
 }
 
 impl<'tcx, 'ctx, 'o, 'a> Rcx<'tcx, 'o, 'a> for IntraFlowAnalysis<'tcx, 'ctx, 'a> {
@@ -542,10 +717,7 @@ pub enum Z3GoalDisplay {
 }
 
 pub fn is_z3_goal_verbose() -> bool {
-    match env::var_os("Z3") {
-        Some(_) => true,
-        _ => false,
-    }
+    config().display.z3_goal
 }
 
 #[derive(Debug, Copy, Clone, Hash)]
@@ -555,8 +727,5 @@ pub enum IcxSliceDisplay {
 }
 
 pub fn is_icx_slice_verbose() -> bool {
-    match env::var_os("ICX_SLICE") {
-        Some(_) => true,
-        _ => false,
-    }
+    config().display.icx_slice
 }
@@ -1,29 +1,161 @@
+use rustc_middle::mir::{Body, Local, Operand, Rvalue, StatementKind, TerminatorKind};
+use rustc_middle::ty::{self, Ty};
+use rustc_span::def_id::DefId;
+
+use std::collections::{HashMap, HashSet};
+
 use super::super::RcxMut;
-use super::FlowAnalysis;
+use super::intra_visitor::solver_timeout;
+use super::{FlowAnalysis, IntraFlowAnalysis};
 use crate::analysis::core::heap_item::mir_body;
+use crate::utils::cache;
+use crate::utils::report::{stable_hash, Detector};
 
 impl<'tcx, 'a> FlowAnalysis<'tcx, 'a> {
+    /// Compute the ownership summaries of the functions in the crate, where the callees are summarized before
+    /// their callers. The functions in a recursion use the summaries of each other as far as they are computed.
     pub fn inter_run(&mut self) {
         let tcx = self.tcx();
-        let mir_keys = tcx.mir_keys(());
+        let adt_key = cache::summary_key(tcx, None, Some(self.rcx().adt_owner()), None);
+        for def_id in self.bottom_up() {
+            let summary_key = format!("{}{}", adt_key, self.ownership_key(def_id));
+            let ownership =
+                cache::with_summary(tcx, Detector::RCanary, def_id, &summary_key, || {
+                    //let sw = Stopwatch::start_new();
+                    let mut cfg = z3::Config::new();
+                    cfg.set_model_generation(true);
+                    cfg.set_timeout_msec(solver_timeout(tcx, def_id));
+                    let ctx = z3::Context::new(&cfg);
+                    let goal = z3::Goal::new(&ctx, true, false, false);
+                    let solver = z3::Solver::new(&ctx);
+                    let mut params = z3::Params::new(&ctx);
+                    params.set_bool("core.minimize", true);
+                    solver.set_params(&params);
 
-        for each_mir in mir_keys {
-            //let sw = Stopwatch::start_new();
-            let def_id = each_mir.to_def_id();
-            let body = mir_body(tcx, def_id);
+                    let body = mir_body(tcx, def_id);
+                    let mut intra_visitor = IntraFlowAnalysis::new(self.rcx, def_id);
+                    intra_visitor.summary_only = true;
+                    intra_visitor.visit_body(&ctx, &goal, &solver, body);
 
-            if body.basic_blocks.is_cfg_cyclic() {
-                continue;
+                    let ownership = intra_visitor.ownership().clone();
+                    let sec_build = intra_visitor.get_time_build();
+                    self.rcx_mut().add_time_build(sec_build);
+                    ownership
+                });
+            if !ownership.is_trivial() {
+                self.rcx_mut().fn_ownership_mut().insert(def_id, ownership);
+            }
+        }
+    }
+
+    // the functions of the crate in post order of the call graph.
+    fn bottom_up(&self) -> Vec<DefId> {
+        let tcx = self.tcx();
+        let mir_keys: HashSet<DefId> = tcx
+            .mir_keys(())
+            .iter()
+            .map(|each_mir| each_mir.to_def_id())
+            .collect();
+        let mut order = Vec::new();
+        let mut visited = HashSet::new();
+        // the functions being visited, each with its callees left to visit.
+        let mut worklist: Vec<(DefId, std::vec::IntoIter<DefId>)> = Vec::new();
+        for each_mir in tcx.mir_keys(()) {
+            let def_id = each_mir.to_def_id();
+            if visited.insert(def_id) {
+                worklist.push((def_id, self.callees(def_id).into_iter()));
             }
+            while let Some((def_id, callees)) = worklist.last_mut() {
+                match callees.next() {
+                    Some(callee) => {
+                        if mir_keys.contains(&callee) && visited.insert(callee) {
+                            worklist.push((callee, self.callees(callee).into_iter()));
+                        }
+                    }
+                    None => {
+                        order.push(*def_id);
+                        worklist.pop();
+                    }
+                }
+            }
+        }
+        order
+    }
 
-            let mut cfg = z3::Config::new();
-            cfg.set_model_generation(true);
-            cfg.set_timeout_msec(1000);
+    // the functions called by the function, in the order of the blocks.
+    fn callees(&self, def_id: DefId) -> Vec<DefId> {
+        let body = mir_body(self.tcx(), def_id);
+        body.basic_blocks
+            .iter()
+            .filter_map(|data| match &data.terminator().kind {
+                TerminatorKind::Call {
+                    func: Operand::Constant(constant),
+                    ..
+                } => match constant.ty().kind() {
+                    ty::FnDef(callee, ..) => Some(*callee),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
 
-            //let ctx = z3::Context::new(&cfg);
-            //let goal = z3::Goal::new(&ctx, true, false, false);
-            //let solver = z3::Solver::new(&ctx);
-            //let inter_visitor = InterFlowAnalysis::new(self.rcx);
+    /// A key of the ownership summaries of the callees, on which the summary and the findings of the function depend.
+    pub fn ownership_key(&self, def_id: DefId) -> String {
+        let tcx = self.tcx();
+        let mut summaries: Vec<String> = self
+            .callees(def_id)
+            .into_iter()
+            .filter_map(|callee| {
+                let ownership = self.rcx().fn_ownership().get(&callee)?;
+                Some(format!(
+                    "{}{:?}{:?}{:?}",
+                    tcx.def_path_str(callee),
+                    ownership.returned,
+                    ownership.taken,
+                    ownership.leaked
+                ))
+            })
+            .collect();
+        summaries.sort();
+        summaries.dedup();
+        format!("{:016x}", stable_hash(&[&summaries.join(";")]))
+    }
+}
+
+/// The locals holding the arguments of the function whose types are selected by the filter, which are either
+/// the arguments or their copies, mapped to the indexes of the arguments.
+pub fn arg_copies<'tcx>(
+    body: &Body<'tcx>,
+    filter: impl Fn(Ty<'tcx>) -> bool,
+) -> HashMap<usize, usize> {
+    let mut copies: HashMap<usize, usize> = (1..=body.arg_count)
+        .filter(|local| filter(body.local_decls[Local::from_usize(*local)].ty))
+        .map(|local| (local, local - 1))
+        .collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for data in body.basic_blocks.iter() {
+            for stmt in data.statements.iter() {
+                let StatementKind::Assign(box (lplace, rvalue)) = &stmt.kind else {
+                    continue;
+                };
+                let (Rvalue::Use(Operand::Copy(rplace) | Operand::Move(rplace))
+                | Rvalue::Cast(_, Operand::Copy(rplace) | Operand::Move(rplace), _)) = rvalue
+                else {
+                    continue;
+                };
+                if !lplace.projection.is_empty() || !rplace.projection.is_empty() {
+                    continue;
+                }
+                if let Some(arg_idx) = copies.get(&rplace.local.as_usize()).copied() {
+                    if copies.insert(lplace.local.as_usize(), arg_idx).is_none() {
+                        changed = true;
+                    }
+                }
+            }
         }
     }
+    copies
 }
@@ -1,29 +1,34 @@
 use rustc_middle::mir::{
-    BasicBlock, BasicBlockData, Body, Local, Operand, Place, ProjectionElem, Rvalue, Statement,
-    StatementKind, Terminator, TerminatorKind,
+    BasicBlock, BasicBlockData, Body, Local, Location, Operand, Place, ProjectionElem, Rvalue,
+    Statement, StatementKind, Terminator, TerminatorKind, VarDebugInfoContents,
 };
-use rustc_middle::ty::{self, Ty, TyKind, TypeVisitable};
+use rustc_middle::ty::{self, Ty, TyCtxt, TyKind, TypeVisitable};
+use rustc_span::def_id::DefId;
 use rustc_span::source_map::Spanned;
-use rustc_span::Symbol;
+use rustc_span::{Span, Symbol};
 use rustc_target::abi::VariantIdx;
 
-use annotate_snippets::{Level, Renderer, Snippet};
 use std::ops::Add;
 use z3::ast::{self, Ast};
 
 use super::super::{IcxMut, IcxSliceMut, Rcx, RcxMut};
 use super::is_z3_goal_verbose;
-use super::ownership::IntraVar;
-use super::{FlowAnalysis, IcxSliceFroBlock, IntraFlowAnalysis};
+use super::order::unroll_limit;
+use super::ownership::{FnOwnership, IntraVar};
+use super::{
+    ConstraintKind, FlowAnalysis, IcxSliceFroBlock, IntraFlowAnalysis, SolverOutcome,
+    TrackedConstraint,
+};
 use crate::analysis::core::heap_item::ownership::*;
 use crate::analysis::core::heap_item::type_visitor::*;
 use crate::analysis::core::heap_item::*;
-use crate::utils::log::{
-    are_spans_in_same_file, relative_pos_range, span_to_filename, span_to_line_number,
-    span_to_source_code,
-};
+use crate::analysis::utils::intrinsic_id::{freed_arg, intrinsic, is_intrinsic, Intrinsic};
+use crate::utils::cache;
+use crate::utils::config::config;
+use crate::utils::report::outcome::Outcome;
+use crate::utils::report::{self, Detector, Finding, FindingKind};
 use crate::utils::source::get_name;
-use crate::{rap_debug, rap_error, rap_trace, rap_warn};
+use crate::{rap_debug, rap_error, rap_trace};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum AsgnKind {
@@ -37,31 +42,103 @@ impl<'tcx, 'a> FlowAnalysis<'tcx, 'a> {
     pub fn intra_run(&mut self) {
         let tcx = self.tcx();
         let mir_keys = tcx.mir_keys(());
+        let adt_key = cache::summary_key(tcx, None, Some(self.rcx().adt_owner()), None);
 
         for each_mir in mir_keys {
             let def_id = each_mir.to_def_id();
-            let body = mir_body(tcx, def_id);
-            if body.basic_blocks.is_cfg_cyclic() {
+            if !report::in_scope(tcx, def_id) {
                 continue;
             }
+            let body = mir_body(tcx, def_id);
+            let summary_key = format!("{}{}", adt_key, self.ownership_key(def_id));
+            let unrolled_loops = self.unrolled_loops(def_id, body);
+
+            let outcome =
+                cache::with_findings(tcx, Detector::RCanary, def_id, &summary_key, || {
+                    let mut cfg = z3::Config::new();
+                    cfg.set_model_generation(true);
+                    cfg.set_timeout_msec(solver_timeout(tcx, def_id));
+                    let ctx = z3::Context::new(&cfg);
+                    let goal = z3::Goal::new(&ctx, true, false, false);
+                    let solver = z3::Solver::new(&ctx);
+                    // a minimal unsat core locates the leaks precisely
+                    let mut params = z3::Params::new(&ctx);
+                    params.set_bool("core.minimize", true);
+                    solver.set_params(&params);
+
+                    let mut intra_visitor = IntraFlowAnalysis::new(self.rcx, def_id);
+                    intra_visitor.visit_body(&ctx, &goal, &solver, body);
+
+                    let sec_build = intra_visitor.get_time_build();
+                    let sec_solve = intra_visitor.get_time_solve();
+                    let outcome = intra_visitor.outcome();
+
+                    self.rcx_mut().add_time_build(sec_build);
+                    self.rcx_mut().add_time_solve(sec_solve);
+                    outcome
+                });
+            report::record_outcome(
+                Outcome::new(
+                    tcx,
+                    Detector::RCanary,
+                    def_id,
+                    outcome.name(),
+                    outcome.is_verified(),
+                )
+                .unrolled_loops(unrolled_loops),
+            );
+        }
+    }
 
-            let mut cfg = z3::Config::new();
-            cfg.set_model_generation(true);
-            cfg.set_timeout_msec(1000);
-            let ctx = z3::Context::new(&cfg);
-            let goal = z3::Goal::new(&ctx, true, false, false);
-            let solver = z3::Solver::new(&ctx);
+    // the lines of the loops whose iterations after `limits.rcanary-unroll` are approximated.
+    fn unrolled_loops(&self, def_id: DefId, body: &'tcx Body<'tcx>) -> Vec<usize> {
+        let loops = self.mir_graph()[&def_id].get_loops();
+        if loops.is_empty() {
+            return Vec::new();
+        }
+        let source_map = self.tcx().sess.source_map();
+        let lines: Vec<usize> = loops
+            .iter()
+            .map(|head| loop_span(body, *head))
+            .map(|span| source_map.lookup_char_pos(span.lo()).line)
+            .collect();
+        rap_debug!(
+            "Loops of {} at lines {:?} are unrolled {} times, so the leaks after more iterations may be missed.",
+            self.tcx().def_path_str(def_id),
+            lines,
+            unroll_limit()
+        );
+        lines
+    }
+}
 
-            let mut intra_visitor = IntraFlowAnalysis::new(self.rcx, def_id);
-            intra_visitor.visit_body(&ctx, &goal, &solver, body);
+/// The time limit of the z3 solver for the function, in milliseconds.
+pub fn solver_timeout(tcx: TyCtxt<'_>, def_id: DefId) -> u64 {
+    config()
+        .limits
+        .rcanary_timeout_of(&tcx.def_path_str(def_id))
+}
 
-            let sec_build = intra_visitor.get_time_build();
-            let sec_solve = intra_visitor.get_time_solve();
+// the name of the local in the source code, which temporaries do not have.
+fn local_name(body: &Body<'_>, local: usize) -> Option<String> {
+    body.var_debug_info
+        .iter()
+        .find_map(|info| match info.value {
+            VarDebugInfoContents::Place(place)
+                if place.projection.is_empty() && place.local.as_usize() == local =>
+            {
+                Some(info.name.to_string())
+            }
+            _ => None,
+        })
+}
 
-            self.rcx_mut().add_time_build(sec_build);
-            self.rcx_mut().add_time_solve(sec_solve);
-        }
-    }
+// the span of the loop whose head is the block.
+fn loop_span(body: &Body<'_>, head: usize) -> Span {
+    body.basic_blocks[BasicBlock::from(head)]
+        .terminator()
+        .source_info
+        .span
 }
 
 impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
@@ -74,7 +151,8 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
     ) {
         let topo: Vec<usize> = self.graph().get_topo().iter().map(|id| *id).collect();
         for bidx in topo {
-            let data = &body.basic_blocks[BasicBlock::from(bidx)];
+            let block = self.graph().get_block(bidx);
+            let data = &body.basic_blocks[BasicBlock::from(block)];
             self.visit_block_data(ctx, goal, solver, data, bidx);
         }
     }
@@ -87,12 +165,20 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
         data: &'tcx BasicBlockData<'tcx>,
         bidx: usize,
     ) {
+        // the location of the constraints asserted from now on
+        let block = BasicBlock::from(self.graph().get_block(bidx));
+        self.location = Location {
+            block,
+            statement_index: 0,
+        };
         self.preprocess_for_basic_block(ctx, goal, solver, bidx);
 
         for (sidx, stmt) in data.statements.iter().enumerate() {
+            self.location.statement_index = sidx;
             self.visit_statement(ctx, goal, solver, stmt, bidx, sidx);
         }
 
+        self.location.statement_index = data.statements.len();
         self.visit_terminator(ctx, goal, solver, data.terminator(), bidx);
 
         self.reprocess_for_basic_block(bidx);
@@ -137,7 +223,7 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                 let constraint_init_arg = new_bv._eq(&init_const);
 
                 goal.assert(&constraint_init_arg);
-                solver.assert(&constraint_init_arg);
+                self.assert_tracked(ctx, solver, &constraint_init_arg, ConstraintKind::Arg, idx);
 
                 icx_slice.len_mut()[idx] = len;
                 icx_slice.var_mut()[idx] = IntraVar::Init(new_bv);
@@ -220,7 +306,7 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                 let constraint_phi = phi_bv._eq(&using_for_and_bv.unwrap());
 
                 goal.assert(&constraint_phi);
-                solver.assert(&constraint_phi);
+                self.assert_tracked(ctx, solver, &constraint_phi, ConstraintKind::Phi, var_idx);
 
                 ans_icx_slice.var_mut()[var_idx] = IntraVar::Init(phi_bv);
 
@@ -579,7 +665,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
             let l_zero_const = ast::BV::from_u64(ctx, 0, llen as u32);
             let constraint_l_ori_zero = l_ori_bv._safe_eq(&l_zero_const).unwrap();
             goal.assert(&constraint_l_ori_zero);
-            solver.assert(&constraint_l_ori_zero);
+            self.assert_tracked(
+                ctx,
+                solver,
+                &constraint_l_ori_zero,
+                ConstraintKind::Overwrite,
+                lu,
+            );
             is_ctor = false;
         } else {
             // this branch means that the assignment is the constructor of the lvalue
@@ -642,7 +734,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
         let constraint_owning_now = ast::Bool::or(ctx, args3);
 
         goal.assert(&constraint_owning_now);
-        solver.assert(&constraint_owning_now);
+        self.assert_tracked(
+            ctx,
+            solver,
+            &constraint_owning_now,
+            ConstraintKind::Assign,
+            lu,
+        );
 
         // update the Intra var value in current basic block (exactly, the statement)
         self.icx_slice_mut().var_mut()[lu] = IntraVar::Init(l_new_bv);
@@ -708,7 +806,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
             let l_zero_const = ast::BV::from_u64(ctx, 0, llen as u32);
             let constraint_l_ori_zero = l_ori_bv._safe_eq(&l_zero_const).unwrap();
             goal.assert(&constraint_l_ori_zero);
-            solver.assert(&constraint_l_ori_zero);
+            self.assert_tracked(
+                ctx,
+                solver,
+                &constraint_l_ori_zero,
+                ConstraintKind::Overwrite,
+                lu,
+            );
             is_ctor = false;
         } else {
             // this branch means that the assignment is the constructor of the lvalue
@@ -760,8 +864,8 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
 
         goal.assert(&r_non_owning);
         goal.assert(&l_owning);
-        solver.assert(&r_non_owning);
-        solver.assert(&l_owning);
+        self.assert_tracked(ctx, solver, &r_non_owning, ConstraintKind::Assign, ru);
+        self.assert_tracked(ctx, solver, &l_owning, ConstraintKind::Assign, lu);
 
         // update the Intra var value in current basic block (exactly, the statement)
         self.icx_slice_mut().var_mut()[lu] = IntraVar::Init(l_new_bv);
@@ -811,6 +915,21 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
         let rpj_ty = rplace.ty(&self.body().local_decls, self.tcx());
         let rpj_fields = extract_projection(rplace);
         if rpj_fields.is_unsupported() {
+            // dereferencing a box of plain data, e.g., `*b` or `b.iter()` of a box of integers, reads
+            // the pointer in the box and then the data behind it, which passes no ownership
+            let tcx = self.tcx();
+            let is_plain = |ty: Ty<'tcx>| {
+                !ty.needs_drop(tcx, ty::ParamEnv::reveal_all()) && !is_place_containing_ptr(&ty)
+            };
+            let rlocal_ty = self.body().local_decls[rlocal].ty;
+            let is_box_pointer = rlocal_ty.boxed_ty().is_some_and(is_plain)
+                && rplace
+                    .projection
+                    .iter()
+                    .all(|pj| matches!(pj, ProjectionElem::Field(..)));
+            if is_box_pointer || is_plain(rpj_ty.ty) {
+                return;
+            }
             // we only support that the field depth is 1 in max
             self.handle_intra_var_unsupported(lu);
             self.handle_intra_var_unsupported(ru);
@@ -845,7 +964,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
             let l_zero_const = ast::BV::from_u64(ctx, 0, llen as u32);
             let constraint_l_ori_zero = l_ori_bv._safe_eq(&l_zero_const).unwrap();
             goal.assert(&constraint_l_ori_zero);
-            solver.assert(&constraint_l_ori_zero);
+            self.assert_tracked(
+                ctx,
+                solver,
+                &constraint_l_ori_zero,
+                ConstraintKind::Overwrite,
+                lu,
+            );
             is_ctor = false;
         } else {
             // this branch means that the assignment is the constructor of the lvalue
@@ -936,7 +1061,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
         let constraint_owning_now = ast::Bool::or(ctx, args3);
 
         goal.assert(&constraint_owning_now);
-        solver.assert(&constraint_owning_now);
+        self.assert_tracked(
+            ctx,
+            solver,
+            &constraint_owning_now,
+            ConstraintKind::Assign,
+            lu,
+        );
 
         // update the Intra var value in current basic block (exactly, the statement)
         self.icx_slice_mut().var_mut()[lu] = IntraVar::Init(l_new_bv);
@@ -1026,7 +1157,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
             let l_zero_const = ast::BV::from_u64(ctx, 0, llen as u32);
             let constraint_l_ori_zero = l_ori_bv._safe_eq(&l_zero_const).unwrap();
             goal.assert(&constraint_l_ori_zero);
-            solver.assert(&constraint_l_ori_zero);
+            self.assert_tracked(
+                ctx,
+                solver,
+                &constraint_l_ori_zero,
+                ConstraintKind::Overwrite,
+                lu,
+            );
             is_ctor = false;
         } else {
             // this branch means that the assignment is the constructor of the lvalue
@@ -1104,8 +1241,8 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
 
         goal.assert(&l_extend_owning);
         goal.assert(&rpj_non_owning);
-        solver.assert(&l_extend_owning);
-        solver.assert(&rpj_non_owning);
+        self.assert_tracked(ctx, solver, &l_extend_owning, ConstraintKind::Assign, lu);
+        self.assert_tracked(ctx, solver, &rpj_non_owning, ConstraintKind::Assign, ru);
 
         // update the Intra var value in current basic block (exactly, the statement)
         self.icx_slice_mut().var_mut()[lu] = IntraVar::Init(l_new_bv);
@@ -1216,7 +1353,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                 let l_f_zero_const = ast::BV::from_u64(ctx, 0, 1);
                 let constraint_l_f_ori_zero = extract_from_field._safe_eq(&l_f_zero_const).unwrap();
                 goal.assert(&constraint_l_f_ori_zero);
-                solver.assert(&constraint_l_f_ori_zero);
+                self.assert_tracked(
+                    ctx,
+                    solver,
+                    &constraint_l_f_ori_zero,
+                    ConstraintKind::Overwrite,
+                    lu,
+                );
             }
         } else {
             // this branch means that the assignment is the constructor of the lvalue (either l and l.f)
@@ -1226,7 +1369,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
             let l_ori_zero = ast::BV::from_u64(ctx, 0, llen as u32);
             let constraint_l_ctor_zero = l_ori_bv_ctor._safe_eq(&l_ori_zero).unwrap();
             goal.assert(&constraint_l_ctor_zero);
-            solver.assert(&constraint_l_ctor_zero);
+            self.assert_tracked(
+                ctx,
+                solver,
+                &constraint_l_ctor_zero,
+                ConstraintKind::Assign,
+                lu,
+            );
             l_ori_bv = l_ori_zero;
             self.icx_slice_mut().ty_mut()[lu] = TyWithIndex::new(l_local_ty, disc);
             self.icx_slice_mut().layout_mut()[lu] = default_ownership.layout().clone();
@@ -1294,7 +1443,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
         let constraint_owning_now = ast::Bool::or(ctx, args3);
 
         goal.assert(&constraint_owning_now);
-        solver.assert(&constraint_owning_now);
+        self.assert_tracked(
+            ctx,
+            solver,
+            &constraint_owning_now,
+            ConstraintKind::Assign,
+            lu,
+        );
 
         // update the Intra var value in current basic block (exactly, the statement)
         self.icx_slice_mut().var_mut()[lu] = IntraVar::Init(l_new_bv);
@@ -1406,7 +1561,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                 let l_f_zero_const = ast::BV::from_u64(ctx, 0, 1);
                 let constraint_l_f_ori_zero = extract_from_field._safe_eq(&l_f_zero_const).unwrap();
                 goal.assert(&constraint_l_f_ori_zero);
-                solver.assert(&constraint_l_f_ori_zero);
+                self.assert_tracked(
+                    ctx,
+                    solver,
+                    &constraint_l_f_ori_zero,
+                    ConstraintKind::Overwrite,
+                    lu,
+                );
             }
         } else {
             // this branch means that the assignment is the constructor of the lvalue (either l and l.f)
@@ -1416,7 +1577,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
             let l_ori_zero = ast::BV::from_u64(ctx, 0, llen as u32);
             let constraint_l_ctor_zero = l_ori_bv_ctor._safe_eq(&l_ori_zero).unwrap();
             goal.assert(&constraint_l_ctor_zero);
-            solver.assert(&constraint_l_ctor_zero);
+            self.assert_tracked(
+                ctx,
+                solver,
+                &constraint_l_ctor_zero,
+                ConstraintKind::Assign,
+                lu,
+            );
             l_ori_bv = l_ori_zero;
             self.icx_slice_mut().ty_mut()[lu] = TyWithIndex::new(l_local_ty, disc);
             self.icx_slice_mut().layout_mut()[lu] = default_ownership.layout_mut().clone();
@@ -1462,8 +1629,8 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
 
         goal.assert(&r_non_owning);
         goal.assert(&lpj_shrink_owning);
-        solver.assert(&r_non_owning);
-        solver.assert(&lpj_shrink_owning);
+        self.assert_tracked(ctx, solver, &r_non_owning, ConstraintKind::Assign, ru);
+        self.assert_tracked(ctx, solver, &lpj_shrink_owning, ConstraintKind::Assign, lu);
 
         // update the Intra var value in current basic block (exactly, the statement)
         self.icx_slice_mut().var_mut()[lu] = IntraVar::Init(l_new_bv);
@@ -1573,7 +1740,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                 let l_f_zero_const = ast::BV::from_u64(ctx, 0, 1);
                 let constraint_l_f_ori_zero = extract_from_field._safe_eq(&l_f_zero_const).unwrap();
                 goal.assert(&constraint_l_f_ori_zero);
-                solver.assert(&constraint_l_f_ori_zero);
+                self.assert_tracked(
+                    ctx,
+                    solver,
+                    &constraint_l_f_ori_zero,
+                    ConstraintKind::Overwrite,
+                    lu,
+                );
             }
         } else {
             // this branch means that the assignment is the constructor of the lvalue (either l and l.f)
@@ -1583,7 +1756,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
             let l_ori_zero = ast::BV::from_u64(ctx, 0, llen as u32);
             let constraint_l_ctor_zero = l_ori_bv_ctor._safe_eq(&l_ori_zero).unwrap();
             goal.assert(&constraint_l_ctor_zero);
-            solver.assert(&constraint_l_ctor_zero);
+            self.assert_tracked(
+                ctx,
+                solver,
+                &constraint_l_ctor_zero,
+                ConstraintKind::Assign,
+                lu,
+            );
             l_ori_bv = l_ori_zero;
             self.icx_slice_mut().ty_mut()[lu] = TyWithIndex::new(l_local_ty, disc);
             self.icx_slice_mut().layout_mut()[lu] = default_ownership.layout().clone();
@@ -1649,7 +1828,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
         let constraint_owning_now = ast::Bool::or(ctx, args3);
 
         goal.assert(&constraint_owning_now);
-        solver.assert(&constraint_owning_now);
+        self.assert_tracked(
+            ctx,
+            solver,
+            &constraint_owning_now,
+            ConstraintKind::Assign,
+            lu,
+        );
 
         // update the Intra var value in current basic block (exactly, the statement)
         self.icx_slice_mut().var_mut()[lu] = IntraVar::Init(l_new_bv);
@@ -1762,7 +1947,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                 let l_f_zero_const = ast::BV::from_u64(ctx, 0, 1);
                 let constraint_l_f_ori_zero = extract_from_field._safe_eq(&l_f_zero_const).unwrap();
                 goal.assert(&constraint_l_f_ori_zero);
-                solver.assert(&constraint_l_f_ori_zero);
+                self.assert_tracked(
+                    ctx,
+                    solver,
+                    &constraint_l_f_ori_zero,
+                    ConstraintKind::Overwrite,
+                    lu,
+                );
             }
         } else {
             // this branch means that the assignment is the constructor of the lvalue (either l and l.f)
@@ -1772,7 +1963,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
             let l_ori_zero = ast::BV::from_u64(ctx, 0, llen as u32);
             let constraint_l_ctor_zero = l_ori_bv_ctor._safe_eq(&l_ori_zero).unwrap();
             goal.assert(&constraint_l_ctor_zero);
-            solver.assert(&constraint_l_ctor_zero);
+            self.assert_tracked(
+                ctx,
+                solver,
+                &constraint_l_ctor_zero,
+                ConstraintKind::Assign,
+                lu,
+            );
             l_ori_bv = l_ori_zero;
             self.icx_slice_mut().ty_mut()[lu] = TyWithIndex::new(l_local_ty, disc);
             self.icx_slice_mut().layout_mut()[lu] = default_ownership.layout().clone();
@@ -1819,8 +2016,8 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
 
         goal.assert(&rpj_non_owning);
         goal.assert(&lpj_owning);
-        solver.assert(&rpj_non_owning);
-        solver.assert(&lpj_owning);
+        self.assert_tracked(ctx, solver, &rpj_non_owning, ConstraintKind::Assign, ru);
+        self.assert_tracked(ctx, solver, &lpj_owning, ConstraintKind::Assign, lu);
 
         // update the Intra var value in current basic block (exactly, the statement)
         self.icx_slice_mut().var_mut()[lu] = IntraVar::Init(l_new_bv);
@@ -1859,6 +2056,95 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
         }
     }
 
+    // the ownership summary of the function called, if it is defined in the crate.
+    pub(crate) fn callee_ownership(&self, func: &Operand<'tcx>) -> Option<&'a FnOwnership> {
+        let rcx = self.rcx;
+        match func {
+            Operand::Constant(constant) => match constant.ty().kind() {
+                ty::FnDef(id, ..) => rcx.fn_ownership().get(id),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // record the pointer arguments of the function that are taken over by the call, either
+    // for fn(*) -> Self or by a callee taking them over.
+    pub(crate) fn record_fn_taken(
+        &mut self,
+        args: &[Spanned<Operand<'tcx>>],
+        dest: &Place<'tcx>,
+        callee: Option<&FnOwnership>,
+    ) {
+        let l_place_ty = dest.ty(&self.body().local_decls, self.tcx());
+        let default_layout =
+            self.extract_default_ty_layout(l_place_ty.ty, l_place_ty.variant_index);
+        let is_recovery = default_layout.is_owned() && !l_place_ty.ty.is_any_ptr();
+
+        for (index, arg) in args.iter().enumerate() {
+            let aplace = match arg.node {
+                Operand::Move(aplace) | Operand::Copy(aplace) => aplace,
+                Operand::Constant(..) => continue,
+            };
+            if !aplace.projection.is_empty() {
+                continue;
+            }
+            let Some(arg_idx) = self.arg_copies.get(&aplace.local.as_usize()).copied() else {
+                continue;
+            };
+            let taken =
+                is_recovery || callee.is_some_and(|ownership| ownership.taken.contains(&index));
+            if taken && !self.ownership.taken.contains(&arg_idx) {
+                self.ownership.taken.push(arg_idx);
+            }
+        }
+    }
+
+    // the locals of the owning arguments moved into the call that the callee leaks.
+    pub(crate) fn leaked_args(
+        &mut self,
+        args: &[Spanned<Operand<'tcx>>],
+        callee: Option<&FnOwnership>,
+    ) -> Vec<usize> {
+        let Some(ownership) = callee else {
+            return Vec::new();
+        };
+        let mut leaked = Vec::new();
+        for index in ownership.leaked.iter() {
+            if let Some(Operand::Move(aplace)) = args.get(*index).map(|arg| &arg.node) {
+                if aplace.projection.is_empty() {
+                    leaked.push(aplace.local.as_usize());
+                    self.taint_flag = true;
+                }
+            }
+        }
+        leaked
+    }
+
+    // record the owning arguments of the function that are moved into a source, e.g., `Box::into_raw(arg)`,
+    // so that a leak of the source is a leak of the arguments to the callers.
+    pub(crate) fn record_source_args(&mut self, args: &[Spanned<Operand<'tcx>>]) {
+        for arg in args.iter() {
+            let Operand::Move(aplace) = arg.node else {
+                continue;
+            };
+            if !aplace.projection.is_empty() {
+                continue;
+            }
+            let Some(arg_idx) = self
+                .owning_arg_copies
+                .get(&aplace.local.as_usize())
+                .copied()
+            else {
+                continue;
+            };
+            let sources = self.source_args.entry(self.location).or_default();
+            if !sources.contains(&arg_idx) {
+                sources.push(arg_idx);
+            }
+        }
+    }
+
     pub(crate) fn check_fn_recovery(
         &mut self,
         //args: &Vec<Operand<'tcx>>,
@@ -1921,25 +2207,22 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                     ty::FnDef(id, ..) => {
                         //rap_debug!("{:?}", id);
                         //rap_debug!("{:?}", mir_body(self.tcx(), *id));
-                        match id.index.as_usize() {
-                            2171 => {
-                                // this for calling std::mem::drop(TY)
-                                match args[0].node {
-                                    Operand::Move(aplace) => {
-                                        let a_place_ty =
-                                            dest.ty(&self.body().local_decls, self.tcx());
-                                        let a_ty = a_place_ty.ty;
-                                        if a_ty.is_adt() {
-                                            self.handle_drop(
-                                                ctx, goal, solver, &aplace, bidx, false,
-                                            );
-                                            return;
-                                        }
+                        if is_intrinsic(*id, Intrinsic::Drop) {
+                            // this for calling std::mem::drop(TY)
+                            // the destination is always `()`, so it is the type of the argument
+                            // that tells whether an instance is dropped
+                            match args[0].node {
+                                Operand::Move(aplace) => {
+                                    let a_place_ty =
+                                        aplace.ty(&self.body().local_decls, self.tcx());
+                                    let a_ty = a_place_ty.ty;
+                                    if a_ty.is_adt() {
+                                        self.handle_drop(ctx, goal, solver, &aplace, bidx, false);
+                                        return;
                                     }
-                                    _ => (),
                                 }
+                                _ => (),
                             }
-                            _ => (),
                         }
                     }
                     _ => (),
@@ -1954,12 +2237,59 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
 
         // the source flag is for fn(self) -> */&
         // we will tag the lvalue as tainted and change the default ctor to modified one
-        let source_flag = self.check_fn_source(args, dest);
+        let mut source_flag = self.check_fn_source(args, dest);
         // the recovery flag is for fn(*) -> Self
         // the return value should have the same layout as tainted one
         // we will take the ownership of the args if the arg is a pointer
-        let recovery_flag = self.check_fn_recovery(args, dest);
+        let mut recovery_flag = self.check_fn_recovery(args, dest);
+        // allocators: the returned pointer owns the new allocation,
+        // and the pointer given to dealloc or realloc gives up its ownership
+        if let Operand::Constant(constant) = func {
+            if let ty::FnDef(id, ..) = constant.ty().kind() {
+                let intrinsic = intrinsic(*id);
+                let l_place_ty = dest.ty(&self.body().local_decls, self.tcx());
+                if matches!(intrinsic, Some(Intrinsic::Alloc | Intrinsic::Realloc))
+                    && is_place_containing_ptr(&l_place_ty.ty)
+                {
+                    self.taint_flag = true;
+                    source_flag = true;
+                }
+                if matches!(intrinsic, Some(Intrinsic::Dealloc | Intrinsic::Realloc)) {
+                    if let Some(arg) = args.get(freed_arg(self.tcx(), *id)) {
+                        if let Operand::Move(aplace) | Operand::Copy(aplace) = arg.node {
+                            recovery_flag.0 = true;
+                            recovery_flag.1.push(aplace.local.as_usize());
+                        }
+                    }
+                }
+            }
+        }
+        // the ownership summary of the callee if it is defined in the crate
+        let callee = self.callee_ownership(func);
+        if let Some(ownership) = callee {
+            let l_place_ty = dest.ty(&self.body().local_decls, self.tcx());
+            if ownership.returned && is_place_containing_ptr(&l_place_ty.ty) {
+                self.taint_flag = true;
+                source_flag = true;
+                self.icx_slice_mut().taint_mut()[lu].insert(TyWithIndex::new(l_place_ty.ty, None));
+            }
+            for index in ownership.taken.iter() {
+                if let Some(Operand::Move(aplace) | Operand::Copy(aplace)) =
+                    args.get(*index).map(|arg| &arg.node)
+                {
+                    let au = aplace.local.as_usize();
+                    if self.icx_slice().taint()[au].is_tainted() {
+                        recovery_flag.0 = true;
+                        recovery_flag.1.push(au);
+                    }
+                }
+            }
+        }
+        self.record_fn_taken(args, dest, callee);
+        let leaked = self.leaked_args(args, callee);
+
         if source_flag {
+            self.record_source_args(args);
             self.add_taint(term);
         }
 
@@ -2014,11 +2344,36 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
 
                                 goal.assert(&a_ori_non_owing);
                                 goal.assert(&update_a);
-                                solver.assert(&a_ori_non_owing);
-                                solver.assert(&update_a);
+                                self.assert_tracked(
+                                    ctx,
+                                    solver,
+                                    &a_ori_non_owing,
+                                    ConstraintKind::PassPtr,
+                                    au,
+                                );
+                                self.assert_tracked(
+                                    ctx,
+                                    solver,
+                                    &update_a,
+                                    ConstraintKind::Call,
+                                    au,
+                                );
 
                                 self.icx_slice_mut().var_mut()[au] = IntraVar::Init(a_new_bv);
                             } else {
+                                // if the callee leaks the instance, it must not hold ownership (move i => i=0)
+                                if leaked.contains(&au) {
+                                    let a_zero_const = ast::BV::from_u64(ctx, 0, alen as u32);
+                                    let a_ori_non_owing = a_ori_bv._safe_eq(&a_zero_const).unwrap();
+                                    goal.assert(&a_ori_non_owing);
+                                    self.assert_tracked(
+                                        ctx,
+                                        solver,
+                                        &a_ori_non_owing,
+                                        ConstraintKind::Leaked,
+                                        au,
+                                    );
+                                }
                                 // if the aplace is a instance (move i => drop)
                                 self.handle_drop(ctx, goal, solver, &aplace, bidx, false);
                             }
@@ -2038,7 +2393,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                                 let update_a = a_new_bv._safe_eq(&a_ori_bv).unwrap();
 
                                 goal.assert(&update_a);
-                                solver.assert(&update_a);
+                                self.assert_tracked(
+                                    ctx,
+                                    solver,
+                                    &update_a,
+                                    ConstraintKind::Call,
+                                    au,
+                                );
                             } else {
                                 // if the aplace is a instance (move i.f => i.f=0)
                                 self.handle_drop(ctx, goal, solver, &aplace, bidx, false);
@@ -2093,8 +2454,20 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
 
                                 goal.assert(&a_ori_non_owing);
                                 goal.assert(&update_a);
-                                solver.assert(&a_ori_non_owing);
-                                solver.assert(&update_a);
+                                self.assert_tracked(
+                                    ctx,
+                                    solver,
+                                    &a_ori_non_owing,
+                                    ConstraintKind::PassPtr,
+                                    au,
+                                );
+                                self.assert_tracked(
+                                    ctx,
+                                    solver,
+                                    &update_a,
+                                    ConstraintKind::Call,
+                                    au,
+                                );
 
                                 self.icx_slice_mut().var_mut()[au] = IntraVar::Init(a_new_bv);
                             } else {
@@ -2113,7 +2486,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                                 let update_a = a_new_bv._safe_eq(&a_ori_bv).unwrap();
 
                                 goal.assert(&update_a);
-                                solver.assert(&update_a);
+                                self.assert_tracked(
+                                    ctx,
+                                    solver,
+                                    &update_a,
+                                    ConstraintKind::Call,
+                                    au,
+                                );
                             }
                         }
                         1 => {
@@ -2123,7 +2502,7 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                             let update_a = a_new_bv._safe_eq(&a_ori_bv).unwrap();
 
                             goal.assert(&update_a);
-                            solver.assert(&update_a);
+                            self.assert_tracked(ctx, solver, &update_a, ConstraintKind::Call, au);
                         }
                         _ => {
                             self.handle_intra_var_unsupported(au);
@@ -2176,7 +2555,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                     let l_zero_const = ast::BV::from_u64(ctx, 0, llen as u32);
                     let constraint_l_ori_zero = l_ori_bv._safe_eq(&l_zero_const).unwrap();
                     goal.assert(&constraint_l_ori_zero);
-                    solver.assert(&constraint_l_ori_zero);
+                    self.assert_tracked(
+                        ctx,
+                        solver,
+                        &constraint_l_ori_zero,
+                        ConstraintKind::Overwrite,
+                        lu,
+                    );
                     is_ctor = false;
                 } else {
                     // this branch means that the assignment is the constructor of the lvalue
@@ -2214,7 +2599,12 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                 let constraint_new_owning = l_new_bv._safe_eq(&l_layout_bv).unwrap();
 
                 goal.assert(&constraint_new_owning);
-                solver.assert(&constraint_new_owning);
+                let kind = if source_flag {
+                    ConstraintKind::Source
+                } else {
+                    ConstraintKind::Call
+                };
+                self.assert_tracked(ctx, solver, &constraint_new_owning, kind, lu);
 
                 self.icx_slice_mut().len_mut()[lu] = llen;
                 self.icx_slice_mut().var_mut()[lu] = IntraVar::Init(l_new_bv);
@@ -2243,7 +2633,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                         extract_from_field._safe_eq(&l_f_zero_const).unwrap();
 
                     goal.assert(&constraint_l_f_ori_zero);
-                    solver.assert(&constraint_l_f_ori_zero);
+                    self.assert_tracked(
+                        ctx,
+                        solver,
+                        &constraint_l_f_ori_zero,
+                        ConstraintKind::Overwrite,
+                        lu,
+                    );
                 } else {
                     let l_ori_name_ctor = new_local_name(lu, bidx, 0).add("_ctor_fn");
                     let l_ori_bv_ctor = ast::BV::new_const(ctx, l_ori_name_ctor, llen as u32);
@@ -2251,7 +2647,13 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                     let constraint_l_ctor_zero = l_ori_bv_ctor._safe_eq(&l_ori_zero).unwrap();
 
                     goal.assert(&constraint_l_ctor_zero);
-                    solver.assert(&constraint_l_ctor_zero);
+                    self.assert_tracked(
+                        ctx,
+                        solver,
+                        &constraint_l_ctor_zero,
+                        ConstraintKind::Call,
+                        lu,
+                    );
 
                     l_ori_bv = l_ori_zero;
                     self.icx_slice_mut().ty_mut()[lu] = TyWithIndex::new(l_local_ty, None);
@@ -2285,7 +2687,12 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                 let update_filed_using_func = l_new_bv._safe_eq(&final_bv).unwrap();
 
                 goal.assert(&update_filed_using_func);
-                solver.assert(&update_filed_using_func);
+                let kind = if source_flag {
+                    ConstraintKind::Source
+                } else {
+                    ConstraintKind::Call
+                };
+                self.assert_tracked(ctx, solver, &update_filed_using_func, kind, lu);
 
                 self.icx_slice_mut().len_mut()[lu] = return_value_layout.layout().len();
                 self.icx_slice_mut().var_mut()[lu] = IntraVar::Init(l_new_bv);
@@ -2304,16 +2711,36 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
         solver: &'ctx z3::Solver<'ctx>,
         bidx: usize,
     ) {
+        // the returned pointer owns the heap items that it is tainted by
+        if is_place_containing_ptr(&self.body().local_decls[Local::from_usize(0)].ty)
+            && self.icx_slice().taint()[0].is_tainted()
+        {
+            self.ownership.returned = true;
+        }
+        // the leaks of the arguments are only checked in the summaries of the functions with owning arguments.
+        let has_owning_args = self
+            .constraints
+            .values()
+            .any(|constraint| constraint.kind == ConstraintKind::Arg);
+        if self.summary_only && !has_owning_args {
+            return;
+        }
+
         let place_0 = Place::from(Local::from_usize(0));
         self.handle_drop(ctx, goal, solver, &place_0, bidx, false);
+        // the ownership is lost if it is returned in a value that neither owns heap items nor is a pointer, e.g., a usize.
+        let ret_ty = self.body().local_decls[Local::from_usize(0)].ty;
+        let ret_loses_ownership = !is_place_containing_ptr(&ret_ty)
+            && !self.extract_default_ty_layout(ret_ty, None).is_owned();
 
         // when whole function return => we need to check every variable is freed
+        let mut returns = Vec::new();
         for (iidx, var) in self.icx_slice().var.iter().enumerate() {
             let len = self.icx_slice().len()[iidx];
             if len == 0 {
                 continue;
             }
-            if iidx <= self.body().arg_count {
+            if iidx <= self.body().arg_count && !(iidx == 0 && ret_loses_ownership) {
                 continue;
             }
 
@@ -2332,12 +2759,28 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                 let constraint_return = ast::Bool::and(ctx, args);
 
                 goal.assert(&constraint_return);
-                solver.assert(&constraint_return);
+                returns.push((iidx, constraint_return));
             }
         }
+        for (iidx, constraint_return) in returns {
+            self.assert_tracked(
+                ctx,
+                solver,
+                &constraint_return,
+                ConstraintKind::Return,
+                iidx,
+            );
+        }
 
         let result = solver.check();
+        if self.summary_only {
+            if result == z3::SatResult::Unsat && self.taint_flag {
+                self.record_leaked_args(solver);
+            }
+            return;
+        }
         let model = solver.get_model();
+        self.outcome = self.outcome.join(self.solver_outcome(solver, result));
 
         if is_z3_goal_verbose() {
             let g = format!("{}", goal);
@@ -2356,38 +2799,57 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
             let fn_name = get_name(self.tcx(), self.did)
                 .unwrap_or_else(|| Symbol::intern("no symbol available"));
 
-            rap_warn!("Memory Leak detected in function {:}", fn_name);
-            let source = span_to_source_code(self.body().span);
-            let file = span_to_filename(self.body().span);
-            let mut snippet = Snippet::source(&source)
-                .line_start(span_to_line_number(self.body().span))
-                .origin(&file)
+            let mut finding = Finding::new(FindingKind::MemoryLeak, self.did, self.body().span)
+                .summary(format!("Memory Leak detected in function {:}", fn_name))
+                .message("Memory Leak detected.")
                 .fold(false);
-
-            for source in self.taint_source.iter() {
-                if are_spans_in_same_file(self.body().span, source.source_info.span) {
-                    snippet = snippet.annotation(
-                        Level::Warning
-                            .span(unsafe {
-                                relative_pos_range(self.body().span, source.source_info.span)
-                            })
-                            .label("Memory Leak Candidates."),
-                    );
+            // the conflicting constraints point to the leaked heap items and to where their ownership is lost.
+            let core = self.unsat_core(solver);
+            let mut spans: Vec<Span> = Vec::new();
+            for constraint in core.iter() {
+                let message = match constraint.kind {
+                    ConstraintKind::Source => "The heap item allocated here is leaked.",
+                    ConstraintKind::Leaked => "The heap item passed here is leaked by the callee.",
+                    _ => continue,
+                };
+                let span = self.body().source_info(constraint.location).span;
+                if !spans.contains(&span) {
+                    spans.push(span);
+                    finding = finding.primary(span, message);
                 }
-                // rap_warn!(
-                //     "{}",
-                //     format!(
-                //         "RCanary: LeakItem Candidates: {:?}, {:?}",
-                //         source.kind, source.source_info.span
-                //     )
-                // );
             }
-
-            let message = Level::Warning
-                .title("Memory Leak detected.")
-                .snippet(snippet);
-            let renderer = Renderer::styled();
-            println!("{}", renderer.render(message));
+            if spans.is_empty() {
+                // the copies of an unrolled loop share the same candidates.
+                for source in self.taint_source.iter() {
+                    if !spans.contains(&source.source_info.span) {
+                        spans.push(source.source_info.span);
+                        finding =
+                            finding.primary(source.source_info.span, "Memory Leak Candidates.");
+                    }
+                }
+            }
+            let mut losses: Vec<Span> = Vec::new();
+            for constraint in core.iter().filter(|c| c.kind.is_loss()) {
+                let span = self.body().source_info(constraint.location).span;
+                if !losses.contains(&span) && !spans.contains(&span) {
+                    losses.push(span);
+                    let message = match local_name(self.body(), constraint.local) {
+                        Some(name) => format!("The ownership of `{}` is lost here.", name),
+                        None => "The ownership is lost here.".to_string(),
+                    };
+                    finding = finding.related(span, message);
+                }
+            }
+            if !self.graph().get_loops().is_empty() {
+                for head in self.graph().get_loops() {
+                    finding = finding.related(loop_span(self.body(), *head), "Loop unrolled.");
+                }
+                finding = finding.help(format!(
+                    "The loops are unrolled {} times by `limits.rcanary-unroll`, so the later iterations are approximated.",
+                    unroll_limit()
+                ));
+            }
+            report::emit(finding);
         }
     }
 
@@ -2436,7 +2898,7 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                     let constraint_recovery = new_bv._eq(&and_bv);
 
                     goal.assert(&constraint_recovery);
-                    solver.assert(&constraint_recovery);
+                    self.assert_tracked(ctx, solver, &constraint_recovery, ConstraintKind::Drop, u);
 
                     self.icx_slice_mut().var_mut()[u] = IntraVar::Init(new_bv);
                 } else {
@@ -2451,7 +2913,7 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                     let constraint_reverse = new_bv._eq(&and_bv);
 
                     goal.assert(&constraint_reverse);
-                    solver.assert(&constraint_reverse);
+                    self.assert_tracked(ctx, solver, &constraint_reverse, ConstraintKind::Drop, u);
 
                     self.icx_slice_mut().var_mut()[u] = IntraVar::Init(new_bv);
                 }
@@ -2477,7 +2939,7 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                     let constraint_update = new_bv._eq(&ori_bv);
 
                     goal.assert(&constraint_update);
-                    solver.assert(&constraint_update);
+                    self.assert_tracked(ctx, solver, &constraint_update, ConstraintKind::Drop, u);
 
                     self.icx_slice_mut().var_mut()[u] = IntraVar::Init(new_bv);
                 } else {
@@ -2497,7 +2959,7 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
                     let constraint_free_f = new_bv._safe_eq(&final_bv).unwrap();
 
                     goal.assert(&constraint_free_f);
-                    solver.assert(&constraint_free_f);
+                    self.assert_tracked(ctx, solver, &constraint_free_f, ConstraintKind::Drop, u);
 
                     self.icx_slice_mut().var_mut()[u] = IntraVar::Init(new_bv);
                 }
@@ -2505,7 +2967,89 @@ impl<'tcx, 'ctx, 'a> IntraFlowAnalysis<'tcx, 'ctx, 'a> {
         }
     }
 
+    // assert the constraint tracked by a boolean constant, so that it can be found in the unsat core.
+    pub(crate) fn assert_tracked(
+        &mut self,
+        ctx: &'ctx z3::Context,
+        solver: &'ctx z3::Solver<'ctx>,
+        constraint: &ast::Bool<'ctx>,
+        kind: ConstraintKind,
+        local: usize,
+    ) {
+        let name = format!(
+            "{:?}_{}@{:?}#{}",
+            kind,
+            local,
+            self.location,
+            self.constraints.len()
+        );
+        let tracker = ast::Bool::new_const(ctx, name);
+        solver.assert_and_track(constraint, &tracker);
+        self.constraints.insert(
+            tracker.to_string(),
+            TrackedConstraint::new(kind, local, self.location),
+        );
+    }
+
+    // the owning arguments whose heap items are leaked: either their initial ownership or the sources they are
+    // turned into conflict with the other constraints.
+    fn record_leaked_args(&mut self, solver: &'ctx z3::Solver<'ctx>) {
+        let arg_count = self.body().arg_count;
+        let mut leaked = Vec::new();
+        for constraint in self.unsat_core(solver) {
+            match constraint.kind {
+                ConstraintKind::Arg if (1..=arg_count).contains(&constraint.local) => {
+                    leaked.push(constraint.local - 1)
+                }
+                ConstraintKind::Source => {
+                    if let Some(args) = self.source_args.get(&constraint.location) {
+                        leaked.extend(args.iter().copied());
+                    }
+                }
+                _ => (),
+            }
+        }
+        for arg_idx in leaked {
+            if !self.ownership.leaked.contains(&arg_idx) {
+                self.ownership.leaked.push(arg_idx);
+            }
+        }
+    }
+
+    // the tracked constraints in the unsat core of the solver.
+    pub(crate) fn unsat_core(&self, solver: &'ctx z3::Solver<'ctx>) -> Vec<&TrackedConstraint> {
+        solver
+            .get_unsat_core()
+            .iter()
+            .filter_map(|tracker| self.constraints.get(&tracker.to_string()))
+            .collect()
+    }
+
+    // the outcome of checking a return: the function is checked partially if some ownership is not modeled.
+    fn solver_outcome(
+        &self,
+        solver: &'ctx z3::Solver<'ctx>,
+        result: z3::SatResult,
+    ) -> SolverOutcome {
+        match result {
+            z3::SatResult::Unsat if self.taint_flag => SolverOutcome::Leak,
+            z3::SatResult::Unsat => SolverOutcome::Unsat,
+            z3::SatResult::Sat if self.unsupported => SolverOutcome::Unsupported,
+            z3::SatResult::Sat => SolverOutcome::Sat,
+            z3::SatResult::Unknown => match solver.get_reason_unknown() {
+                Some(reason) if reason.contains("timeout") || reason.contains("canceled") => {
+                    SolverOutcome::Timeout
+                }
+                _ => SolverOutcome::Unknown,
+            },
+        }
+    }
+
     pub(crate) fn handle_intra_var_unsupported(&mut self, idx: usize) {
+        let ty = self.body().local_decls[Local::from_usize(idx)].ty;
+        if ty.needs_drop(self.tcx(), ty::ParamEnv::reveal_all()) || is_place_containing_ptr(&ty) {
+            self.unsupported = true;
+        }
         match self.icx_slice_mut().var_mut()[idx] {
             IntraVar::Unsupported => return,
             IntraVar::Declared | IntraVar::Init(_) => {
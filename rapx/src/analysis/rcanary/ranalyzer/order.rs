@@ -1,11 +1,17 @@
 use rustc_middle::mir::TerminatorKind;
 
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 //use stopwatch::Stopwatch;
 
-use super::super::ranalyzer::{FlowAnalysis, NodeOrder};
+use super::super::ranalyzer::{Edges, FlowAnalysis, Graph, NodeOrder};
 use super::super::RcxMut;
 use super::mir_body;
+use crate::utils::config::config;
+
+/// The times that the loops are unrolled, set by `limits.rcanary-unroll` in the configuration.
+pub fn unroll_limit() -> usize {
+    config().limits.rcanary_unroll
+}
 
 impl<'tcx, 'a> FlowAnalysis<'tcx, 'a> {
     pub fn order(&mut self) {
@@ -26,6 +32,7 @@ impl<'tcx, 'a> FlowAnalysis<'tcx, 'a> {
             let mut lev: Vec<usize> = vec![0; body.basic_blocks.len()];
 
             path.collect_edges(&mut lev);
+            path.unroll_loops(unroll_limit(), &mut lev);
             path.topo_order(&mut lev);
             self.rcx_mut()
                 .mir_graph_mut()
@@ -82,6 +89,70 @@ impl<'tcx> NodeOrder<'tcx> {
         }
     }
 
+    /// Unroll the loops so that the graph is acyclic: the blocks of a loop are copied for each iteration, and a back
+    /// edge leads to the copy of the next iteration. The loop may be left after `times` iterations at most, as the
+    /// back edges of the copy after them are removed. The blocks out of any loop are not copied, so the exits of all
+    /// the iterations join there.
+    pub(crate) fn unroll_loops(&mut self, times: usize, lev: &mut Vec<usize>) {
+        let edges = self.graph().get_edges().clone();
+        let len = edges.len();
+        let mut finder = LoopFinder::new(len);
+        for block in 0..len {
+            if finder.dfn[block] == usize::MAX {
+                finder.visit(&edges, block);
+            }
+        }
+        if finder.back_edges.is_empty() {
+            return;
+        }
+
+        let mut graph = Graph::new(len);
+        let mut new_lev = vec![0; len];
+        // the nodes of the copies for the iterations after the first one.
+        let mut copies: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut worklist: Vec<(usize, usize)> = (0..len).rev().map(|block| (block, 0)).collect();
+        while let Some((block, iteration)) = worklist.pop() {
+            let node = match iteration {
+                0 => block,
+                _ => copies[&(block, iteration)],
+            };
+            for &next in edges[block].iter() {
+                let next_iteration = if finder.back_edges.contains(&(block, next)) {
+                    if iteration >= times {
+                        continue;
+                    }
+                    iteration + 1
+                } else if finder.scc[next] == finder.scc[block] {
+                    iteration
+                } else {
+                    0
+                };
+                let next_node = match next_iteration {
+                    0 => next,
+                    _ => *copies.entry((next, next_iteration)).or_insert_with(|| {
+                        graph.e.push(Vec::new());
+                        graph.pre.push(Vec::new());
+                        graph.block.push(next);
+                        new_lev.push(0);
+                        worklist.push((next, next_iteration));
+                        graph.block.len() - 1
+                    }),
+                };
+                graph.e[node].push(next_node);
+                graph.pre[next_node].push(node);
+                new_lev[next_node] += 1;
+            }
+        }
+
+        let mut loops: Vec<usize> = finder.back_edges.iter().map(|edge| edge.1).collect();
+        loops.sort_unstable();
+        loops.dedup();
+        graph.loops = loops;
+
+        *self.graph_mut() = graph;
+        *lev = new_lev;
+    }
+
     pub(crate) fn topo_order(&mut self, lev: &mut Vec<usize>) {
         let mut q: BinaryHeap<usize> = BinaryHeap::new();
         q.push(0);
@@ -98,3 +169,62 @@ impl<'tcx> NodeOrder<'tcx> {
         }
     }
 }
+
+// The SCCs and the back edges of the graph found by Tarjan's algorithm; an edge is a back edge if it leads to a block
+// on the current path of the depth-first search.
+struct LoopFinder {
+    dfn: Vec<usize>,
+    low: Vec<usize>,
+    time: usize,
+    stack: Vec<usize>,
+    in_stack: Vec<bool>,
+    on_path: Vec<bool>,
+    scc: Vec<usize>,
+    back_edges: HashSet<(usize, usize)>,
+}
+
+impl LoopFinder {
+    fn new(len: usize) -> Self {
+        Self {
+            dfn: vec![usize::MAX; len],
+            low: vec![usize::MAX; len],
+            time: 0,
+            stack: Vec::new(),
+            in_stack: vec![false; len],
+            on_path: vec![false; len],
+            scc: vec![usize::MAX; len],
+            back_edges: HashSet::new(),
+        }
+    }
+
+    fn visit(&mut self, edges: &Edges, index: usize) {
+        self.dfn[index] = self.time;
+        self.low[index] = self.time;
+        self.time += 1;
+        self.stack.push(index);
+        self.in_stack[index] = true;
+        self.on_path[index] = true;
+        for &target in edges[index].iter() {
+            if self.on_path[target] {
+                self.back_edges.insert((index, target));
+            }
+            if self.dfn[target] == usize::MAX {
+                self.visit(edges, target);
+                self.low[index] = self.low[index].min(self.low[target]);
+            } else if self.in_stack[target] {
+                self.low[index] = self.low[index].min(self.dfn[target]);
+            }
+        }
+        self.on_path[index] = false;
+        // generate SCC
+        if self.dfn[index] == self.low[index] {
+            while let Some(node) = self.stack.pop() {
+                self.in_stack[node] = false;
+                self.scc[node] = index;
+                if node == index {
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fmt::Debug;
 use z3::ast;
@@ -45,6 +46,26 @@ impl<'tcx> Taint<'tcx> {
     }
 }
 
+/// The ownership summary of a function, which is applied at its call sites instead of the default
+/// constraints of a call.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FnOwnership {
+    /// Whether the returned pointer owns heap items, e.g., through `Box::into_raw`.
+    pub returned: bool,
+    /// The indexes of the pointer arguments whose heap items are taken over, e.g., through `Box::from_raw`,
+    /// and then freed or kept by the function.
+    pub taken: Vec<usize>,
+    /// The indexes of the owning arguments whose heap items are leaked by the function, e.g., turned into a pointer
+    /// by `Box::into_raw` that is never freed.
+    pub leaked: Vec<usize>,
+}
+
+impl FnOwnership {
+    pub fn is_trivial(&self) -> bool {
+        !self.returned && self.taken.is_empty() && self.leaked.is_empty()
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub enum IntraVar<'ctx> {
     Declared,
@@ -1,11 +1,16 @@
 pub mod alias;
 pub mod bug_records;
 pub mod check_bugs;
+pub mod constraint;
 pub mod corner_handle;
+pub mod dangling;
+pub mod fixpoint;
 pub mod graph;
 pub mod safedrop;
+pub mod trace;
 pub mod types;
 
+use rustc_data_structures::fx::FxHashSet;
 use rustc_hir::def_id::DefId;
 use rustc_middle::ty::TyCtxt;
 
@@ -13,8 +18,11 @@ use crate::analysis::core::alias::mop::MopAlias;
 use crate::analysis::core::alias::FnMap;
 use crate::analysis::core::heap_item::{AdtOwner, TypeAnalysis};
 use crate::analysis::rcanary::rCanary;
+use crate::utils::cache;
+use crate::utils::report::{self, Detector};
+use crate::{rap_debug, rap_warn};
+use dangling::{callee_dangling, callees, DanglingMap, FnDangling};
 use graph::SafeDropGraph;
-use safedrop::*;
 
 pub struct SafeDrop<'tcx> {
     pub tcx: TyCtxt<'tcx>,
@@ -32,39 +40,122 @@ impl<'tcx> SafeDrop<'tcx> {
         let rcx = Box::leak(rcx_boxed);
         TypeAnalysis::new(rcx).start();
 
+        let summary_key = cache::summary_key(self.tcx, Some(fn_map), Some(rcx.adt_owner()), None);
+        let mut dangling_map = DanglingMap::default();
+        let mut visited = FxHashSet::default();
         for local_def_id in self.tcx.iter_local_def_id() {
-            let hir_map = self.tcx.hir();
-            if hir_map.maybe_body_owned_by(local_def_id).is_some() {
-                query_safedrop(
-                    self.tcx,
-                    fn_map,
+            if self.tcx.hir().maybe_body_owned_by(local_def_id).is_some() {
+                self.visit(
                     local_def_id.to_def_id(),
-                    rcx.adt_owner().clone(),
+                    fn_map,
+                    rcx.adt_owner(),
+                    &summary_key,
+                    &mut visited,
+                    &mut dangling_map,
                 );
             }
         }
     }
+
+    // check the function after its callees, so that the pointers they leave dangling are known at its call
+    // sites; functions in a recursion only see the pointers found before the recursion is entered.
+    fn visit(
+        &self,
+        def_id: DefId,
+        fn_map: &FnMap,
+        adt_owner: &AdtOwner,
+        summary_key: &str,
+        visited: &mut FxHashSet<DefId>,
+        dangling_map: &mut DanglingMap,
+    ) {
+        let tcx = self.tcx;
+        if !def_id.is_local() || !visited.insert(def_id) {
+            return;
+        }
+        /* filter const mir */
+        if tcx
+            .hir()
+            .body_const_context(def_id.expect_local())
+            .is_some()
+            || !tcx.is_mir_available(def_id)
+        {
+            return;
+        }
+        let body = tcx.optimized_mir(def_id);
+        for callee in callees(tcx, body) {
+            self.visit(
+                callee,
+                fn_map,
+                adt_owner,
+                summary_key,
+                visited,
+                dangling_map,
+            );
+        }
+        let callee_dangling = callee_dangling(tcx, body, dangling_map);
+        // the functions out of the scope are only checked for the pointers they leave dangling.
+        let dangling = match report::in_scope(tcx, def_id) {
+            true => {
+                let callee_key = cache::summary_key(tcx, None, None, Some(&callee_dangling));
+                let key = format!("{summary_key}{callee_key}");
+                cache::with_findings(tcx, Detector::SafeDrop, def_id, &key, || {
+                    query_safedrop(
+                        tcx,
+                        fn_map,
+                        callee_dangling,
+                        def_id,
+                        adt_owner.clone(),
+                        true,
+                    )
+                })
+            }
+            false => query_safedrop(
+                tcx,
+                fn_map,
+                callee_dangling,
+                def_id,
+                adt_owner.clone(),
+                false,
+            ),
+        };
+        if !dangling.is_empty() {
+            rap_debug!(
+                "Pointers left dangling by {}: {:?}",
+                tcx.def_path_str(def_id),
+                dangling
+            );
+            dangling_map.insert(def_id, dangling);
+        }
+    }
 }
 
+/// Check the function for bugs, which are reported if `report` is set, and return the pointers it leaves
+/// dangling.
 pub fn query_safedrop<'tcx>(
     tcx: TyCtxt<'tcx>,
     fn_map: &FnMap,
+    callee_dangling: DanglingMap,
     def_id: DefId,
     adt_owner: AdtOwner,
-) -> () {
-    /* filter const mir */
-    if let Some(_other) = tcx.hir().body_const_context(def_id.expect_local()) {
-        return;
-    }
-    if tcx.is_mir_available(def_id) {
-        let body = tcx.optimized_mir(def_id);
-        let mut safedrop_graph = SafeDropGraph::new(&body, tcx, def_id, adt_owner);
-        safedrop_graph.solve_scc();
-        safedrop_graph.check(0, tcx, fn_map);
-        if safedrop_graph.visit_times <= VISIT_LIMIT {
-            safedrop_graph.report_bugs();
-        } else {
-            println!("Over visited: {:?}", def_id);
+    report: bool,
+) -> FnDangling {
+    let body = tcx.optimized_mir(def_id);
+    let mut safedrop_graph = SafeDropGraph::new(&body, tcx, def_id, adt_owner);
+    safedrop_graph.callee_dangling = callee_dangling;
+    safedrop_graph.solve_scc();
+    let complete = safedrop_graph.check_paths(tcx, fn_map);
+    if report {
+        let loss = safedrop_graph.precision_loss;
+        // nothing is lost if no paths are joined and no loops are widened.
+        if !complete && (loss.merged > 0 || loss.widened > 0) {
+            rap_warn!(
+                "Paths of {} exceed the visit limit: they are joined {} times and {} loops are widened, so bugs may be missed.",
+                tcx.def_path_str(def_id),
+                loss.merged,
+                loss.widened
+            );
         }
+        safedrop_graph.report_bugs();
     }
+    safedrop_graph.dangling
 }
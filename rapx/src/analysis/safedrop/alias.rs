@@ -1,17 +1,23 @@
-use rustc_middle::mir::{Operand, Place, ProjectionElem, TerminatorKind};
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir::{Operand, Place, ProjectionElem, SourceInfo, TerminatorKind};
 use rustc_middle::ty;
 use rustc_middle::ty::TyCtxt;
 
 use super::graph::*;
+use super::trace::{AliasSource, TraceEvent};
 use super::types::*;
-use crate::analysis::core::alias::{FnMap, RetAlias};
+use crate::analysis::core::alias::closure::resolve_call;
+use crate::analysis::core::alias::container::{container_ops, ContainerOp, ELEMENT};
+use crate::analysis::core::alias::{FnMap, RetAlias, WILDCARD};
+use crate::analysis::utils::intrinsic_id::{is_allocator, is_intrinsic, Intrinsic};
 use crate::rap_error;
 
 impl<'tcx> SafeDropGraph<'tcx> {
     /* alias analysis for a single block */
     pub fn alias_bb(&mut self, bb_index: usize, tcx: TyCtxt<'tcx>) {
-        for stmt in self.blocks[bb_index].const_value.clone() {
-            self.constant.insert(stmt.0, stmt.1);
+        for (place, value) in self.blocks[bb_index].const_value.clone() {
+            let node = self.projection(tcx, false, place);
+            self.constant.insert(node, value);
         }
         let cur_block = self.blocks[bb_index].clone();
         for assign in cur_block.assignments {
@@ -27,15 +33,28 @@ impl<'tcx> SafeDropGraph<'tcx> {
                 }
                 _ => {} // Copy or Move
             }
-            self.uaf_check(
-                rv_aliaset_idx,
-                assign.span,
-                assign.rv.local.as_usize(),
-                false,
-            );
-            self.fill_birth(lv_aliaset_idx, self.scc_indices[bb_index] as isize);
+            // the destination of `ptr::write` may point to a dropped place, which is initialized again.
+            if assign.atype != AssignType::WriteDest {
+                self.uaf_check(
+                    rv_aliaset_idx,
+                    assign.span,
+                    assign.rv.local.as_usize(),
+                    false,
+                );
+            }
+            self.fill_birth(lv_aliaset_idx, self.block_birth(bb_index) as isize);
+            self.record(TraceEvent::Birth {
+                node: lv_aliaset_idx,
+                span: assign.span,
+            });
             if self.values[lv_aliaset_idx].local != self.values[rv_aliaset_idx].local {
                 self.merge_alias(lv_aliaset_idx, rv_aliaset_idx);
+                self.record(TraceEvent::Alias {
+                    lv: lv_aliaset_idx,
+                    rv: rv_aliaset_idx,
+                    span: assign.span,
+                    source: AliasSource::Assign,
+                });
             }
         }
     }
@@ -56,51 +75,108 @@ impl<'tcx> SafeDropGraph<'tcx> {
             {
                 if let Operand::Constant(ref constant) = func {
                     let lv = self.projection(tcx, false, destination.clone());
-                    self.values[lv].birth = self.scc_indices[bb_index] as isize;
+                    self.values[lv].birth = self.block_birth(bb_index) as isize;
+                    self.record(TraceEvent::Birth {
+                        node: lv,
+                        span: call.source_info.span,
+                    });
                     let mut merge_vec = Vec::new();
                     merge_vec.push(lv);
                     let mut may_drop_flag = 0;
                     if self.values[lv].may_drop {
                         may_drop_flag += 1;
                     }
-                    for arg in args {
-                        match arg.node {
-                            Operand::Copy(ref p) => {
-                                let rv = self.projection(tcx, true, p.clone());
-                                self.uaf_check(rv, call.source_info.span, p.local.as_usize(), true);
-                                merge_vec.push(rv);
-                                if self.values[rv].may_drop {
-                                    may_drop_flag += 1;
+                    if let ty::FnDef(ref target_id, generic_args) = constant.const_.ty().kind() {
+                        // calls of closures and coroutines run their bodies.
+                        let (callee, places) =
+                            resolve_call(tcx, self.body(), *target_id, generic_args, args);
+                        // ptr::write initializes the pointee of its first argument again.
+                        let is_write = is_intrinsic(*target_id, Intrinsic::Write);
+                        for (i, place) in places.into_iter().enumerate() {
+                            match place {
+                                Some(p) => {
+                                    let rv = self.projection(tcx, true, p);
+                                    if !(is_write && i == 0) {
+                                        self.uaf_check(
+                                            rv,
+                                            call.source_info.span,
+                                            p.local.as_usize(),
+                                            true,
+                                        );
+                                    }
+                                    merge_vec.push(rv);
+                                    if self.values[rv].may_drop {
+                                        may_drop_flag += 1;
+                                    }
                                 }
-                            }
-                            Operand::Move(ref p) => {
-                                let rv = self.projection(tcx, true, p.clone());
-                                self.uaf_check(rv, call.source_info.span, p.local.as_usize(), true);
-                                merge_vec.push(rv);
-                                if self.values[rv].may_drop {
-                                    may_drop_flag += 1;
+                                None => {
+                                    merge_vec.push(0);
                                 }
                             }
-                            Operand::Constant(_) => {
-                                merge_vec.push(0);
+                        }
+                        if is_write && merge_vec.len() > 1 {
+                            self.fill_birth(merge_vec[1], self.block_birth(bb_index) as isize);
+                            continue;
+                        }
+                        // the pointer returned by an allocator is a new allocation
+                        if is_allocator(*target_id) {
+                            continue;
+                        }
+                        if let Some(ops) = container_ops(*target_id, generic_args) {
+                            self.container_call(
+                                ops,
+                                &merge_vec,
+                                &call.source_info,
+                                *target_id,
+                                bb_index,
+                            );
+                            continue;
+                        }
+                        // the returned value and the pointee of the argument own the same heap item
+                        if is_intrinsic(*target_id, Intrinsic::Read) {
+                            if merge_vec.len() > 1 && self.values[lv].may_drop {
+                                self.merge_alias(lv, merge_vec[1]);
+                                self.record(TraceEvent::Alias {
+                                    lv,
+                                    rv: merge_vec[1],
+                                    span: call.source_info.span,
+                                    source: AliasSource::Duplicate(*target_id),
+                                });
                             }
+                            continue;
+                        }
+                        // copying values that own no heap item aliases nothing.
+                        if is_intrinsic(*target_id, Intrinsic::Copy)
+                            && is_not_drop(tcx, generic_args.type_at(0))
+                        {
+                            continue;
                         }
-                    }
-                    if let ty::FnDef(ref target_id, _) = constant.const_.ty().kind() {
                         if may_drop_flag > 1 {
-                            if tcx.is_mir_available(*target_id) {
-                                if fn_map.contains_key(&target_id) {
-                                    let assignments = fn_map.get(&target_id).unwrap();
+                            // Callees in dependencies may only have summaries.
+                            if tcx.is_mir_available(callee) || fn_map.contains_key(&callee) {
+                                if fn_map.contains_key(&callee) {
+                                    let assignments = fn_map.get(&callee).unwrap();
                                     for assign in assignments.aliases().iter() {
                                         if !assign.valuable() {
                                             continue;
                                         }
                                         self.merge(assign, &merge_vec);
+                                        if let (Some(lv), Some(rv)) = (
+                                            merge_vec.get(assign.left_index),
+                                            merge_vec.get(assign.right_index),
+                                        ) {
+                                            self.record(TraceEvent::Alias {
+                                                lv: *lv,
+                                                rv: *rv,
+                                                span: call.source_info.span,
+                                                source: AliasSource::Summary(callee),
+                                            });
+                                        }
                                     }
                                 }
                             } else {
                                 if self.values[lv].may_drop {
-                                    if self.corner_handle(lv, &merge_vec, *target_id) {
+                                    if self.corner_handle(lv, &merge_vec, callee) {
                                         continue;
                                     }
                                     let mut right_set = Vec::new();
@@ -114,6 +190,12 @@ impl<'tcx> SafeDropGraph<'tcx> {
                                     }
                                     if right_set.len() == 1 {
                                         self.merge_alias(lv, right_set[0]);
+                                        self.record(TraceEvent::Alias {
+                                            lv,
+                                            rv: right_set[0],
+                                            span: call.source_info.span,
+                                            source: AliasSource::Call(callee),
+                                        });
                                     }
                                 }
                             }
@@ -124,6 +206,167 @@ impl<'tcx> SafeDropGraph<'tcx> {
         }
     }
 
+    // apply the built-in summary of a std collection, `Option` or `Box`.
+    pub fn container_call(
+        &mut self,
+        ops: &[ContainerOp],
+        merge_vec: &[usize],
+        info: &SourceInfo,
+        callee: DefId,
+        bb_index: usize,
+    ) {
+        if merge_vec.len() < 2 {
+            return;
+        }
+        let (ret, container) = (merge_vec[0], merge_vec[1]);
+        for op in ops {
+            let (lv, rv) = match *op {
+                ContainerOp::Insert(arg) => match merge_vec.get(arg) {
+                    Some(&value) if self.values[value].may_drop => (self.element(container), value),
+                    _ => continue,
+                },
+                ContainerOp::Remove => {
+                    if !self.values[ret].may_drop {
+                        continue;
+                    }
+                    let element = self.element(container);
+                    self.detach_element(container);
+                    (ret, element)
+                }
+                ContainerOp::Borrow => {
+                    if !self.values[ret].may_drop {
+                        continue;
+                    }
+                    (ret, self.element(container))
+                }
+                ContainerOp::Clear => {
+                    let element = self.element(container);
+                    self.detach_element(container);
+                    self.dead_node(element, self.call_birth(bb_index), info, true);
+                    continue;
+                }
+                ContainerOp::Alias => {
+                    if !self.values[ret].may_drop || !self.values[container].may_drop {
+                        continue;
+                    }
+                    (ret, container)
+                }
+                ContainerOp::New(arg) => match merge_vec.get(arg) {
+                    Some(&value) if self.values[value].may_drop => (self.element(ret), value),
+                    _ => continue,
+                },
+                ContainerOp::Share => {
+                    let element = self.element(container);
+                    self.link_element(ret, element);
+                    self.record(TraceEvent::Alias {
+                        lv: ret,
+                        rv: element,
+                        span: info.span,
+                        source: AliasSource::Summary(callee),
+                    });
+                    continue;
+                }
+                ContainerOp::Release => {
+                    if !self.values[ret].may_drop {
+                        continue;
+                    }
+                    let element = self.element(container);
+                    self.detach_element(container);
+                    (ret, element)
+                }
+                ContainerOp::Adopt => {
+                    if !self.values[container].may_drop {
+                        continue;
+                    }
+                    self.link_element(ret, container);
+                    self.record(TraceEvent::Alias {
+                        lv: ret,
+                        rv: container,
+                        span: info.span,
+                        source: AliasSource::Summary(callee),
+                    });
+                    continue;
+                }
+            };
+            self.merge_alias(lv, rv);
+            self.record(TraceEvent::Alias {
+                lv,
+                rv,
+                span: info.span,
+                source: AliasSource::Summary(callee),
+            });
+        }
+    }
+
+    /*
+     * The node of the elements of a container, which is shared by all the aliases of the container.
+     * It is named after the smallest local among the aliases and born with the oldest one, so that the elements of an argument
+     * can be found after they are moved out of the argument.
+     */
+    pub fn element(&mut self, container: usize) -> usize {
+        let mut aliases = Vec::new();
+        for i in 0..self.alias_set.len() {
+            if self.union_is_same(i, container) {
+                aliases.push(i);
+            }
+        }
+        let element = match aliases
+            .iter()
+            .find_map(|alias| self.values[*alias].fields.get(&ELEMENT).copied())
+        {
+            Some(element) => element,
+            None => {
+                let father = aliases
+                    .iter()
+                    .copied()
+                    .min_by_key(|alias| self.values[*alias].local)
+                    .unwrap_or(container);
+                let local = self.values[father].local;
+                let mut node = ValueNode::new(self.values.len(), local, true, true);
+                node.father = father;
+                // the elements live as long as the container, not the reference to it.
+                node.birth = aliases
+                    .iter()
+                    .map(|alias| self.values[*alias].birth)
+                    .filter(|birth| *birth >= 0)
+                    .min()
+                    .unwrap_or(self.values[container].birth);
+                node.field_id = ELEMENT;
+                self.alias_set.push(self.values.len());
+                self.dead_record.push(false);
+                self.values.push(node);
+                self.values.len() - 1
+            }
+        };
+        self.link_element(container, element);
+        element
+    }
+
+    // make the node the elements of all the aliases of the container.
+    pub fn link_element(&mut self, container: usize, element: usize) {
+        for alias in 0..self.alias_set.len() {
+            if !self.union_is_same(alias, container) {
+                continue;
+            }
+            match self.values[alias].fields.get(&ELEMENT).copied() {
+                Some(other) if other != element => self.merge_alias(element, other),
+                Some(_) => {}
+                None => {
+                    self.values[alias].fields.insert(ELEMENT, element);
+                }
+            }
+        }
+    }
+
+    // the elements moved out of a container are no longer dropped with the container.
+    pub fn detach_element(&mut self, container: usize) {
+        for i in 0..self.alias_set.len() {
+            if self.union_is_same(i, container) {
+                self.values[i].fields.remove(&ELEMENT);
+            }
+        }
+    }
+
     // assign to the variable _x, we will set the birth of _x and its child self.values a new birth.
     pub fn fill_birth(&mut self, node: usize, birth: isize) {
         self.values[node].birth = birth;
@@ -144,9 +387,11 @@ impl<'tcx> SafeDropGraph<'tcx> {
      *
      */
     pub fn projection(&mut self, tcx: TyCtxt<'tcx>, is_right: bool, place: Place<'tcx>) -> usize {
+        let place = self.deref_copy(tcx, place);
         let mut local = place.local.as_usize();
         let mut proj_id = local;
-        for proj in place.projection {
+        let mut variant = None;
+        for (base, proj) in place.iter_projections() {
             let new_id = self.values.len();
             match proj {
                 ProjectionElem::Deref => {
@@ -161,15 +406,21 @@ impl<'tcx> SafeDropGraph<'tcx> {
                         proj_id = self.alias_set[proj_id];
                         local = self.values[proj_id].local;
                     }
-                    let field_idx = field.as_usize();
+                    let field_idx = match (variant.take(), &self.coroutine) {
+                        (Some(variant), Some(coroutine)) => {
+                            coroutine.field(variant, field.as_usize())
+                        }
+                        _ => field.as_usize(),
+                    };
                     if !self.values[proj_id].fields.contains_key(&field_idx) {
                         let param_env = tcx.param_env(self.def_id);
                         let need_drop = ty.needs_drop(tcx, param_env);
                         let may_drop = !is_not_drop(tcx, ty);
                         let mut node =
                             ValueNode::new(new_id, local, need_drop, need_drop || may_drop);
-                        node.kind = kind(ty);
+                        node.kind = kind(tcx, ty);
                         node.birth = self.values[proj_id].birth;
+                        node.father = proj_id;
                         node.field_id = field_idx;
                         self.values[proj_id].fields.insert(field_idx, node.index);
                         self.alias_set.push(self.values.len());
@@ -178,12 +429,26 @@ impl<'tcx> SafeDropGraph<'tcx> {
                     }
                     proj_id = *self.values[proj_id].fields.get(&field_idx).unwrap();
                 }
+                // the fields of the variants of a coroutine are numbered by its saved locals.
+                ProjectionElem::Downcast(_, index) => {
+                    if let Some(coroutine) = &self.coroutine {
+                        variant = coroutine.downcast(tcx, self.body(), base, index);
+                    }
+                }
                 _ => {}
             }
         }
         return proj_id;
     }
 
+    // the place behind the pointer copied into a `deref_copy` temporary.
+    pub fn deref_copy(&self, tcx: TyCtxt<'tcx>, mut place: Place<'tcx>) -> Place<'tcx> {
+        while let Some(base) = self.deref_copies.get(&place.local.as_usize()) {
+            place = base.project_deeper(place.projection, tcx);
+        }
+        place
+    }
+
     //instruction to assign alias for a variable.
     pub fn merge_alias(&mut self, lv: usize, rv: usize) {
         // if self.values[lv].alias.len() > 1 {
@@ -204,6 +469,7 @@ impl<'tcx> SafeDropGraph<'tcx> {
                 );
                 node.kind = self.values[field.1].kind;
                 node.birth = self.values[lv].birth;
+                node.father = lv;
                 node.field_id = field.0;
                 self.values[lv].fields.insert(field.0, node.index);
                 self.alias_set.push(self.values.len());
@@ -223,15 +489,21 @@ impl<'tcx> SafeDropGraph<'tcx> {
         }
         let left_init = arg_vec[ret_alias.left_index];
         let mut right_init = arg_vec[ret_alias.right_index];
-        let mut lv = left_init;
-        let mut rv = right_init;
+        let mut lv = self.field_base(left_init, &ret_alias.left_field_seq);
+        let mut rv = self.field_base(right_init, &ret_alias.right_field_seq);
         for index in ret_alias.left_field_seq.iter() {
+            // a wildcard stands for the field reached so far and all the fields nested in it.
+            if *index == WILDCARD {
+                break;
+            }
             if self.values[lv].fields.contains_key(&index) == false {
                 let need_drop = ret_alias.left_need_drop;
                 let may_drop = ret_alias.left_may_drop;
-                let mut node = ValueNode::new(self.values.len(), left_init, need_drop, may_drop);
+                let local = self.values[lv].local;
+                let mut node = ValueNode::new(self.values.len(), local, need_drop, may_drop);
                 node.kind = TyKind::RawPtr;
                 node.birth = self.values[lv].birth;
+                node.father = lv;
                 node.field_id = *index;
                 self.values[lv].fields.insert(*index, node.index);
                 self.alias_set.push(self.values.len());
@@ -241,6 +513,9 @@ impl<'tcx> SafeDropGraph<'tcx> {
             lv = *self.values[lv].fields.get(&index).unwrap();
         }
         for index in ret_alias.right_field_seq.iter() {
+            if *index == WILDCARD {
+                break;
+            }
             // if self.values[rv].alias[0] != rv {
             if self.union_is_same(rv, self.alias_set[rv]) {
                 rv = self.values[rv].index;
@@ -252,6 +527,7 @@ impl<'tcx> SafeDropGraph<'tcx> {
                 let mut node = ValueNode::new(self.values.len(), right_init, need_drop, may_drop);
                 node.kind = TyKind::RawPtr;
                 node.birth = self.values[rv].birth;
+                node.father = rv;
                 node.field_id = *index;
                 self.values[rv].fields.insert(*index, node.index);
                 self.alias_set.push(self.values.len());
@@ -263,6 +539,14 @@ impl<'tcx> SafeDropGraph<'tcx> {
         self.merge_alias(lv, rv);
     }
 
+    // the fields of a summary are those of the pointee if the argument is a pointer, as for a `Deref`.
+    pub fn field_base(&self, node: usize, field_seq: &[usize]) -> usize {
+        match field_seq.is_empty() || !self.values[node].is_ptr() {
+            true => node,
+            false => self.alias_set[node],
+        }
+    }
+
     #[inline]
     pub fn union_find(&mut self, e: usize) -> usize {
         let mut r = e;
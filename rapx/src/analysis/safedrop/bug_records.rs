@@ -1,24 +1,20 @@
-use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_data_structures::fx::FxHashMap;
+use rustc_hir::def_id::DefId;
 use rustc_span::Span;
 
-use crate::rap_warn;
-use crate::utils::log::are_spans_in_same_file;
+use crate::utils::report::{self, Finding, FindingKind, Trace};
 use rustc_span::symbol::Symbol;
 
-use annotate_snippets::Level;
-use annotate_snippets::Renderer;
-use annotate_snippets::Snippet;
-
-use crate::utils::log::{
-    relative_pos_range, span_to_filename, span_to_line_number, span_to_source_code,
-};
-
+/// The bugs of a function, each with the trace of the path where it is first found.
+#[derive(Clone)]
 pub struct BugRecords {
-    pub df_bugs: FxHashMap<usize, Span>,
-    pub df_bugs_unwind: FxHashMap<usize, Span>,
-    pub uaf_bugs: FxHashSet<Span>,
-    pub dp_bugs: FxHashSet<Span>,
-    pub dp_bugs_unwind: FxHashSet<Span>,
+    pub df_bugs: FxHashMap<usize, (Span, Trace)>,
+    pub df_bugs_unwind: FxHashMap<usize, (Span, Trace)>,
+    pub uaf_bugs: FxHashMap<Span, Trace>,
+    pub dp_bugs: FxHashMap<Span, Trace>,
+    pub dp_bugs_unwind: FxHashMap<Span, Trace>,
+    // whether the paths of the function are merged, so the traces may not be feasible paths.
+    pub merged: bool,
 }
 
 impl BugRecords {
@@ -26,9 +22,10 @@ impl BugRecords {
         BugRecords {
             df_bugs: FxHashMap::default(),
             df_bugs_unwind: FxHashMap::default(),
-            uaf_bugs: FxHashSet::default(),
-            dp_bugs: FxHashSet::default(),
-            dp_bugs_unwind: FxHashSet::default(),
+            uaf_bugs: FxHashMap::default(),
+            dp_bugs: FxHashMap::default(),
+            dp_bugs_unwind: FxHashMap::default(),
+            merged: false,
         }
     }
 
@@ -39,109 +36,66 @@ impl BugRecords {
             && self.dp_bugs_unwind.is_empty()
     }
 
-    pub fn df_bugs_output(&self, fn_name: Symbol, span: Span) {
-        if !self.df_bugs.is_empty() {
-            rap_warn!("Double free detected in function {:}", fn_name);
-            let code_source = span_to_source_code(span);
-            let filename = span_to_filename(span);
-            let mut snippet = Snippet::source(&code_source)
-                .line_start(span_to_line_number(span))
-                .origin(&filename)
-                .fold(true);
-            for i in self.df_bugs.iter() {
-                //todo: remove this condition
-                if are_spans_in_same_file(span, *i.1) {
-                    snippet = snippet.annotation(
-                        Level::Warning
-                            .span(unsafe { relative_pos_range(span, *i.1) })
-                            .label("Double free detected."),
-                    );
-                }
-            }
-            let message = Level::Warning
-                .title("Double free detected.")
-                .snippet(snippet);
-            let renderer = Renderer::styled();
-            println!("{}", renderer.render(message));
+    fn emit(&self, mut finding: Finding) {
+        if self.merged {
+            finding = finding.help(
+                "The paths of the function exceed `limits.safedrop-visit` and are merged, so the path may be infeasible.",
+            );
         }
+        report::emit(finding);
     }
 
-    pub fn uaf_bugs_output(&self, fn_name: Symbol, span: Span) {
-        if !self.uaf_bugs.is_empty() {
-            rap_warn!("Use after free detected in function {:?}", fn_name);
-            let code_source = span_to_source_code(span);
-            let filename = span_to_filename(span);
-            let mut snippet = Snippet::source(&code_source)
-                .line_start(span_to_line_number(span))
-                .origin(&filename)
-                .fold(true);
-            for i in self.uaf_bugs.iter() {
-                //todo: remove this condition
-                if are_spans_in_same_file(span, *i) {
-                    snippet = snippet.annotation(
-                        Level::Warning
-                            .span(unsafe { relative_pos_range(span, *i) })
-                            .label("Use after free detected."),
-                    );
-                }
-            }
-            let message = Level::Warning
-                .title("Use after free detected.")
-                .snippet(snippet);
-            let renderer = Renderer::styled();
-            println!("{}", renderer.render(message));
+    pub fn df_bugs_output(&self, def_id: DefId, fn_name: Symbol, span: Span) {
+        for (bug_span, trace) in sorted(self.df_bugs.values().map(|(span, trace)| (span, trace))) {
+            let finding = Finding::new(FindingKind::DoubleFree, def_id, span)
+                .summary(format!("Double free detected in function {:}", fn_name))
+                .message("Double free detected.")
+                .traced(*bug_span, "Double free detected.", trace.clone());
+            self.emit(finding);
         }
     }
 
-    pub fn dp_bug_output(&self, fn_name: Symbol, span: Span) {
-        let code_source = span_to_source_code(span);
-        let filename = span_to_filename(span);
-        if !self.dp_bugs.is_empty() {
-            rap_warn!("Dangling pointer detected in function {:?}", fn_name);
-            let mut snippet = Snippet::source(&code_source)
-                .line_start(span_to_line_number(span))
-                .origin(&filename)
-                .fold(true);
-            for i in self.dp_bugs.iter() {
-                //todo: remove this condition
-                if are_spans_in_same_file(span, *i) {
-                    snippet = snippet.annotation(
-                        Level::Warning
-                            .span(unsafe { relative_pos_range(span, *i) })
-                            .label("Dangling pointer detected."),
-                    );
-                }
-            }
-            let message = Level::Warning
-                .title("Dangling pointer detected.")
-                .snippet(snippet);
-            let renderer = Renderer::styled();
-            println!("{}", renderer.render(message));
+    pub fn uaf_bugs_output(&self, def_id: DefId, fn_name: Symbol, span: Span) {
+        for (bug_span, trace) in sorted(self.uaf_bugs.iter()) {
+            let finding = Finding::new(FindingKind::UseAfterFree, def_id, span)
+                .summary(format!("Use after free detected in function {:?}", fn_name))
+                .message("Use after free detected.")
+                .traced(*bug_span, "Use after free detected.", trace.clone());
+            self.emit(finding);
         }
-        if !self.dp_bugs_unwind.is_empty() {
-            rap_warn!(
-                "Dangling pointer detected in function {:?} during unwinding.",
-                fn_name
-            );
-            let mut snippet = Snippet::source(&code_source)
-                .line_start(span_to_line_number(span))
-                .origin(&filename)
-                .fold(true);
-            for i in self.dp_bugs_unwind.iter() {
-                //todo: remove this condition
-                if are_spans_in_same_file(span, *i) {
-                    snippet = snippet.annotation(
-                        Level::Warning
-                            .span(unsafe { relative_pos_range(span, *i) })
-                            .label("Dangling pointer detected during unwinding."),
-                    );
-                }
-            }
-            let message = Level::Warning
-                .title("Dangling pointer detected during unwinding.")
-                .snippet(snippet);
-            let renderer = Renderer::styled();
-            println!("{}", renderer.render(message));
+    }
+
+    pub fn dp_bug_output(&self, def_id: DefId, fn_name: Symbol, span: Span) {
+        for (bug_span, trace) in sorted(self.dp_bugs.iter()) {
+            let finding = Finding::new(FindingKind::DanglingPointer, def_id, span)
+                .summary(format!(
+                    "Dangling pointer detected in function {:?}",
+                    fn_name
+                ))
+                .message("Dangling pointer detected.")
+                .traced(*bug_span, "Dangling pointer detected.", trace.clone());
+            self.emit(finding);
+        }
+        for (bug_span, trace) in sorted(self.dp_bugs_unwind.iter()) {
+            let finding = Finding::new(FindingKind::DanglingPointer, def_id, span)
+                .summary(format!(
+                    "Dangling pointer detected in function {:?} during unwinding.",
+                    fn_name
+                ))
+                .message("Dangling pointer detected during unwinding.")
+                .traced(
+                    *bug_span,
+                    "Dangling pointer detected during unwinding.",
+                    trace.clone(),
+                );
+            self.emit(finding);
         }
     }
 }
+
+// each bug site is reported as a finding of its own, in the order of the source code.
+fn sorted<'a>(bugs: impl Iterator<Item = (&'a Span, &'a Trace)>) -> Vec<(&'a Span, &'a Trace)> {
+    let mut bugs: Vec<_> = bugs.collect();
+    bugs.sort_by_key(|(span, _)| **span);
+    bugs
+}
@@ -1,4 +1,6 @@
 use super::graph::*;
+use super::trace::TraceEvent;
+use crate::analysis::core::alias::container::ELEMENT;
 use crate::utils::source::*;
 use rustc_data_structures::fx::FxHashSet;
 use rustc_middle::mir::SourceInfo;
@@ -19,25 +21,44 @@ impl<'tcx> SafeDropGraph<'tcx> {
         if self.bug_records.is_bug_free() {
             return;
         }
-        let fn_name = match get_name(self.tcx, self.def_id) {
+        // closures and coroutines are reported under the name of the function defining them.
+        let fn_name = match get_name(self.tcx, self.tcx.typeck_root_def_id(self.def_id)) {
             Some(name) => name,
             None => Symbol::intern("no symbol available"),
         };
-        self.bug_records.df_bugs_output(fn_name, self.span);
-        self.bug_records.uaf_bugs_output(fn_name, self.span);
-        self.bug_records.dp_bug_output(fn_name, self.span);
+        self.bug_records
+            .df_bugs_output(self.def_id, fn_name, self.span);
+        self.bug_records
+            .uaf_bugs_output(self.def_id, fn_name, self.span);
+        self.bug_records
+            .dp_bug_output(self.def_id, fn_name, self.span);
+    }
+
+    // the span of a bug in the source code of the function, e.g., the call of `println!` rather
+    // than the code it expands to, so that a bug in a macro is reported once at its call site.
+    pub fn bug_span(&self, span: Span) -> Span {
+        let callsite = span.source_callsite();
+        match self.span.contains(callsite) {
+            true => callsite,
+            false => span.find_ancestor_inside(self.span).unwrap_or(callsite),
+        }
     }
 
     pub fn uaf_check(&mut self, aliaset_idx: usize, span: Span, local: usize, is_func_call: bool) {
+        let span = self.bug_span(span);
         let mut record = FxHashSet::default();
         if self.values[aliaset_idx].may_drop
             && (!self.values[aliaset_idx].is_ptr()
                 || self.values[aliaset_idx].local != local
                 || is_func_call)
-            && self.exist_dead(aliaset_idx, &mut record, false)
-            && !self.bug_records.uaf_bugs.contains(&span)
+            // the expressions enclosing a use, e.g., the block of `unsafe { &*ptr }` and the macro
+            // call around it, use the same value, so only the first use found is reported.
+            && !self.bug_records.uaf_bugs.keys().any(|bug| bug.overlaps(span))
         {
-            self.bug_records.uaf_bugs.insert(span.clone());
+            if let Some(dead) = self.find_dead(aliaset_idx, &mut record, false) {
+                let trace = self.bug_trace(aliaset_idx, dead);
+                self.bug_records.uaf_bugs.insert(span, trace);
+            }
         }
     }
 
@@ -47,11 +68,21 @@ impl<'tcx> SafeDropGraph<'tcx> {
         record: &mut FxHashSet<usize>,
         dangling: bool,
     ) -> bool {
+        self.find_dead(node, record, dangling).is_some()
+    }
+
+    // find a dead node among the aliases and the fields of the node.
+    pub fn find_dead(
+        &mut self,
+        node: usize,
+        record: &mut FxHashSet<usize>,
+        dangling: bool,
+    ) -> Option<usize> {
         //if is a dangling pointer check, only check the pointer type varible.
         if self.values[node].is_alive() == false
             && (dangling && self.values[node].is_ptr() || !dangling)
         {
-            return true;
+            return Some(node);
         }
         record.insert(node);
         if self.union_has_alias(node) {
@@ -65,22 +96,35 @@ impl<'tcx> SafeDropGraph<'tcx> {
                 if i != node && !self.union_is_same(i, node) {
                     continue;
                 }
-                if record.contains(&i) == false && self.exist_dead(i, record, dangling) {
-                    return true;
+                if record.contains(&i) == false {
+                    if let Some(dead) = self.find_dead(i, record, dangling) {
+                        return Some(dead);
+                    }
                 }
             }
         }
         for i in self.values[node].fields.clone().into_iter() {
-            if record.contains(&i.1) == false && self.exist_dead(i.1, record, dangling) {
-                return true;
+            // a dropped element does not make the container dangling.
+            if i.0 == ELEMENT {
+                continue;
+            }
+            if record.contains(&i.1) == false {
+                if let Some(dead) = self.find_dead(i.1, record, dangling) {
+                    return Some(dead);
+                }
             }
         }
-        return false;
+        return None;
     }
 
     pub fn is_dangling(&mut self, local: usize) -> bool {
+        self.dangling_node(local).is_some()
+    }
+
+    // the dead node that the local points to, if any.
+    pub fn dangling_node(&mut self, local: usize) -> Option<usize> {
         let mut record = FxHashSet::default();
-        return self.exist_dead(local, &mut record, local != 0);
+        return self.find_dead(local, &mut record, local != 0);
     }
 
     pub fn df_check(&mut self, drop: usize, span: Span) -> bool {
@@ -88,7 +132,9 @@ impl<'tcx> SafeDropGraph<'tcx> {
         if self.values[drop].is_alive() == false
             && self.bug_records.df_bugs.contains_key(&root) == false
         {
-            self.bug_records.df_bugs.insert(root, span.clone());
+            let trace = self.bug_trace(drop, drop);
+            let span = self.bug_span(span);
+            self.bug_records.df_bugs.insert(root, (span, trace));
         }
         return self.values[drop].is_alive() == false;
     }
@@ -97,18 +143,28 @@ impl<'tcx> SafeDropGraph<'tcx> {
         match current_block.is_cleanup {
             true => {
                 for i in 0..self.arg_size {
-                    if self.values[i + 1].is_ptr() && self.is_dangling(i + 1) {
-                        self.bug_records.dp_bugs_unwind.insert(self.span);
+                    if self.values[i + 1].is_ptr() {
+                        if let Some(dead) = self.dangling_node(i + 1) {
+                            let trace = self.bug_trace(i + 1, dead);
+                            self.bug_records
+                                .dp_bugs_unwind
+                                .entry(self.span)
+                                .or_insert(trace);
+                        }
                     }
                 }
             }
             false => {
-                if self.values[0].may_drop && self.is_dangling(0) {
-                    self.bug_records.dp_bugs.insert(self.span);
+                if let Some(dead) = self.dangling_return() {
+                    let trace = self.bug_trace(0, dead);
+                    self.bug_records.dp_bugs.entry(self.span).or_insert(trace);
                 } else {
                     for i in 0..self.arg_size {
-                        if self.values[i + 1].is_ptr() && self.is_dangling(i + 1) {
-                            self.bug_records.dp_bugs.insert(self.span);
+                        if self.values[i + 1].is_ptr() {
+                            if let Some(dead) = self.dangling_node(i + 1) {
+                                let trace = self.bug_trace(i + 1, dead);
+                                self.bug_records.dp_bugs.entry(self.span).or_insert(trace);
+                            }
                         }
                     }
                 }
@@ -116,9 +172,16 @@ impl<'tcx> SafeDropGraph<'tcx> {
         }
     }
 
+    fn dangling_return(&mut self) -> Option<usize> {
+        if !self.values[0].may_drop {
+            return None;
+        }
+        self.dangling_node(0)
+    }
+
     pub fn dead_node(&mut self, drop: usize, birth: usize, info: &SourceInfo, alias: bool) {
-        //Rc drop
-        if self.values[drop].is_corner_case() {
+        // dropping a guard of RefCell only releases the borrow.
+        if self.values[drop].is_guard() {
             return;
         }
         //check if there is a double free bug.
@@ -131,7 +194,7 @@ impl<'tcx> SafeDropGraph<'tcx> {
             self.dead_record[drop] = true;
         }
         //drop their alias
-        if self.alias_set[drop] != drop {
+        if self.union_has_alias(drop) {
             // for i in self.values[drop].alias.clone().into_iter() {
             //     if self.values[i].is_ref() {
             //         continue;
@@ -152,22 +215,55 @@ impl<'tcx> SafeDropGraph<'tcx> {
                 if self.values[drop].is_tuple() == true && self.values[i.1].need_drop == false {
                     continue;
                 }
-                self.dead_node(i.1, birth, info, false);
+                // the payload of Rc is dropped with the last strong pointer to it.
+                if i.0 == ELEMENT && (self.values[drop].is_weak() || self.is_shared(drop, i.1)) {
+                    continue;
+                }
+                // the elements may have been dropped when they were moved out.
+                self.dead_node(i.1, birth, info, i.0 == ELEMENT);
             }
         }
         //SCC.
         if self.values[drop].birth < birth as isize && self.values[drop].may_drop {
             self.values[drop].dead();
+            self.record(TraceEvent::Drop {
+                node: drop,
+                span: info.span,
+                callee: None,
+            });
+        }
+    }
+
+    // whether another alive strong pointer shares the payload with the dropped one.
+    pub fn is_shared(&mut self, drop: usize, payload: usize) -> bool {
+        if !self.values[drop].is_counted() {
+            return false;
+        }
+        for i in 0..self.values.len() {
+            if !self.values[i].is_counted()
+                || !self.values[i].is_alive()
+                || self.union_is_same(i, drop)
+            {
+                continue;
+            }
+            if let Some(other) = self.values[i].fields.get(&ELEMENT).copied() {
+                if self.union_is_same(other, payload) {
+                    return true;
+                }
+            }
         }
+        false
     }
 
+    // the numbers of the fields from the local to the node.
     pub fn get_field_seq(&self, value: &ValueNode) -> Vec<usize> {
         let mut field_id_seq = vec![];
         let mut node_ref = value;
         while node_ref.field_id != usize::MAX {
             field_id_seq.push(node_ref.field_id);
-            node_ref = &self.values[value.father];
+            node_ref = &self.values[node_ref.father];
         }
+        field_id_seq.reverse();
         return field_id_seq;
     }
 }
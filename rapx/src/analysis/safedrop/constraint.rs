@@ -0,0 +1,452 @@
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_middle::mir::{
+    AggregateKind, BasicBlock, BinOp, Body, Operand, Rvalue, StatementKind, TerminatorKind, UnOp,
+};
+use rustc_middle::ty::{self, Ty, TyCtxt};
+use z3::ast::{self, Ast};
+
+use super::graph::*;
+use crate::rap_debug;
+
+/// The value of a scalar local, or of the discriminant of an enum, on the current path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    /// An unknown value, e.g., an argument or the return value of a call; `true` for booleans.
+    Symbol(usize, bool),
+    Int(i128),
+    Bool(bool),
+    Not(Box<Term>),
+    /// `Eq`, `Ne`, `Lt`, `Le`, `Gt` or `Ge` on integers, and `Eq` or `Ne` on booleans.
+    Cmp(BinOp, Box<Term>, Box<Term>),
+    /// `BitAnd`, `BitOr` or `BitXor` on booleans.
+    Logic(BinOp, Box<Term>, Box<Term>),
+}
+
+impl Term {
+    fn is_bool(&self) -> bool {
+        !matches!(self, Term::Int(_) | Term::Symbol(_, false))
+    }
+
+    fn symbols(&self, symbols: &mut Vec<usize>) {
+        match self {
+            Term::Symbol(symbol, _) => symbols.push(*symbol),
+            Term::Int(_) | Term::Bool(_) => {}
+            Term::Not(term) => term.symbols(symbols),
+            Term::Cmp(_, lhs, rhs) | Term::Logic(_, lhs, rhs) => {
+                lhs.symbols(symbols);
+                rhs.symbols(symbols);
+            }
+        }
+    }
+
+    /*
+     * Whether every comparison of the condition involves a symbol. Such a condition is satisfiable
+     * if its symbols occur nowhere else, as the terms of integers are only constants and symbols.
+     */
+    fn is_free(&self) -> bool {
+        match self {
+            Term::Symbol(..) => true,
+            Term::Int(_) | Term::Bool(_) => false,
+            Term::Not(term) => term.is_free(),
+            Term::Cmp(..) => {
+                let mut symbols = Vec::new();
+                self.symbols(&mut symbols);
+                !symbols.is_empty()
+            }
+            Term::Logic(_, lhs, rhs) => lhs.is_free() && rhs.is_free(),
+        }
+    }
+
+    fn encode_bool<'ctx>(&self, ctx: &'ctx z3::Context) -> ast::Bool<'ctx> {
+        match self {
+            Term::Symbol(symbol, _) => ast::Bool::new_const(ctx, format!("s{}", symbol)),
+            Term::Bool(value) => ast::Bool::from_bool(ctx, *value),
+            Term::Not(term) => term.encode_bool(ctx).not(),
+            Term::Cmp(op, lhs, rhs) if lhs.is_bool() => {
+                let eq = lhs.encode_bool(ctx)._eq(&rhs.encode_bool(ctx));
+                if *op == BinOp::Ne {
+                    eq.not()
+                } else {
+                    eq
+                }
+            }
+            Term::Cmp(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.encode_int(ctx), rhs.encode_int(ctx));
+                match op {
+                    BinOp::Ne => lhs._eq(&rhs).not(),
+                    BinOp::Lt => lhs.lt(&rhs),
+                    BinOp::Le => lhs.le(&rhs),
+                    BinOp::Gt => lhs.gt(&rhs),
+                    BinOp::Ge => lhs.ge(&rhs),
+                    _ => lhs._eq(&rhs),
+                }
+            }
+            Term::Logic(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.encode_bool(ctx), rhs.encode_bool(ctx));
+                match op {
+                    BinOp::BitAnd => ast::Bool::and(ctx, &[&lhs, &rhs]),
+                    BinOp::BitOr => ast::Bool::or(ctx, &[&lhs, &rhs]),
+                    _ => lhs.xor(&rhs),
+                }
+            }
+            Term::Int(_) => unreachable!("an integer is used as a condition"),
+        }
+    }
+
+    fn encode_int<'ctx>(&self, ctx: &'ctx z3::Context) -> ast::Int<'ctx> {
+        match self {
+            Term::Symbol(symbol, _) => ast::Int::new_const(ctx, format!("s{}", symbol)),
+            Term::Int(value) => ast::Int::from_str(ctx, &value.to_string()).unwrap(),
+            _ => unreachable!("a condition is used as an integer"),
+        }
+    }
+}
+
+/*
+ * The symbolic values of the scalar locals and the discriminants of the enum locals on the current
+ * path, and the conditions of the branches taken. Locals whose addresses are taken may be written
+ * through pointers, so they are unknown whenever they are read.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct PathCondition {
+    values: FxHashMap<usize, Term>,
+    discriminants: FxHashMap<usize, Term>,
+    conds: Vec<Term>,
+    symbols: usize,
+}
+
+impl PathCondition {
+    fn fresh(&mut self, is_bool: bool) -> Term {
+        self.symbols += 1;
+        Term::Symbol(self.symbols, is_bool)
+    }
+
+    fn forget(&mut self, local: usize) {
+        self.values.remove(&local);
+        self.discriminants.remove(&local);
+    }
+
+    /*
+     * Merge the condition of another path reaching the same block. Only the values equal on both
+     * paths and the conditions taken before the paths split are kept. A value equal on both paths
+     * holds on either path even if its symbols are created after the split.
+     */
+    pub fn join(&mut self, other: &PathCondition) {
+        self.values
+            .retain(|local, value| other.values.get(local) == Some(value));
+        self.discriminants
+            .retain(|local, value| other.discriminants.get(local) == Some(value));
+        let common = self
+            .conds
+            .iter()
+            .zip(other.conds.iter())
+            .take_while(|(cond, other)| cond == other)
+            .count();
+        self.conds.truncate(common);
+        self.symbols = self.symbols.max(other.symbols);
+    }
+}
+
+/// The locals whose addresses are taken, e.g., `&mut flag`.
+pub fn borrowed_locals(body: &Body<'_>) -> FxHashSet<usize> {
+    let mut borrowed = FxHashSet::default();
+    for data in body.basic_blocks.iter() {
+        for stmt in &data.statements {
+            if let StatementKind::Assign(box (_, Rvalue::Ref(_, _, place)))
+            | StatementKind::Assign(box (_, Rvalue::RawPtr(_, place))) = &stmt.kind
+            {
+                if !place.is_indirect() {
+                    borrowed.insert(place.local.as_usize());
+                }
+            }
+        }
+    }
+    borrowed
+}
+
+// the value of a constant or a switch value of the type, whose bits are sign-extended if signed.
+fn scalar<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>, bits: u128) -> Option<Term> {
+    match ty.kind() {
+        ty::Bool => Some(Term::Bool(bits != 0)),
+        ty::Char | ty::Uint(_) => i128::try_from(bits).ok().map(Term::Int),
+        ty::Int(int_ty) => {
+            let size = int_ty
+                .bit_width()
+                .unwrap_or(tcx.data_layout.pointer_size.bits()) as u32;
+            let shift = 128 - size;
+            Some(Term::Int(((bits << shift) as i128) >> shift))
+        }
+        _ => None,
+    }
+}
+
+fn is_scalar(ty: Ty<'_>) -> bool {
+    matches!(ty.kind(), ty::Bool | ty::Char | ty::Int(_) | ty::Uint(_))
+}
+
+impl<'tcx> SafeDropGraph<'tcx> {
+    pub fn body(&self) -> &'tcx Body<'tcx> {
+        self.tcx.optimized_mir(self.def_id)
+    }
+
+    fn read(&mut self, local: usize) -> Option<Term> {
+        let ty = self.body().local_decls[local.into()].ty;
+        if !is_scalar(ty) {
+            return None;
+        }
+        let is_bool = ty.is_bool();
+        if self.borrowed.contains(&local) {
+            return Some(self.path_cond.fresh(is_bool));
+        }
+        if let Some(value) = self.path_cond.values.get(&local) {
+            return Some(value.clone());
+        }
+        let value = self.path_cond.fresh(is_bool);
+        self.path_cond.values.insert(local, value.clone());
+        Some(value)
+    }
+
+    fn discriminant(&mut self, local: usize) -> Term {
+        if self.borrowed.contains(&local) {
+            return self.path_cond.fresh(false);
+        }
+        if let Some(value) = self.path_cond.discriminants.get(&local) {
+            return value.clone();
+        }
+        let value = self.path_cond.fresh(false);
+        self.path_cond.discriminants.insert(local, value.clone());
+        value
+    }
+
+    fn operand_term(&mut self, operand: &Operand<'tcx>) -> Option<Term> {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => {
+                if !place.projection.is_empty() {
+                    return None;
+                }
+                self.read(place.local.as_usize())
+            }
+            Operand::Constant(constant) => {
+                let ty = constant.ty();
+                if !is_scalar(ty) {
+                    return None;
+                }
+                let param_env = self.tcx.param_env(self.def_id);
+                let bits = constant.const_.try_eval_bits(self.tcx, param_env)?;
+                scalar(self.tcx, ty, bits)
+            }
+        }
+    }
+
+    fn rvalue_term(&mut self, rvalue: &Rvalue<'tcx>) -> Option<Term> {
+        match rvalue {
+            Rvalue::Use(operand) => self.operand_term(operand),
+            Rvalue::UnaryOp(UnOp::Not, operand) => {
+                let term = self.operand_term(operand)?;
+                term.is_bool().then(|| Term::Not(Box::new(term)))
+            }
+            Rvalue::BinaryOp(op, box (lhs, rhs)) => {
+                let (lhs, rhs) = (self.operand_term(lhs)?, self.operand_term(rhs)?);
+                if lhs.is_bool() != rhs.is_bool() {
+                    return None;
+                }
+                match op {
+                    BinOp::Eq | BinOp::Ne => Some(Term::Cmp(*op, Box::new(lhs), Box::new(rhs))),
+                    BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge if !lhs.is_bool() => {
+                        Some(Term::Cmp(*op, Box::new(lhs), Box::new(rhs)))
+                    }
+                    BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor if lhs.is_bool() => {
+                        Some(Term::Logic(*op, Box::new(lhs), Box::new(rhs)))
+                    }
+                    _ => None,
+                }
+            }
+            Rvalue::Discriminant(place) if place.projection.is_empty() => {
+                Some(self.discriminant(place.local.as_usize()))
+            }
+            _ => None,
+        }
+    }
+
+    fn variant_discriminant(&self, ty: Ty<'tcx>, variant: usize) -> Option<Term> {
+        let discr = ty.discriminant_for_variant(self.tcx, variant.into())?;
+        scalar(self.tcx, discr.ty, discr.val)
+    }
+
+    // update the values of the locals assigned in the block.
+    pub fn path_block(&mut self, bb_index: usize) {
+        let body = self.body();
+        let data = &body.basic_blocks[BasicBlock::from_usize(bb_index)];
+        for stmt in &data.statements {
+            match &stmt.kind {
+                StatementKind::Assign(box (place, rvalue)) if place.projection.is_empty() => {
+                    let local = place.local.as_usize();
+                    if self.borrowed.contains(&local) {
+                        continue;
+                    }
+                    match self.rvalue_term(rvalue) {
+                        Some(value) => self.path_cond.values.insert(local, value),
+                        None => self.path_cond.values.remove(&local),
+                    };
+                    let discriminant = match rvalue {
+                        Rvalue::Aggregate(box AggregateKind::Adt(_, variant, ..), _) => {
+                            let ty = body.local_decls[place.local].ty;
+                            self.variant_discriminant(ty, variant.as_usize())
+                        }
+                        Rvalue::Use(Operand::Copy(rv) | Operand::Move(rv))
+                            if rv.projection.is_empty() =>
+                        {
+                            Some(self.discriminant(rv.local.as_usize()))
+                        }
+                        _ => None,
+                    };
+                    match discriminant {
+                        Some(value) => self.path_cond.discriminants.insert(local, value),
+                        None => self.path_cond.discriminants.remove(&local),
+                    };
+                }
+                StatementKind::SetDiscriminant {
+                    place,
+                    variant_index,
+                } if place.projection.is_empty() => {
+                    let local = place.local.as_usize();
+                    let ty = body.local_decls[place.local].ty;
+                    match self.variant_discriminant(ty, variant_index.as_usize()) {
+                        Some(value) => self.path_cond.discriminants.insert(local, value),
+                        None => self.path_cond.discriminants.remove(&local),
+                    };
+                }
+                StatementKind::Deinit(place) if place.projection.is_empty() => {
+                    self.path_cond.forget(place.local.as_usize());
+                }
+                _ => {}
+            }
+        }
+        if let Some(terminator) = &data.terminator {
+            if let TerminatorKind::Call { destination, .. } = &terminator.kind {
+                self.path_cond.forget(destination.local.as_usize());
+            }
+        }
+    }
+
+    // the locals assigned in the blocks of a loop take unknown values after it.
+    pub fn path_scc(&mut self, blocks: &[usize]) {
+        let body = self.body();
+        for bb_index in blocks {
+            let data = &body.basic_blocks[BasicBlock::from_usize(*bb_index)];
+            for stmt in &data.statements {
+                match &stmt.kind {
+                    StatementKind::Assign(box (place, _))
+                    | StatementKind::SetDiscriminant { box place, .. }
+                    | StatementKind::Deinit(box place) => {
+                        self.path_cond.forget(place.local.as_usize());
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(terminator) = &data.terminator {
+                if let TerminatorKind::Call { destination, .. } = &terminator.kind {
+                    self.path_cond.forget(destination.local.as_usize());
+                }
+            }
+        }
+    }
+
+    // the condition of taking the edge from a block ending with SwitchInt to the target.
+    pub fn branch_cond(&mut self, bb_index: usize, target: usize) -> Option<Term> {
+        let block = &self.blocks[bb_index];
+        if !block.scc_sub_blocks.is_empty() {
+            return None;
+        }
+        let TerminatorKind::SwitchInt { discr, targets } = block.switch_stmts.first()?.kind.clone()
+        else {
+            return None;
+        };
+        let discr_ty = discr.ty(self.body(), self.tcx);
+        let term = self.operand_term(&discr)?;
+        let mut cond: Option<Term> = None;
+        let mut join = |term: Term, op: BinOp| {
+            cond = Some(match cond.take() {
+                Some(cond) => Term::Logic(op, Box::new(cond), Box::new(term)),
+                None => term,
+            });
+        };
+        let mut values = Vec::new();
+        for (bits, bb) in targets.iter() {
+            let value = scalar(self.tcx, discr_ty, bits)?;
+            if bb.as_usize() == target {
+                values.push(Term::Cmp(
+                    BinOp::Eq,
+                    Box::new(term.clone()),
+                    Box::new(value),
+                ));
+            }
+        }
+        for value in values {
+            join(value, BinOp::BitOr);
+        }
+        if targets.otherwise().as_usize() == target {
+            let mut otherwise: Option<Term> = None;
+            for (bits, _) in targets.iter() {
+                let value = scalar(self.tcx, discr_ty, bits)?;
+                let ne = Term::Cmp(BinOp::Ne, Box::new(term.clone()), Box::new(value));
+                otherwise = Some(match otherwise {
+                    Some(otherwise) => {
+                        Term::Logic(BinOp::BitAnd, Box::new(otherwise), Box::new(ne))
+                    }
+                    None => ne,
+                });
+            }
+            join(otherwise.unwrap_or(Term::Bool(true)), BinOp::BitOr);
+        }
+        cond
+    }
+
+    // whether the path is still feasible after taking the branch; the solver is called only if the
+    // condition is related to the conditions taken before.
+    pub fn is_feasible(&self, cond: &Term) -> bool {
+        let mut symbols = Vec::new();
+        cond.symbols(&mut symbols);
+        let mut distinct = FxHashSet::default();
+        let mut free = cond.is_free() && symbols.iter().all(|symbol| distinct.insert(*symbol));
+        if free {
+            let mut taken = Vec::new();
+            for cond in &self.path_cond.conds {
+                cond.symbols(&mut taken);
+            }
+            free = !taken.iter().any(|symbol| distinct.contains(symbol));
+        }
+        if free {
+            return true;
+        }
+        let ctx = self.z3_ctx.get_or_init(|| {
+            let mut cfg = z3::Config::new();
+            cfg.set_timeout_msec(1000);
+            z3::Context::new(&cfg)
+        });
+        let solver = z3::Solver::new(ctx);
+        for cond in self.path_cond.conds.iter().chain(std::iter::once(cond)) {
+            solver.assert(&cond.encode_bool(ctx));
+        }
+        // unknown results, e.g., timeouts, keep the path.
+        solver.check() != z3::SatResult::Unsat
+    }
+
+    // take the branch to the target, and return false if the path becomes infeasible.
+    pub fn take_branch(&mut self, bb_index: usize, target: usize) -> bool {
+        let Some(cond) = self.branch_cond(bb_index, target) else {
+            return true;
+        };
+        if !self.is_feasible(&cond) {
+            rap_debug!(
+                "Infeasible path in {:?}: {:?} -> bb{}",
+                self.def_id,
+                self.path,
+                target
+            );
+            return false;
+        }
+        self.path_cond.conds.push(cond);
+        true
+    }
+}
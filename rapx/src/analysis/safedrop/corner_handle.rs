@@ -1,5 +1,5 @@
 use super::graph::*;
-use crate::analysis::utils::intrinsic_id::*;
+use crate::analysis::utils::intrinsic_id::{intrinsic, Intrinsic};
 use rustc_span::def_id::DefId;
 
 impl<'tcx> SafeDropGraph<'tcx> {
@@ -11,16 +11,18 @@ impl<'tcx> SafeDropGraph<'tcx> {
         def_id: DefId,
     ) -> bool {
         // CASE 1: function::call_mut
+        // Calls of closures are resolved to their bodies; this only remains for closures behind
+        // trait objects or generic parameters.
         // #![feature(fn_traits)]
         // fn main() {
         //     let x = 1i32;
         //     let mut c = || {x+1;};
         //     c.call_mut(());
         // }
-        if def_id.index.as_usize() == CALL_MUT
-            || def_id.index.as_usize() == NEXT
-            || def_id.index.as_usize() == CLONE
-        {
+        if matches!(
+            intrinsic(def_id),
+            Some(Intrinsic::CallMut | Intrinsic::Next | Intrinsic::Clone)
+        ) {
             return true;
         }
 
@@ -74,17 +76,3 @@ impl<'tcx> SafeDropGraph<'tcx> {
         return true;
     }
 }
-
-//these adt structs use the Rc-kind drop instruction, which we do not focus on.
-pub fn is_corner_adt(str: String) -> bool {
-    if let Some(_) = str.find("cell::RefMut") {
-        return true;
-    }
-    if let Some(_) = str.find("cell::Ref") {
-        return true;
-    }
-    if let Some(_) = str.find("rc::Rc") {
-        return true;
-    }
-    return false;
-}
@@ -0,0 +1,206 @@
+use std::collections::BTreeSet;
+
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir::{Body, Operand, TerminatorKind};
+use rustc_middle::ty::{self, TyCtxt};
+use serde::{Deserialize, Serialize};
+
+use super::graph::*;
+use super::trace::TraceEvent;
+use crate::analysis::core::alias::closure::resolve_call;
+use crate::analysis::core::alias::container::ELEMENT;
+
+/// The pointers that a function leaves dangling when it returns normally.
+///
+/// E.g., an argument whose heap item is dropped in place. They are checked at the call sites of
+/// the function, so that the uses of the dropped heap item in the callers are found.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FnDangling {
+    /// The arguments pointing to dropped heap items, numbered from 1 as the MIR locals.
+    pub args: BTreeSet<usize>,
+    /// Whether the return value points to a dropped heap item.
+    pub ret: bool,
+    /// The containers whose elements are dropped, e.g., after being popped, each as the argument pointing to
+    /// it followed by the numbers of the fields holding it.
+    #[serde(default)]
+    pub elements: BTreeSet<Vec<usize>>,
+    /// The dropped fields of the arguments passed by value, e.g., the upvars of a `FnOnce` closure, each as
+    /// the argument followed by the numbers of the fields; the argument alone if it is dropped as a whole.
+    #[serde(default)]
+    pub dropped: BTreeSet<Vec<usize>>,
+}
+
+impl FnDangling {
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty() && !self.ret && self.elements.is_empty() && self.dropped.is_empty()
+    }
+}
+
+pub type DanglingMap = FxHashMap<DefId, FnDangling>;
+
+/// The functions called in the body, including the closures and the coroutines run by the calls.
+pub fn callees<'tcx>(tcx: TyCtxt<'tcx>, body: &Body<'tcx>) -> FxHashSet<DefId> {
+    let mut callees = FxHashSet::default();
+    for data in body.basic_blocks.iter() {
+        if let Some(terminator) = &data.terminator {
+            if let TerminatorKind::Call {
+                func: Operand::Constant(constant),
+                args,
+                ..
+            } = &terminator.kind
+            {
+                if let ty::FnDef(def_id, generic_args) = constant.const_.ty().kind() {
+                    callees.insert(resolve_call(tcx, body, *def_id, generic_args, args).0);
+                }
+            }
+        }
+    }
+    callees
+}
+
+/// The facts of the callees of the function, which are used by `dangling_call_check`.
+pub fn callee_dangling<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    body: &Body<'tcx>,
+    dangling_map: &DanglingMap,
+) -> DanglingMap {
+    callees(tcx, body)
+        .into_iter()
+        .filter_map(|callee| Some((callee, dangling_map.get(&callee)?.clone())))
+        .collect()
+}
+
+impl<'tcx> SafeDropGraph<'tcx> {
+    // record the pointers left dangling when reaching a leaf node of a normal path.
+    pub fn record_dangling(&mut self, current_block: &BlockNode<'tcx>) {
+        if current_block.is_cleanup {
+            return;
+        }
+        if self.values[0].may_drop && self.is_dangling(0) {
+            self.dangling.ret = true;
+        }
+        for i in 1..=self.arg_size {
+            if self.values[i].is_ptr() && self.is_dangling(i) {
+                self.dangling.args.insert(i);
+            }
+        }
+        // elements are named after the argument they are moved out of, and their father is the container, see
+        // `element`.
+        for value in self.values.iter() {
+            if value.field_id == ELEMENT
+                && !value.is_alive()
+                && (1..=self.arg_size).contains(&value.local)
+                && self.values[value.local].is_ptr()
+            {
+                let mut path = vec![value.local];
+                path.extend(self.get_field_seq(&self.values[value.father]));
+                self.dangling.elements.insert(path);
+            }
+        }
+        for i in 1..=self.arg_size {
+            if !self.values[i].is_ptr() && self.values[i].may_drop {
+                self.record_dropped(i, vec![i]);
+            }
+        }
+    }
+
+    // record the dropped fields of an argument passed by value, unless they are moved out.
+    fn record_dropped(&mut self, node: usize, path: Vec<usize>) {
+        if !self.values[node].is_alive() {
+            self.dangling.dropped.insert(path);
+            return;
+        }
+        for (field, child) in self.values[node].fields.clone() {
+            if field == ELEMENT {
+                continue;
+            }
+            let mut path = path.clone();
+            path.push(field);
+            self.record_dropped(child, path);
+        }
+    }
+
+    // drop the heap items that the callees of the block leave dangling.
+    pub fn dangling_call_check(&mut self, bb_index: usize, tcx: TyCtxt<'tcx>) {
+        let cur_block = self.blocks[bb_index].clone();
+        for call in cur_block.calls {
+            let TerminatorKind::Call {
+                func: Operand::Constant(ref constant),
+                ref args,
+                ref destination,
+                ..
+            } = call.kind
+            else {
+                continue;
+            };
+            let ty::FnDef(callee, generic_args) = constant.const_.ty().kind() else {
+                continue;
+            };
+            let (callee, places) = resolve_call(tcx, self.body(), *callee, generic_args, args);
+            let Some(dangling) = self.callee_dangling.get(&callee).cloned() else {
+                continue;
+            };
+            let birth = self.call_birth(bb_index);
+            let events = self.trace.len();
+            for arg in dangling.args {
+                let Some(Some(place)) = places.get(arg - 1) else {
+                    continue;
+                };
+                let node = self.projection(tcx, false, *place);
+                self.dead_node(node, birth, &call.source_info, false);
+            }
+            for path in dangling.elements {
+                let Some(Some(place)) = places.get(path[0] - 1) else {
+                    continue;
+                };
+                let arg = self.projection(tcx, false, *place);
+                let arg = self.field_base(arg, &path[1..]);
+                // containers that the caller never reaches do not hold its elements.
+                let Some(node) = path[1..].iter().try_fold(arg, |node, field| {
+                    self.values[node].fields.get(field).copied()
+                }) else {
+                    continue;
+                };
+                let element = self.element(node);
+                self.detach_element(node);
+                self.dead_node(element, birth, &call.source_info, true);
+            }
+            for path in dangling.dropped {
+                let Some(Some(place)) = places.get(path[0] - 1) else {
+                    continue;
+                };
+                let arg = self.projection(tcx, false, *place);
+                let arg = self.field_base(arg, &path[1..]);
+                // fields that the caller never creates are not aliased by it.
+                let Some(node) = path[1..].iter().try_fold(arg, |node, field| {
+                    self.values[node].fields.get(field).copied()
+                }) else {
+                    continue;
+                };
+                self.dead_node(node, birth, &call.source_info, false);
+            }
+            if dangling.ret {
+                let node = self.projection(tcx, false, *destination);
+                self.dead_node(node, birth, &call.source_info, false);
+                // The return value is born at the call, so it is not dropped by dead_node.
+                if self.values[node].may_drop && self.values[node].is_alive() {
+                    self.values[node].dead();
+                    self.record(TraceEvent::Drop {
+                        node,
+                        span: call.source_info.span,
+                        callee: None,
+                    });
+                }
+            }
+            for event in self.trace[events..].iter_mut() {
+                if let TraceEvent::Drop {
+                    callee: dropper, ..
+                } = event
+                {
+                    *dropper = Some(callee);
+                }
+            }
+        }
+    }
+}
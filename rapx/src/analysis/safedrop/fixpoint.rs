@@ -0,0 +1,399 @@
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_middle::mir::{Operand, TerminatorKind};
+use rustc_middle::ty::TyCtxt;
+
+use super::constraint::PathCondition;
+use super::graph::*;
+use super::safedrop::visit_limit;
+use super::trace::TraceEvent;
+use crate::analysis::core::alias::FnMap;
+use crate::utils::config::config;
+
+/// The rounds of analyzing a loop before widening, set by `limits.safedrop-widening` in the configuration.
+pub fn widening_limit() -> usize {
+    config().limits.safedrop_widening
+}
+
+/// The status of the analysis on a path, which is saved before the paths split and joined where they merge.
+#[derive(Debug, Clone)]
+pub struct PathState {
+    values: Vec<ValueNode>,
+    constant: FxHashMap<usize, usize>,
+    alias_set: Vec<usize>,
+    dead_record: Vec<bool>,
+    trace: Vec<TraceEvent>,
+    path: Vec<usize>,
+    path_cond: PathCondition,
+}
+
+/// How much precision is lost when the paths of a function are too many to be visited one by one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrecisionLoss {
+    /// The times that the status of two paths are joined at a block.
+    pub merged: usize,
+    /// The loops that do not converge within `limits.safedrop-widening` rounds.
+    pub widened: usize,
+}
+
+// the root of a node in the union-find set of a saved status.
+fn find(alias_set: &[usize], mut node: usize) -> usize {
+    while alias_set[node] != node {
+        node = alias_set[node];
+    }
+    node
+}
+
+impl<'tcx> SafeDropGraph<'tcx> {
+    pub fn state(&self) -> PathState {
+        PathState {
+            values: self.values.clone(),
+            constant: self.constant.clone(),
+            alias_set: self.alias_set.clone(),
+            dead_record: self.dead_record.clone(),
+            trace: self.trace.clone(),
+            path: self.path.clone(),
+            path_cond: self.path_cond.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, state: PathState) {
+        self.values = state.values;
+        self.constant = state.constant;
+        self.alias_set = state.alias_set;
+        self.dead_record = state.dead_record;
+        self.trace = state.trace;
+        self.path = state.path;
+        self.path_cond = state.path_cond;
+    }
+
+    /*
+     * Join the status of another path into the current one.
+     * The nodes of the other path are matched with the current ones by their locals and fields,
+     * and the fields created only on the other path are added. Two nodes are aliases if they are
+     * aliases on either path, while a node is dead only if it is dead on both paths, so that the
+     * join does not make up bugs that happen on neither path.
+     */
+    pub fn join(&mut self, other: &PathState) {
+        let locals = self.tcx.optimized_mir(self.def_id).local_decls.len();
+        let mut map: Vec<Option<usize>> = vec![None; other.values.len()];
+        let mut work_list = Vec::new();
+        for (local, mapped) in map.iter_mut().enumerate().take(locals) {
+            *mapped = Some(local);
+            work_list.push(local);
+        }
+        while let Some(node) = work_list.pop() {
+            let parent = map[node].unwrap();
+            // a field not created on the other path has the status of its parent there.
+            let mut missing: Vec<_> = self.values[parent]
+                .fields
+                .iter()
+                .filter(|(field, _)| !other.values[node].fields.contains_key(field))
+                .map(|(_, child)| *child)
+                .collect();
+            missing.sort();
+            for child in missing {
+                self.revive(child, other.values[node].birth);
+            }
+            let mut fields: Vec<_> = other.values[node].fields.iter().collect();
+            fields.sort();
+            for (field, child) in fields {
+                match (map[*child], self.values[parent].fields.get(field).copied()) {
+                    (Some(mapped), Some(current)) if mapped != current => {
+                        self.union_merge(mapped, current);
+                    }
+                    (Some(_), Some(_)) => {}
+                    (Some(mapped), None) => {
+                        self.values[parent].fields.insert(*field, mapped);
+                    }
+                    (None, Some(current)) => {
+                        map[*child] = Some(current);
+                        work_list.push(*child);
+                    }
+                    (None, None) => {
+                        let mut value = other.values[*child].clone();
+                        value.index = self.values.len();
+                        value.fields = FxHashMap::default();
+                        self.values[parent].fields.insert(*field, value.index);
+                        map[*child] = Some(value.index);
+                        self.alias_set.push(value.index);
+                        self.dead_record.push(other.dead_record[*child]);
+                        self.values.push(value);
+                        let birth = self.values[parent].birth;
+                        self.revive(map[*child].unwrap(), birth);
+                        work_list.push(*child);
+                    }
+                }
+            }
+        }
+        for node in 0..other.values.len() {
+            let Some(current) = map[node] else {
+                continue;
+            };
+            if let Some(root) = map[find(&other.alias_set, node)] {
+                self.union_merge(current, root);
+            }
+            let birth = other.values[node].birth;
+            let value = &mut self.values[current];
+            if !value.is_alive() || (birth >= 0 && birth < value.birth) {
+                value.birth = birth;
+            }
+            self.dead_record[current] &= other.dead_record[node];
+        }
+        self.constant
+            .retain(|id, value| other.constant.get(id) == Some(value));
+        // the events of the other path explain the bugs found after the join as well.
+        for event in &other.trace {
+            let remap = |node: usize| map[node];
+            let event = match event.clone() {
+                TraceEvent::Birth { node, span } => {
+                    remap(node).map(|node| TraceEvent::Birth { node, span })
+                }
+                TraceEvent::Alias {
+                    lv,
+                    rv,
+                    span,
+                    source,
+                } => remap(lv).zip(remap(rv)).map(|(lv, rv)| TraceEvent::Alias {
+                    lv,
+                    rv,
+                    span,
+                    source,
+                }),
+                TraceEvent::Drop { node, span, callee } => {
+                    remap(node).map(|node| TraceEvent::Drop { node, span, callee })
+                }
+            };
+            if let Some(event) = event {
+                if !self.trace.iter().any(|e| e.span() == event.span()) {
+                    self.trace.push(event);
+                }
+            }
+        }
+        self.path_cond.join(&other.path_cond);
+    }
+
+    // make the node and its fields alive if they are dead while their counterparts are alive.
+    fn revive(&mut self, node: usize, birth: isize) {
+        if birth < 0 {
+            return;
+        }
+        if !self.values[node].is_alive() {
+            self.values[node].birth = birth;
+        }
+        for child in self.values[node].fields.clone().into_values() {
+            if child != node {
+                self.revive(child, birth);
+            }
+        }
+    }
+
+    // whether the current status is the same as the saved one.
+    fn same(&self, other: &PathState) -> bool {
+        self.values.len() == other.values.len()
+            && self.constant == other.constant
+            && self.dead_record == other.dead_record
+            && self
+                .values
+                .iter()
+                .zip(other.values.iter())
+                .all(|(value, other)| value.birth == other.birth && value.fields == other.fields)
+            && (0..self.alias_set.len()).all(|node| {
+                find(&self.alias_set, node) == find(&self.alias_set, find(&other.alias_set, node))
+                    && find(&other.alias_set, node)
+                        == find(&other.alias_set, find(&self.alias_set, node))
+            })
+    }
+
+    /*
+     * Give up the changes that keep growing in a loop: the nodes killed in the last round are
+     * assumed alive, and the constants changed in the last round are unknown.
+     */
+    fn widen(&mut self, before: &PathState) {
+        for (value, old) in self.values.iter_mut().zip(before.values.iter()) {
+            if !value.is_alive() && old.is_alive() {
+                value.birth = old.birth;
+            }
+        }
+        self.constant
+            .retain(|id, value| before.constant.get(id) == Some(value));
+    }
+
+    fn transfer(&mut self, bb_index: usize, tcx: TyCtxt<'tcx>, fn_map: &FnMap) {
+        self.alias_bb(bb_index, tcx);
+        self.alias_bbcall(bb_index, tcx, fn_map);
+        self.drop_check(bb_index, tcx);
+    }
+
+    // analyze a loop until its status converges, or widen it after the rounds are used up.
+    fn loop_fixpoint(&mut self, scc: usize, tcx: TyCtxt<'tcx>, fn_map: &FnMap) {
+        let sub_blocks = self.blocks[scc].scc_sub_blocks.clone();
+        let mut rounds = 0;
+        let mut bugs = None;
+        loop {
+            let before = self.state();
+            self.transfer(scc, tcx, fn_map);
+            for bb_index in &sub_blocks {
+                self.transfer(*bb_index, tcx, fn_map);
+            }
+            // as on the paths, bugs are found in the first round only; the aliases never split,
+            // so the later rounds mix up the values of different iterations.
+            let bugs = bugs.get_or_insert_with(|| self.bug_records.clone());
+            self.bug_records = bugs.clone();
+            self.join(&before);
+            if self.same(&before) {
+                break;
+            }
+            rounds += 1;
+            if rounds >= widening_limit() {
+                self.widen(&before);
+                self.precision_loss.widened += 1;
+                break;
+            }
+        }
+        self.path_scc(&[scc]);
+        self.path_scc(&sub_blocks);
+    }
+
+    // the successors of a block, each with the discriminant value assumed on the edge.
+    fn successors(
+        &mut self,
+        block: &BlockNode<'tcx>,
+        tcx: TyCtxt<'tcx>,
+    ) -> Vec<(usize, Option<(usize, usize)>)> {
+        if !block.switch_stmts.is_empty() && block.scc_sub_blocks.is_empty() {
+            if let TerminatorKind::SwitchInt { discr, targets } = block.switch_stmts[0].kind.clone()
+            {
+                match discr {
+                    Operand::Copy(p) | Operand::Move(p) => {
+                        let place = self.projection(tcx, false, p);
+                        let discr_id = self.values[place].index;
+                        if let Some(constant) = self.constant.get(&discr_id) {
+                            let target = targets.target_for_value(*constant as u128);
+                            return vec![(target.as_usize(), None)];
+                        }
+                        if discr_id != place {
+                            let mut successors: Vec<_> = targets
+                                .iter()
+                                .map(|(value, target)| {
+                                    (target.as_usize(), Some((discr_id, value as usize)))
+                                })
+                                .collect();
+                            // usize::MAX indicates the default path as in `check`.
+                            successors.push((
+                                targets.otherwise().as_usize(),
+                                Some((discr_id, usize::MAX)),
+                            ));
+                            return successors;
+                        }
+                    }
+                    Operand::Constant(c) => {
+                        let param_env = tcx.param_env(self.def_id);
+                        if let Some(value) = c.const_.try_eval_bits(tcx, param_env) {
+                            return vec![(targets.target_for_value(value).as_usize(), None)];
+                        }
+                    }
+                }
+            }
+        }
+        let mut successors: Vec<_> = block.next.iter().map(|next| (*next, None)).collect();
+        successors.sort();
+        successors
+    }
+
+    // the SCCs reachable from the entry in topological order.
+    fn topological_order(&self) -> Vec<usize> {
+        let entry = self.scc_indices[0];
+        let mut order = Vec::new();
+        let mut visited = FxHashSet::default();
+        let mut stack = vec![(entry, false)];
+        while let Some((scc, finished)) = stack.pop() {
+            if finished {
+                order.push(scc);
+                continue;
+            }
+            if !visited.insert(scc) {
+                continue;
+            }
+            stack.push((scc, true));
+            for next in &self.blocks[scc].next {
+                let next = self.scc_indices[*next];
+                if !visited.contains(&next) {
+                    stack.push((next, false));
+                }
+            }
+        }
+        order.reverse();
+        order
+    }
+
+    /*
+     * Check the paths of the function one by one, and merge them if there are too many.
+     * The bugs found on the visited paths are kept. Return whether all the paths are visited.
+     */
+    pub fn check_paths(&mut self, tcx: TyCtxt<'tcx>, fn_map: &FnMap) -> bool {
+        let init = self.state();
+        self.check(0, tcx, fn_map);
+        if self.visit_times <= visit_limit() {
+            return true;
+        }
+        self.restore(init);
+        self.fixpoint(tcx, fn_map);
+        self.bug_records.merged = true;
+        false
+    }
+
+    /*
+     * Check the function by merging the paths instead of visiting them one by one.
+     * The SCCs are visited in topological order, and the status of the paths reaching an SCC is
+     * joined before it is analyzed, so that each SCC is analyzed once except for the rounds of a loop.
+     */
+    pub fn fixpoint(&mut self, tcx: TyCtxt<'tcx>, fn_map: &FnMap) {
+        let mut states = FxHashMap::default();
+        states.insert(self.scc_indices[0], self.state());
+        for scc in self.topological_order() {
+            let Some(state) = states.remove(&scc) else {
+                // all the paths to the SCC are infeasible.
+                continue;
+            };
+            self.restore(state);
+            self.path.push(scc);
+            let block = self.blocks[scc].clone();
+            if block.scc_sub_blocks.is_empty() {
+                self.transfer(scc, tcx, fn_map);
+                self.path_block(scc);
+            } else {
+                self.loop_fixpoint(scc, tcx, fn_map);
+            }
+            if block.next.is_empty() {
+                self.record_dangling(&block);
+                if Self::should_check(self.def_id) {
+                    self.dp_check(&block);
+                }
+                continue;
+            }
+            let exit = self.state();
+            for (next, discr) in self.successors(&block, tcx) {
+                self.restore(exit.clone());
+                if let Some((discr_id, value)) = discr {
+                    self.constant.insert(discr_id, value);
+                }
+                if !self.take_branch(scc, next) {
+                    continue;
+                }
+                let next = self.scc_indices[next];
+                match states.remove(&next) {
+                    Some(state) => {
+                        let incoming = self.state();
+                        self.restore(state);
+                        self.join(&incoming);
+                        self.precision_loss.merged += 1;
+                        states.insert(next, self.state());
+                    }
+                    None => {
+                        states.insert(next, self.state());
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,17 +1,22 @@
 use super::bug_records::*;
+use super::constraint::{borrowed_locals, PathCondition};
+use super::dangling::{DanglingMap, FnDangling};
+use super::fixpoint::PrecisionLoss;
+use super::trace::TraceEvent;
 use super::types::*;
+use crate::analysis::core::alias::closure::{aggregate_field, by_field, CoroutineFields};
 use crate::analysis::core::heap_item::AdtOwner;
-use crate::analysis::utils::intrinsic_id::*;
+use crate::analysis::utils::intrinsic_id::{intrinsic, Intrinsic};
 use rustc_data_structures::fx::{FxHashMap, FxHashSet};
 use rustc_middle::mir::{
-    BasicBlock, Body, Const, Operand, Place, Rvalue, StatementKind, Terminator, TerminatorKind,
-    UnwindAction,
+    BasicBlock, Body, BorrowKind, Const, Operand, Place, Rvalue, StatementKind, Terminator,
+    TerminatorKind, UnwindAction,
 };
 use rustc_middle::ty;
 use rustc_middle::ty::TyCtxt;
 use rustc_span::def_id::DefId;
 use rustc_span::Span;
-use std::cell::RefCell;
+use std::cell::{OnceCell, RefCell};
 use std::cmp::min;
 use std::vec::Vec;
 
@@ -23,6 +28,9 @@ pub enum AssignType {
     Move,
     InitBox,
     Variant,
+    // a mutable reference or a raw pointer passed as the destination of `ptr::write`, which initializes a dropped
+    // place again.
+    WriteDest,
 }
 
 //self-defined assignments structure.
@@ -65,8 +73,8 @@ pub struct BlockNode<'tcx> {
     pub drops: Vec<Terminator<'tcx>>,
     //store the index of the basic blocks as a SCC node.
     pub scc_sub_blocks: Vec<usize>,
-    //store const values defined in this block, i.e., which id has what value;
-    pub const_value: Vec<(usize, usize)>,
+    //store const values defined in this block, i.e., which place has what value;
+    pub const_value: Vec<(Place<'tcx>, usize)>,
     //store switch stmts in current block for the path filtering in path-sensitive analysis.
     pub switch_stmts: Vec<Terminator<'tcx>>,
 
@@ -85,7 +93,7 @@ impl<'tcx> BlockNode<'tcx> {
             calls: Vec::<Terminator<'tcx>>::new(),
             drops: Vec::<Terminator<'tcx>>::new(),
             scc_sub_blocks: Vec::<usize>::new(),
-            const_value: Vec::<(usize, usize)>::new(),
+            const_value: Vec::<(Place<'tcx>, usize)>::new(),
             switch_stmts: Vec::<Terminator<'tcx>>::new(),
             modified_value: FxHashSet::<usize>::default(),
             scc_outer: RefCell::new(None),
@@ -145,8 +153,16 @@ impl ValueNode {
         self.kind == TyKind::Ref
     }
 
-    pub fn is_corner_case(&self) -> bool {
-        self.kind == TyKind::CornerCase
+    pub fn is_counted(&self) -> bool {
+        self.kind == TyKind::Counted
+    }
+
+    pub fn is_weak(&self) -> bool {
+        self.kind == TyKind::Weak
+    }
+
+    pub fn is_guard(&self) -> bool {
+        self.kind == TyKind::Guard
     }
 }
 
@@ -167,10 +183,27 @@ pub struct SafeDropGraph<'tcx> {
     pub return_set: FxHashSet<(usize, usize)>,
     // record the information of bugs for the function.
     pub bug_records: BugRecords,
+    // the events and the basic blocks on the current path, which explain the bugs found on it.
+    pub trace: Vec<TraceEvent>,
+    pub path: Vec<usize>,
+    // the values of the scalar locals and the branch conditions on the current path.
+    pub path_cond: PathCondition,
+    pub borrowed: FxHashSet<usize>,
+    // the context of the solver checking the branch conditions, created at the first check.
+    pub z3_ctx: OnceCell<z3::Context>,
+    // the pointers left dangling by the function, and those left by its callees.
+    pub dangling: FnDangling,
+    pub callee_dangling: DanglingMap,
     // a threhold to avoid path explosion.
     pub visit_times: usize,
+    // the precision lost by merging the paths after the threshold is reached.
+    pub precision_loss: PrecisionLoss,
     pub alias_set: Vec<usize>,
     pub dead_record: Vec<bool>,
+    // the fields of the state machine if the function is the body of a coroutine.
+    pub coroutine: Option<CoroutineFields>,
+    // the pointers copied into temporaries only to be dereferenced, e.g., to the state machine of a coroutine.
+    pub deref_copies: FxHashMap<usize, Place<'tcx>>,
     // analysis of heap item
     pub adt_owner: AdtOwner,
 
@@ -207,7 +240,7 @@ impl<'tcx> SafeDropGraph<'tcx> {
                 need_drop,
                 need_drop || may_drop,
             );
-            node.kind = kind(local_decl.ty);
+            node.kind = kind(tcx, local_decl.ty);
             alias.push(values.len());
             dead.push(false);
             values.push(node);
@@ -216,6 +249,8 @@ impl<'tcx> SafeDropGraph<'tcx> {
         let basicblocks = &body.basic_blocks;
         let mut blocks = Vec::<BlockNode<'tcx>>::new();
         let mut scc_indices = Vec::<usize>::new();
+        let mut deref_copies = FxHashMap::default();
+        let write_dests = write_dests(body);
 
         // handle each basicblock
         for i in 0..basicblocks.len() {
@@ -262,14 +297,14 @@ impl<'tcx> SafeDropGraph<'tcx> {
                                                 const_value.try_eval_scalar_int(tcx, param_env)
                                             {
                                                 let val = scalar.to_uint(scalar.size());
-                                                cur_bb.const_value.push((lv_local, val as usize));
+                                                cur_bb.const_value.push((lv, val as usize));
                                             }
                                         }
                                         Const::Unevaluated(_unevaluated, _ty) => {}
                                         Const::Val(const_value, _ty) => {
                                             if let Some(scalar) = const_value.try_to_scalar_int() {
                                                 let val = scalar.to_uint(scalar.size());
-                                                cur_bb.const_value.push((lv_local, val as usize));
+                                                cur_bb.const_value.push((lv, val as usize));
                                             }
                                         }
                                     }
@@ -280,7 +315,16 @@ impl<'tcx> SafeDropGraph<'tcx> {
                             let rv_local = p.local.as_usize();
                             if values[lv_local].may_drop && values[rv_local].may_drop {
                                 let rv = p.clone();
-                                let assign = Assignment::new(lv, rv, AssignType::Copy, span);
+                                let atype = match assign.1 {
+                                    Rvalue::Ref(_, BorrowKind::Mut { .. }, _)
+                                    | Rvalue::RawPtr(..)
+                                        if write_dests.contains(&lv_local) =>
+                                    {
+                                        AssignType::WriteDest
+                                    }
+                                    _ => AssignType::Copy,
+                                };
+                                let assign = Assignment::new(lv, rv, atype, span);
                                 cur_bb.assignments.push(assign);
                             }
                         }
@@ -331,13 +375,18 @@ impl<'tcx> SafeDropGraph<'tcx> {
                             }
                             Operand::Constant(_) => {}
                         },
-                        Rvalue::Aggregate(_, ref x) => {
-                            for each_x in x {
+                        Rvalue::Aggregate(ref kind, ref x) => {
+                            for (i, each_x) in x.iter().enumerate() {
                                 match each_x {
                                     Operand::Copy(ref p) | Operand::Move(ref p) => {
                                         let rv_local = p.local.as_usize();
                                         if values[lv_local].may_drop && values[rv_local].may_drop {
                                             let rv = p.clone();
+                                            // e.g., the upvars of a closure are the fields of its environment.
+                                            let lv = match by_field(kind) {
+                                                true => aggregate_field(tcx, body, lv, i, each_x),
+                                                false => lv,
+                                            };
                                             let assign =
                                                 Assignment::new(lv, rv, AssignType::Copy, span);
                                             cur_bb.assignments.push(assign);
@@ -347,6 +396,12 @@ impl<'tcx> SafeDropGraph<'tcx> {
                                 }
                             }
                         }
+                        Rvalue::CopyForDeref(ref p) => {
+                            let ty = p.ty(body, tcx).ty;
+                            if ty.is_ref() || ty.is_unsafe_ptr() {
+                                deref_copies.insert(lv_local, *p);
+                            }
+                        }
                         Rvalue::Discriminant(ref p) => {
                             let rv = p.clone();
                             let assign = Assignment::new(lv, rv, AssignType::Variant, span);
@@ -402,12 +457,17 @@ impl<'tcx> SafeDropGraph<'tcx> {
                             match c.ty().kind() {
                                 ty::FnDef(id, ..) => {
                                     //rap_info!("The ID of {:?} is {:?}", c, id);
-                                    if id.index.as_usize() == DROP
-                                        || id.index.as_usize() == DROP_IN_PLACE
-                                        || id.index.as_usize() == MANUALLYDROP
-                                        || id.index.as_usize() == BOX_DROP_IN_PLACE
-                                        || id.index.as_usize() == DEALLOC
-                                    {
+                                    if matches!(
+                                        intrinsic(*id),
+                                        Some(
+                                            Intrinsic::Drop
+                                                | Intrinsic::DropInPlace
+                                                | Intrinsic::AssumeInitDrop
+                                                | Intrinsic::ManuallyDropDrop
+                                                | Intrinsic::Dealloc
+                                                | Intrinsic::Realloc
+                                        )
+                                    ) {
                                         cur_bb.drops.push(terminator.clone());
                                     }
                                 }
@@ -495,14 +555,43 @@ impl<'tcx> SafeDropGraph<'tcx> {
             constant: FxHashMap::default(),
             return_set: FxHashSet::default(),
             bug_records: BugRecords::new(),
+            trace: Vec::new(),
+            path: Vec::new(),
+            path_cond: PathCondition::default(),
+            borrowed: borrowed_locals(body),
+            z3_ctx: OnceCell::new(),
+            dangling: FnDangling::default(),
+            callee_dangling: DanglingMap::default(),
             visit_times: 0,
+            precision_loss: PrecisionLoss::default(),
             alias_set: alias,
             dead_record: dead,
+            coroutine: CoroutineFields::new(tcx, body),
+            deref_copies,
             adt_owner,
             child_scc: FxHashMap::default(),
         }
     }
 
+    // the birth of the values assigned or dropped in a block, which is the index of its SCC.
+    // E.g., the pointee written by ptr::write after being dropped in place is born again with it.
+    pub fn block_birth(&self, bb_index: usize) -> usize {
+        self.scc_indices[bb_index]
+    }
+
+    // the birth of the values dropped by a call in a block.
+    // Births are the indices of blocks instead of the order on the path, while the values
+    // passed to the callee are all born before they are dropped by it.
+    pub fn call_birth(&self, bb_index: usize) -> usize {
+        self.values
+            .iter()
+            .filter(|value| value.is_alive())
+            .map(|value| value.birth as usize + 1)
+            .max()
+            .unwrap_or_default()
+            .max(self.block_birth(bb_index))
+    }
+
     pub fn tarjan(
         &mut self,
         index: usize,
@@ -649,3 +738,55 @@ impl<'tcx> SafeDropGraph<'tcx> {
         res
     }
 }
+
+// the locals whose values are passed as the destination of `ptr::write`, either directly or through copies and
+// reborrows, e.g., `_3` and `_4` in `_3 = &raw mut _1; _4 = &mut (*_3); write(move _4, _2)`.
+fn write_dests(body: &Body<'_>) -> FxHashSet<usize> {
+    let mut dests = FxHashSet::default();
+    for data in body.basic_blocks.iter() {
+        if let TerminatorKind::Call {
+            func: Operand::Constant(ref constant),
+            ref args,
+            ..
+        } = data.terminator().kind
+        {
+            let ty::FnDef(callee, _) = *constant.const_.ty().kind() else {
+                continue;
+            };
+            if intrinsic(callee) != Some(Intrinsic::Write) {
+                continue;
+            }
+            if let Some(Operand::Move(dest) | Operand::Copy(dest)) = args.first().map(|a| &a.node) {
+                dests.insert(dest.local.as_usize());
+            }
+        }
+    }
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for data in body.basic_blocks.iter() {
+            for stmt in data.statements.iter() {
+                let StatementKind::Assign(box (ref lplace, ref rvalue)) = stmt.kind else {
+                    continue;
+                };
+                if !lplace.projection.is_empty() || !dests.contains(&lplace.local.as_usize()) {
+                    continue;
+                }
+                let rplace = match rvalue {
+                    Rvalue::Use(Operand::Copy(p) | Operand::Move(p))
+                    | Rvalue::Cast(_, Operand::Copy(p) | Operand::Move(p), _) => p,
+                    Rvalue::Ref(_, BorrowKind::Mut { .. }, p) | Rvalue::RawPtr(_, p)
+                        if p.is_indirect_first_projection() =>
+                    {
+                        p
+                    }
+                    _ => continue,
+                };
+                if dests.insert(rplace.local.as_usize()) {
+                    changed = true;
+                }
+            }
+        }
+    }
+    dests
+}
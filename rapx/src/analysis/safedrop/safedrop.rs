@@ -4,10 +4,15 @@ use rustc_middle::ty::{TyCtxt, TyKind};
 
 use crate::analysis::core::alias::FnMap;
 use crate::analysis::safedrop::SafeDropGraph;
+use crate::analysis::utils::intrinsic_id::freed_arg;
 use crate::rap_error;
+use crate::utils::config::config;
 use rustc_data_structures::fx::FxHashSet;
 
-pub const VISIT_LIMIT: usize = 1000;
+/// The maximum number of visited paths of a function, set by `limits.safedrop-visit` in the configuration.
+pub fn visit_limit() -> usize {
+    config().limits.safedrop_visit
+}
 
 impl<'tcx> SafeDropGraph<'tcx> {
     // analyze the drop statement and update the liveness for nodes.
@@ -24,21 +29,28 @@ impl<'tcx> SafeDropGraph<'tcx> {
                     if !self.drop_heap_item_check(place, tcx) {
                         continue;
                     }
-                    let birth = self.scc_indices[bb_index];
+                    let birth = self.block_birth(bb_index);
                     let drop_local = self.projection(tcx, false, place.clone());
                     let info = drop.source_info.clone();
                     self.dead_node(drop_local, birth, &info, false);
                 }
                 TerminatorKind::Call {
-                    func: _, ref args, ..
+                    ref func, ref args, ..
                 } => {
-                    if args.len() > 0 {
-                        let birth = self.scc_indices[bb_index];
-                        let place = match args[0].node {
+                    let freed = match func {
+                        Operand::Constant(constant) => match constant.ty().kind() {
+                            TyKind::FnDef(def_id, _) => freed_arg(tcx, *def_id),
+                            _ => 0,
+                        },
+                        _ => 0,
+                    };
+                    if args.len() > freed {
+                        let birth = self.call_birth(bb_index);
+                        let place = match args[freed].node {
                             Operand::Copy(place) => place,
                             Operand::Move(place) => place,
                             _ => {
-                                rap_error!("Constant operand exists: {:?}", args[0]);
+                                rap_error!("Constant operand exists: {:?}", args[freed]);
                                 return;
                             }
                         };
@@ -50,6 +62,7 @@ impl<'tcx> SafeDropGraph<'tcx> {
                 _ => {}
             }
         }
+        self.dangling_call_check(bb_index, tcx);
     }
 
     pub fn drop_heap_item_check(&self, place: &Place<'tcx>, tcx: TyCtxt<'tcx>) -> bool {
@@ -73,22 +86,32 @@ impl<'tcx> SafeDropGraph<'tcx> {
         }
     }
 
-    pub fn split_check(&mut self, bb_index: usize, tcx: TyCtxt<'tcx>, fn_map: &FnMap) {
+    pub fn split_check(&mut self, from: usize, bb_index: usize, tcx: TyCtxt<'tcx>, fn_map: &FnMap) {
         /* duplicate the status before visiting a path; */
         let backup_values = self.values.clone(); // duplicate the status when visiting different paths;
         let backup_constant = self.constant.clone();
         let backup_alias_set = self.alias_set.clone();
         let backup_dead = self.dead_record.clone();
-        self.check(bb_index, tcx, fn_map);
+        let backup_trace = self.trace.len();
+        let backup_path = self.path.len();
+        let backup_cond = self.path_cond.clone();
+        /* skip the path if its branch conditions cannot hold together */
+        if self.take_branch(from, bb_index) {
+            self.check(bb_index, tcx, fn_map);
+        }
         /* restore after visit */
         self.values = backup_values;
         self.constant = backup_constant;
         self.alias_set = backup_alias_set;
         self.dead_record = backup_dead;
+        self.trace.truncate(backup_trace);
+        self.path.truncate(backup_path);
+        self.path_cond = backup_cond;
     }
 
     pub fn split_check_with_cond(
         &mut self,
+        from: usize,
         bb_index: usize,
         path_discr_id: usize,
         path_discr_val: usize,
@@ -100,22 +123,31 @@ impl<'tcx> SafeDropGraph<'tcx> {
         let backup_constant = self.constant.clone();
         let backup_alias_set = self.alias_set.clone();
         let backup_dead = self.dead_record.clone();
+        let backup_trace = self.trace.len();
+        let backup_path = self.path.len();
+        let backup_cond = self.path_cond.clone();
         /* add control-sensitive indicator to the path status */
         self.constant.insert(path_discr_id, path_discr_val);
-        self.check(bb_index, tcx, fn_map);
+        if self.take_branch(from, bb_index) {
+            self.check(bb_index, tcx, fn_map);
+        }
         /* restore after visit */
         self.values = backup_values;
         self.constant = backup_constant;
         self.alias_set = backup_alias_set;
         self.dead_record = backup_dead;
+        self.trace.truncate(backup_trace);
+        self.path.truncate(backup_path);
+        self.path_cond = backup_cond;
     }
 
     // the core function of the safedrop.
     pub fn check(&mut self, bb_index: usize, tcx: TyCtxt<'tcx>, fn_map: &FnMap) {
         self.visit_times += 1;
-        if self.visit_times > VISIT_LIMIT {
+        if self.visit_times > visit_limit() {
             return;
         }
+        self.path.push(bb_index);
         let cur_block = self.blocks[self.scc_indices[bb_index]].clone();
         self.alias_bb(self.scc_indices[bb_index], tcx);
         self.alias_bbcall(self.scc_indices[bb_index], tcx, fn_map);
@@ -129,6 +161,11 @@ impl<'tcx> SafeDropGraph<'tcx> {
             for enum_index in cur_targets.all_targets() {
                 let backup_values = self.values.clone();
                 let backup_constant = self.constant.clone();
+                let backup_alias_set = self.alias_set.clone();
+                let backup_dead = self.dead_record.clone();
+                let backup_trace = self.trace.len();
+                let backup_path = self.path.len();
+                let backup_cond = self.path_cond.clone();
 
                 let mut block_node = if bb_index == init_index {
                     init_block.clone()
@@ -230,10 +267,13 @@ impl<'tcx> SafeDropGraph<'tcx> {
                     self.alias_bbcall(i, tcx, fn_map);
                     self.drop_check(i, tcx);
                 }
+                self.path_scc(&[init_index]);
+                self.path_scc(&block_node.scc_sub_blocks);
                 /* Reach a leaf node, check bugs */
                 match block_node.next.len() {
                     0 => {
                         // check the bugs.
+                        self.record_dangling(&cur_block);
                         if Self::should_check(self.def_id) {
                             self.dp_check(&cur_block);
                         }
@@ -252,6 +292,11 @@ impl<'tcx> SafeDropGraph<'tcx> {
 
                 self.values = backup_values;
                 self.constant = backup_constant;
+                self.alias_set = backup_alias_set;
+                self.dead_record = backup_dead;
+                self.trace.truncate(backup_trace);
+                self.path.truncate(backup_path);
+                self.path_cond = backup_cond;
             }
 
             return;
@@ -264,12 +309,17 @@ impl<'tcx> SafeDropGraph<'tcx> {
                 self.alias_bbcall(i, tcx, fn_map);
                 self.drop_check(i, tcx);
             }
+            self.path_scc(&[self.scc_indices[bb_index]]);
+            self.path_scc(&cur_block.scc_sub_blocks);
+        } else {
+            self.path_block(self.scc_indices[bb_index]);
         }
 
         /* Reach a leaf node, check bugs */
         match cur_block.next.len() {
             0 => {
                 // check the bugs.
+                self.record_dangling(&cur_block);
                 if Self::should_check(self.def_id) {
                     self.dp_check(&cur_block);
                 }
@@ -351,12 +401,13 @@ impl<'tcx> SafeDropGraph<'tcx> {
             // Other cases in switchInt terminators
             if let Some(targets) = sw_targets {
                 for iter in targets.iter() {
-                    if self.visit_times > VISIT_LIMIT {
+                    if self.visit_times > visit_limit() {
                         continue;
                     }
                     let next_index = iter.1.as_usize();
                     let path_discr_val = iter.0 as usize;
                     self.split_check_with_cond(
+                        bb_index,
                         next_index,
                         path_discr_id,
                         path_discr_val,
@@ -367,14 +418,21 @@ impl<'tcx> SafeDropGraph<'tcx> {
                 let all_targets = targets.all_targets();
                 let next_index = all_targets[all_targets.len() - 1].as_usize();
                 let path_discr_val = usize::MAX; // to indicate the default path;
-                self.split_check_with_cond(next_index, path_discr_id, path_discr_val, tcx, fn_map);
+                self.split_check_with_cond(
+                    bb_index,
+                    next_index,
+                    path_discr_id,
+                    path_discr_val,
+                    tcx,
+                    fn_map,
+                );
             } else {
                 for i in cur_block.next {
-                    if self.visit_times > VISIT_LIMIT {
+                    if self.visit_times > visit_limit() {
                         continue;
                     }
                     let next_index = i;
-                    self.split_check(next_index, tcx, fn_map);
+                    self.split_check(self.scc_indices[bb_index], next_index, tcx, fn_map);
                 }
             }
         }
@@ -0,0 +1,132 @@
+use rustc_hir::def_id::DefId;
+use rustc_span::Span;
+
+use super::graph::*;
+use crate::utils::report::Trace;
+
+/// How two nodes become aliases.
+#[derive(Debug, Copy, Clone)]
+pub enum AliasSource {
+    Assign,
+    /// The alias is added by the `FnRetAlias` summary of the callee.
+    Summary(DefId),
+    /// The callee has no summary, and the returned pointer is assumed to alias the only argument
+    /// that may be dropped.
+    Call(DefId),
+    /// The callee duplicates the ownership of the pointee, e.g., `ptr::read`.
+    Duplicate(DefId),
+}
+
+/// An event on the path being checked, which is kept to explain the bugs found on the path.
+#[derive(Debug, Clone)]
+pub enum TraceEvent {
+    /// The node is assigned, e.g., by the return value of `Box::new`.
+    Birth { node: usize, span: Span },
+    Alias {
+        lv: usize,
+        rv: usize,
+        span: Span,
+        source: AliasSource,
+    },
+    /// The node is dropped, possibly by a callee that leaves it dangling.
+    Drop {
+        node: usize,
+        span: Span,
+        callee: Option<DefId>,
+    },
+}
+
+impl TraceEvent {
+    pub fn span(&self) -> Span {
+        match self {
+            TraceEvent::Birth { span, .. }
+            | TraceEvent::Alias { span, .. }
+            | TraceEvent::Drop { span, .. } => *span,
+        }
+    }
+}
+
+impl SafeDropGraph<'_> {
+    pub fn record(&mut self, event: TraceEvent) {
+        self.trace.push(event);
+    }
+
+    /*
+     * The trace of a bug found at node, where dead is the node that has been dropped.
+     * It contains the first birth of the dropped value, the alias steps linking the two nodes,
+     * and the first drop of the dead node, in the order they happen on the current path.
+     */
+    pub fn bug_trace(&mut self, node: usize, dead: usize) -> Trace {
+        let roots = [self.union_find(node), self.union_find(dead)];
+        let mut trace = Trace {
+            steps: Vec::new(),
+            blocks: self.path.clone(),
+        };
+        let mut born = false;
+        let mut dropped = false;
+        for event in self.trace.clone() {
+            let (span, message) = match event {
+                TraceEvent::Birth { node, span } => {
+                    if born || !roots.contains(&self.union_find(node)) {
+                        continue;
+                    }
+                    born = true;
+                    (span, "The heap item is created here.".to_string())
+                }
+                TraceEvent::Alias {
+                    lv,
+                    rv,
+                    span,
+                    source,
+                } => {
+                    if !roots.contains(&self.union_find(lv))
+                        && !roots.contains(&self.union_find(rv))
+                    {
+                        continue;
+                    }
+                    let message = match source {
+                        AliasSource::Assign => "Aliased here.".to_string(),
+                        AliasSource::Summary(callee) => format!(
+                            "Aliased by the summary of `{}`.",
+                            self.tcx.def_path_str(callee)
+                        ),
+                        AliasSource::Duplicate(callee) => {
+                            format!("Duplicated by `{}`.", self.tcx.def_path_str(callee))
+                        }
+                        AliasSource::Call(callee) => format!(
+                            "Assumed to be aliased by the return value of `{}`.",
+                            self.tcx.def_path_str(callee)
+                        ),
+                    };
+                    (span, message)
+                }
+                TraceEvent::Drop { node, span, callee } => {
+                    if dropped || node != dead {
+                        continue;
+                    }
+                    dropped = true;
+                    let message = match callee {
+                        Some(callee) => format!(
+                            "Dropped by `{}` for the first time.",
+                            self.tcx.def_path_str(callee)
+                        ),
+                        None => "Dropped here for the first time.".to_string(),
+                    };
+                    (span, message)
+                }
+            };
+            // Steps in macros or out of the function cannot be shown in the snippet, and a step is
+            // shown once, e.g., the borrow and the assignment of `p = &x` are aliased both.
+            if span.from_expansion()
+                || !self.span.contains(span)
+                || trace.steps.iter().any(|step| {
+                    step.span == span || (step.message == message && step.span.overlaps(span))
+                })
+            {
+                continue;
+            }
+            trace.step(span, message);
+        }
+        trace
+    }
+}
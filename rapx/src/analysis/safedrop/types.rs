@@ -1,28 +1,32 @@
-use super::corner_handle::is_corner_adt;
 use rustc_middle::ty;
 use rustc_middle::ty::{Ty, TyCtxt};
+use rustc_span::sym;
 
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum TyKind {
     Adt,
     RawPtr,
     Tuple,
-    CornerCase,
     Ref,
+    /// `Rc` and `Arc`, whose payload is dropped with the last of the pointers sharing it.
+    Counted,
+    /// `rc::Weak` and `sync::Weak`, which do not own the payload.
+    Weak,
+    /// `cell::Ref` and `cell::RefMut`, whose drop only releases the borrow of the cell.
+    Guard,
 }
 
-pub fn kind<'tcx>(current_ty: Ty<'tcx>) -> TyKind {
+pub fn kind<'tcx>(tcx: TyCtxt<'tcx>, current_ty: Ty<'tcx>) -> TyKind {
     match current_ty.kind() {
         ty::RawPtr(..) => TyKind::RawPtr,
         ty::Ref(..) => TyKind::Ref,
         ty::Tuple(..) => TyKind::Tuple,
-        ty::Adt(ref adt_def, _) => {
-            if is_corner_adt(format!("{:?}", adt_def)) {
-                return TyKind::CornerCase;
-            } else {
-                return TyKind::Adt;
-            }
-        }
+        ty::Adt(ref adt_def, _) => match tcx.get_diagnostic_name(adt_def.did()) {
+            Some(sym::Rc | sym::Arc) => TyKind::Counted,
+            Some(sym::RcWeak | sym::ArcWeak) => TyKind::Weak,
+            Some(sym::RefCellRef | sym::RefCellRefMut) => TyKind::Guard,
+            _ => TyKind::Adt,
+        },
         _ => TyKind::Adt,
     }
 }
@@ -7,6 +7,8 @@ use crate::analysis::unsafety_isolation::{
     hir_visitor::{ContainsUnsafe, RelatedFnCollector},
     UnsafetyIsolationCheck,
 };
+use crate::rap_debug;
+use crate::utils::report::{self, Finding, FindingKind};
 use rustc_hir::def_id::DefId;
 use rustc_middle::ty::TyCtxt;
 use visitor::{BodyVisitor, CheckResult};
@@ -29,6 +31,9 @@ impl<'tcx> SenryxCheck<'tcx> {
                 let (function_unsafe, block_unsafe) =
                     ContainsUnsafe::contains_unsafe(self.tcx, *body_id);
                 let def_id = hir_map.body_owner_def_id(*body_id).to_def_id();
+                if !report::in_scope(self.tcx, def_id) {
+                    continue;
+                }
                 if block_unsafe {
                     self.check_soundness(def_id);
                 }
@@ -42,14 +47,14 @@ impl<'tcx> SenryxCheck<'tcx> {
     pub fn check_soundness(&self, def_id: DefId) {
         let check_results = self.body_visit_and_check(def_id);
         if check_results.len() > 0 {
-            Self::show_check_results(def_id, check_results);
+            self.show_check_results(def_id, check_results);
         }
     }
 
     pub fn annotate_safety(&self, def_id: DefId) {
         let check_results = self.body_visit_and_check(def_id);
         if check_results.len() > 0 {
-            Self::show_check_results(def_id, check_results);
+            self.show_check_results(def_id, check_results);
         }
     }
 
@@ -75,18 +80,40 @@ impl<'tcx> SenryxCheck<'tcx> {
         return body_visitor.check_results;
     }
 
-    pub fn show_check_results(def_id: DefId, check_results: Vec<CheckResult>) {
-        println!("--------In {:?}---------", def_id);
+    pub fn show_check_results(&self, def_id: DefId, check_results: Vec<CheckResult>) {
+        let body_span = self.tcx.optimized_mir(def_id).span;
+        let fn_name = self.tcx.def_path_str(def_id);
         for check_result in check_results {
-            println!(
-                "  Unsafe api {:?}: {} passed, {} failed!",
-                check_result.func_name,
-                check_result.passed_contracts.len(),
-                check_result.failed_contracts.len()
-            );
+            if check_result.failed_contracts.is_empty() {
+                rap_debug!(
+                    "Unsafe api {:?} in {}: {} contract(s) passed.",
+                    check_result.func_name,
+                    fn_name,
+                    check_result.passed_contracts.len()
+                );
+                continue;
+            }
+            let mut finding = Finding::new(FindingKind::ContractFailure, def_id, body_span)
+                .summary(format!(
+                    "Contract failure detected in function {:}",
+                    fn_name
+                ))
+                .message(format!(
+                    "Contract of unsafe api {} failed.",
+                    check_result.func_name
+                ))
+                .help(format!(
+                    "{} contract(s) passed, {} failed.",
+                    check_result.passed_contracts.len(),
+                    check_result.failed_contracts.len()
+                ));
             for failed_contract in check_result.failed_contracts {
-                println!("      Contract failed: {:?}", failed_contract);
+                finding = finding.primary(
+                    check_result.func_span,
+                    format!("Contract failed: {:?}", failed_contract),
+                );
             }
+            report::emit(finding);
         }
     }
 }
@@ -1,16 +1,224 @@
+use rustc_data_structures::fx::FxHashMap;
+use rustc_hir::def_id::DefId;
+use rustc_hir::LangItem;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::Symbol;
+use std::sync::OnceLock;
+
+use super::def_path::def_path_def_ids;
+use crate::rap_debug;
+
 /*
- * NOTE: This should be updated with different rustc versions.
+ * Functions with special semantics in the analyses.
+ * They are resolved by lang items, diagnostic items or def paths at startup,
+ * so that they do not depend on the DefIndex of a specific rustc version.
  */
-pub const DROP: usize = 2177;
-pub const DROP_IN_PLACE: usize = 2744;
-pub const BOX_DROP_IN_PLACE: usize = 2699;
-pub const ASSUME_INIT_DROP: usize = 2058;
-pub const DEALLOC: usize = 137;
-pub const MANUALLYDROP: usize = 2018;
-pub const CALL_MUT: usize = 3575;
-pub const NEXT: usize = 8583;
-pub const CLONE: usize = 2913;
-pub const COPY_FROM_NONOVERLAPPING: usize = 2698;
-pub const COPY_TO_NONOVERLAPPING: usize = 2598;
-pub const COPY_TO: usize = 2597;
-pub const COPY_FROM: usize = 2697;
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Intrinsic {
+    /// `core::mem::drop`
+    Drop,
+    /// `core::ptr::drop_in_place` and `<*mut T>::drop_in_place`
+    DropInPlace,
+    /// `MaybeUninit::assume_init_drop`
+    AssumeInitDrop,
+    /// `alloc::alloc::{alloc, alloc_zeroed}` and the allocation methods of `GlobalAlloc` and `Allocator`
+    Alloc,
+    /// `alloc::alloc::dealloc`, `GlobalAlloc::dealloc` and `Allocator::deallocate`
+    Dealloc,
+    /// `alloc::alloc::realloc`, `GlobalAlloc::realloc` and `Allocator::{grow, grow_zeroed, shrink}`,
+    /// which free the old pointer and return a new allocation
+    Realloc,
+    /// `ManuallyDrop::drop`
+    ManuallyDropDrop,
+    /// `FnMut::call_mut`
+    CallMut,
+    /// `Iterator::next`
+    Next,
+    /// `Clone::clone`
+    Clone,
+    /// `core::ptr::copy`, `core::ptr::copy_nonoverlapping` and the `copy_*` methods of raw pointers
+    Copy,
+    /// `core::ptr::write` and its variants, which initialize the pointee again, e.g., after it is
+    /// dropped in place
+    Write,
+    /// `core::ptr::read` and its variants, and `core::mem::transmute_copy`, which duplicate
+    /// the ownership of the pointee
+    Read,
+}
+
+enum Source {
+    Lang(LangItem),
+    Diagnostic(&'static str),
+    Path(&'static str),
+}
+
+const SOURCES: &[(Intrinsic, Source)] = &[
+    (Intrinsic::Drop, Source::Diagnostic("mem_drop")),
+    (Intrinsic::Drop, Source::Path("core::mem::drop")),
+    (Intrinsic::DropInPlace, Source::Lang(LangItem::DropInPlace)),
+    (
+        Intrinsic::DropInPlace,
+        Source::Path("mut_ptr::drop_in_place"),
+    ),
+    (
+        Intrinsic::AssumeInitDrop,
+        Source::Path("core::mem::MaybeUninit::assume_init_drop"),
+    ),
+    (Intrinsic::Alloc, Source::Path("alloc::alloc::alloc")),
+    (Intrinsic::Alloc, Source::Path("alloc::alloc::alloc_zeroed")),
+    (
+        Intrinsic::Alloc,
+        Source::Path("core::alloc::GlobalAlloc::alloc"),
+    ),
+    (
+        Intrinsic::Alloc,
+        Source::Path("core::alloc::GlobalAlloc::alloc_zeroed"),
+    ),
+    (
+        Intrinsic::Alloc,
+        Source::Path("core::alloc::Allocator::allocate"),
+    ),
+    (
+        Intrinsic::Alloc,
+        Source::Path("core::alloc::Allocator::allocate_zeroed"),
+    ),
+    (Intrinsic::Dealloc, Source::Path("alloc::alloc::dealloc")),
+    (
+        Intrinsic::Dealloc,
+        Source::Path("core::alloc::GlobalAlloc::dealloc"),
+    ),
+    (
+        Intrinsic::Dealloc,
+        Source::Path("core::alloc::Allocator::deallocate"),
+    ),
+    (Intrinsic::Realloc, Source::Path("alloc::alloc::realloc")),
+    (
+        Intrinsic::Realloc,
+        Source::Path("core::alloc::GlobalAlloc::realloc"),
+    ),
+    (
+        Intrinsic::Realloc,
+        Source::Path("core::alloc::Allocator::grow"),
+    ),
+    (
+        Intrinsic::Realloc,
+        Source::Path("core::alloc::Allocator::grow_zeroed"),
+    ),
+    (
+        Intrinsic::Realloc,
+        Source::Path("core::alloc::Allocator::shrink"),
+    ),
+    (
+        Intrinsic::ManuallyDropDrop,
+        Source::Path("core::mem::ManuallyDrop::drop"),
+    ),
+    (
+        Intrinsic::CallMut,
+        Source::Path("core::ops::FnMut::call_mut"),
+    ),
+    (Intrinsic::Next, Source::Lang(LangItem::IteratorNext)),
+    (Intrinsic::Clone, Source::Lang(LangItem::CloneFn)),
+    (Intrinsic::Copy, Source::Diagnostic("ptr_copy")),
+    (
+        Intrinsic::Copy,
+        Source::Diagnostic("ptr_copy_nonoverlapping"),
+    ),
+    (Intrinsic::Copy, Source::Path("const_ptr::copy_to")),
+    (
+        Intrinsic::Copy,
+        Source::Path("const_ptr::copy_to_nonoverlapping"),
+    ),
+    (Intrinsic::Copy, Source::Path("mut_ptr::copy_to")),
+    (
+        Intrinsic::Copy,
+        Source::Path("mut_ptr::copy_to_nonoverlapping"),
+    ),
+    (Intrinsic::Copy, Source::Path("mut_ptr::copy_from")),
+    (
+        Intrinsic::Copy,
+        Source::Path("mut_ptr::copy_from_nonoverlapping"),
+    ),
+    (Intrinsic::Write, Source::Path("core::ptr::write")),
+    (Intrinsic::Write, Source::Path("core::ptr::write_unaligned")),
+    (Intrinsic::Write, Source::Path("core::ptr::write_volatile")),
+    (Intrinsic::Write, Source::Path("mut_ptr::write")),
+    (Intrinsic::Write, Source::Path("mut_ptr::write_unaligned")),
+    (Intrinsic::Write, Source::Path("mut_ptr::write_volatile")),
+    (
+        Intrinsic::Write,
+        Source::Path("core::mem::MaybeUninit::write"),
+    ),
+    (Intrinsic::Read, Source::Path("core::ptr::read")),
+    (Intrinsic::Read, Source::Path("core::ptr::read_unaligned")),
+    (Intrinsic::Read, Source::Path("core::ptr::read_volatile")),
+    (Intrinsic::Read, Source::Path("const_ptr::read")),
+    (Intrinsic::Read, Source::Path("const_ptr::read_unaligned")),
+    (Intrinsic::Read, Source::Path("const_ptr::read_volatile")),
+    (Intrinsic::Read, Source::Path("mut_ptr::read")),
+    (Intrinsic::Read, Source::Path("mut_ptr::read_unaligned")),
+    (Intrinsic::Read, Source::Path("mut_ptr::read_volatile")),
+    (Intrinsic::Read, Source::Path("core::mem::transmute_copy")),
+];
+
+static INTRINSICS: OnceLock<FxHashMap<DefId, Intrinsic>> = OnceLock::new();
+
+/// Resolve the functions of all intrinsics in the current compilation session.
+pub fn init(tcx: TyCtxt<'_>) {
+    INTRINSICS.get_or_init(|| {
+        let mut intrinsics = FxHashMap::default();
+        for (intrinsic, source) in SOURCES {
+            let def_ids: Vec<DefId> = match source {
+                Source::Lang(item) => tcx.lang_items().get(*item).into_iter().collect(),
+                Source::Diagnostic(name) => tcx
+                    .get_diagnostic_item(Symbol::intern(name))
+                    .into_iter()
+                    .collect(),
+                Source::Path(path) => {
+                    let path: Vec<&str> = path.split("::").collect();
+                    def_path_def_ids(&tcx, &path).collect()
+                }
+            };
+            if def_ids.is_empty() {
+                // e.g., `alloc` is not available in `no_std` crates
+                rap_debug!("Failed to resolve {:?} from {}.", intrinsic, source);
+            }
+            for def_id in def_ids {
+                intrinsics.insert(def_id, *intrinsic);
+            }
+        }
+        intrinsics
+    });
+}
+
+/// The intrinsic that `def_id` refers to, if any.
+pub fn intrinsic(def_id: DefId) -> Option<Intrinsic> {
+    INTRINSICS.get()?.get(&def_id).copied()
+}
+
+pub fn is_intrinsic(def_id: DefId, intrinsic_kind: Intrinsic) -> bool {
+    intrinsic(def_id) == Some(intrinsic_kind)
+}
+
+/// Whether the function allocates, frees or reallocates memory.
+pub fn is_allocator(def_id: DefId) -> bool {
+    matches!(
+        intrinsic(def_id),
+        Some(Intrinsic::Alloc | Intrinsic::Dealloc | Intrinsic::Realloc)
+    )
+}
+
+/// The argument freed by a drop or deallocation function, which follows `&self` in trait methods,
+/// e.g., `GlobalAlloc::dealloc(&self, ptr, layout)`.
+pub fn freed_arg(tcx: TyCtxt<'_>, def_id: DefId) -> usize {
+    usize::from(tcx.trait_of_item(def_id).is_some())
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Lang(item) => write!(f, "lang item `{}`", item.name()),
+            Source::Diagnostic(name) => write!(f, "diagnostic item `{name}`"),
+            Source::Path(path) => write!(f, "path `{path}`"),
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use rapx::utils::config::{config, Summaries};
+use rapx::utils::summary::RAP_SUMMARY_DIR;
 use std::{
     env,
     path::{Path, PathBuf},
@@ -12,7 +14,6 @@ struct Arguments {
     /// options as second half after -- in args
     args_group2: Vec<String>,
     current_exe_path: PathBuf,
-    rap_clean: bool,
 }
 
 impl Arguments {
@@ -40,17 +41,6 @@ impl Arguments {
     }
 
     fn new() -> Self {
-        fn rap_clean() -> bool {
-            match env::var("RAP_CLEAN")
-                .ok()
-                .map(|s| s.trim().to_ascii_lowercase())
-                .as_deref()
-            {
-                Some("false") => false,
-                _ => true, // clean is the preferred behavior
-            }
-        }
-
         let args: Vec<_> = env::args().collect();
         let path = env::current_exe().expect("Current executable path invalid.");
         rap_trace!("Current exe: {path:?}\tReceived args: {args:?}");
@@ -61,7 +51,6 @@ impl Arguments {
             args_group1,
             args_group2,
             current_exe_path: path,
-            rap_clean: rap_clean(),
         }
     }
 
@@ -79,7 +68,7 @@ impl Arguments {
 }
 
 pub fn rap_clean() -> bool {
-    ARGS.rap_clean
+    config().is_clean()
 }
 
 fn split_args_by_double_dash(args: &[String]) -> [Vec<String>; 2] {
@@ -123,6 +112,19 @@ pub fn filter_crate_type() -> bool {
     true
 }
 
+/// Returns true if the dependency being compiled in rustc phase should be summarized.
+/// Cargo caps the lints of dependencies from registries and git, but not those of path dependencies.
+pub fn is_summarized_dependency() -> bool {
+    if env::var_os(RAP_SUMMARY_DIR).is_none() || get_arg_flag_value("--crate-name").is_none() {
+        return false;
+    }
+    match config().summaries.unwrap_or_default() {
+        Summaries::None => false,
+        Summaries::Local => get_arg_flag_value("--cap-lints").is_none(),
+        Summaries::All => true,
+    }
+}
+
 pub fn get_arg(pos: usize) -> Option<&'static str> {
     ARGS.args.get(pos).map(|x| x.as_str())
 }
@@ -1,30 +1,37 @@
 use crate::args;
-use cargo_metadata::camino::Utf8Path;
+use cargo_metadata::camino::{Utf8Path, Utf8PathBuf};
+use cargo_metadata::Metadata;
+use rapx::utils::cache::{RAP_CACHE_DIR, RAP_RUN_ID};
+use rapx::utils::config::{config, Config, Recursive, Summaries, RAP_CONFIG};
 use rapx::utils::log::rap_error_and_exit;
-use std::{env, process::Command, time::Duration};
+use rapx::utils::summary::RAP_SUMMARY_DIR;
+use std::{
+    process::Command,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use wait_timeout::ChildExt;
 
+mod report;
 mod workspace;
 
 pub fn run() {
-    match env::var("RAP_RECURSIVE")
-        .ok()
-        .map(|s| s.trim().to_ascii_lowercase())
-        .as_deref()
-    {
-        Some("none") | None => default_run(),
-        Some("deep") => workspace::deep_run(),
-        Some("shallow") => workspace::shallow_run(),
-        _ => rap_error_and_exit(
-            "`recursive` should only accept one the values: none, shallow or deep.",
-        ),
+    let reports = report::Reports::prepare();
+
+    match config().recursive.unwrap_or_default() {
+        Recursive::None => default_run(),
+        Recursive::Deep => workspace::deep_run(),
+        Recursive::Shallow => workspace::shallow_run(),
     }
+
+    reports.finish();
 }
 
 fn cargo_check(dir: &Utf8Path) {
-    // always clean before check due to outdated except `RAP_CLEAN` is false
+    // local crates are always analyzed again, so cleaning is only needed if requested,
+    // or if the dependencies are compiled without the summaries being enabled
+    let summaries = config().summaries.unwrap_or_default();
     rap_trace!("cargo clean in package folder {dir}");
-    cargo_clean(dir, args::rap_clean());
+    cargo_clean(dir, args::rap_clean() || !is_summarized(dir, summaries));
 
     rap_trace!("cargo check in package folder {dir}");
     let [rap_args, cargo_args] = args::rap_and_cargo_args();
@@ -44,9 +51,20 @@ fn cargo_check(dir: &Utf8Path) {
 
     cmd.env(
         "RAP_ARGS",
-        serde_json::to_string(rap_args).expect("Failed to serialize args."),
+        serde_json::to_string(&report::rap_args(rap_args)).expect("Failed to serialize args."),
     );
 
+    if let Some(config) = package_config(dir) {
+        cmd.env(RAP_CONFIG, config.to_json());
+    }
+    cmd.env(RAP_RUN_ID, run_id());
+    if config().is_cached() {
+        cmd.env(RAP_CACHE_DIR, cache_dir(dir));
+    }
+    if summaries != Summaries::None {
+        cmd.env(RAP_SUMMARY_DIR, cache_dir(dir).join("summaries"));
+    }
+
     // Invoke actual cargo for the job, but with different flags.
     let cargo_rap_path = args::current_exe_path();
     cmd.env("RUSTC_WRAPPER", cargo_rap_path);
@@ -62,6 +80,7 @@ fn cargo_check(dir: &Utf8Path) {
             if !status.success() {
                 rap_error_and_exit("Finished with non-zero exit code.");
             }
+            mark_summarized(dir, summaries);
         }
         None => {
             child.kill().expect("Failed to kill subprocess.");
@@ -71,6 +90,79 @@ fn cargo_check(dir: &Utf8Path) {
     };
 }
 
+/// A different value in each run, so that cargo checks the local crates again.
+fn run_id() -> String {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos());
+    format!("{}-{time}", std::process::id())
+}
+
+fn metadata(dir: &Utf8Path) -> Result<Metadata, cargo_metadata::Error> {
+    cargo_metadata::MetadataCommand::new()
+        .current_dir(dir)
+        .no_deps()
+        .exec()
+}
+
+/// The analysis cache and the summaries of dependencies are kept in `rapx` under the target folder
+/// of the package.
+fn cache_dir(dir: &Utf8Path) -> Utf8PathBuf {
+    match metadata(dir) {
+        Ok(metadata) => metadata.target_directory.join("rapx"),
+        Err(err) => rap_error_and_exit(format!("Failed to get the target folder of {dir}:\n{err}")),
+    }
+}
+
+/// Read the configuration of the package in `dir`, or of a folder above it up to the workspace root.
+/// Outside of a package, only `dir` is searched.
+pub fn find_config(dir: &Utf8Path) -> Result<Option<Config>, String> {
+    let root = metadata(dir).map_or_else(|_| dir.to_owned(), |metadata| metadata.workspace_root);
+    let root = std::path::absolute(&root).unwrap_or_else(|_| root.into());
+    Config::resolve(dir.as_std_path(), &root)
+}
+
+/// The configuration of the package checked in `dir`, unless one is given by `-config`.
+/// The members of a workspace checked by `RAP_RECURSIVE` may have their own configurations.
+fn package_config(dir: &Utf8Path) -> Option<Config> {
+    if args::get_arg_flag_value("-config").is_some() {
+        return None;
+    }
+    match find_config(dir) {
+        Ok(config) => config.map(Config::with_env),
+        Err(err) => rap_error_and_exit(err),
+    }
+}
+
+/// The file recording which dependencies are summarized in the target folder.
+fn summaries_stamp(dir: &Utf8Path) -> Utf8PathBuf {
+    cache_dir(dir).join("summaries").join("summaries.stamp")
+}
+
+/// Whether the dependencies in the target folder are compiled with the same summaries enabled.
+/// Cargo does not compile a dependency again once it is built, so a dependency built before
+/// is never summarized unless the target folder is cleaned.
+fn is_summarized(dir: &Utf8Path, summaries: Summaries) -> bool {
+    if summaries == Summaries::None {
+        return true;
+    }
+    let recorded = std::fs::read_to_string(summaries_stamp(dir)).ok();
+    recorded.as_deref() == Some(summaries.name())
+}
+
+fn mark_summarized(dir: &Utf8Path, summaries: Summaries) {
+    if summaries == Summaries::None {
+        return;
+    }
+    let stamp = summaries_stamp(dir);
+    if let Some(parent) = stamp.parent() {
+        _ = std::fs::create_dir_all(parent);
+    }
+    if let Err(err) = std::fs::write(&stamp, summaries.name()) {
+        rap_debug!("Failed to write {stamp}: {err}");
+    }
+}
+
 fn cargo_clean(dir: &Utf8Path, really: bool) {
     if really {
         if let Err(err) = Command::new("cargo").arg("clean").current_dir(dir).output() {
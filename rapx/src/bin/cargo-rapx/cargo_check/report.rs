@@ -0,0 +1,160 @@
+use crate::args;
+use rapx::analysis::core::heap_item::export;
+use rapx::utils::config::config;
+use rapx::utils::log::rap_error_and_exit;
+use rapx::utils::report::policy::{self, DENIED_EXIT_CODE};
+use rapx::utils::report::{baseline, json, outcome, sarif, OutputFormat, RAP_REPORT_DIR};
+use std::{
+    env, fs,
+    path::{self, Path, PathBuf},
+    process,
+};
+
+/// Reports written by each checked crate, which are merged once all crates are checked.
+pub struct Reports {
+    format: OutputFormat,
+    /// The absolute path of the baseline and whether it is being recorded in this run.
+    baseline: Option<(PathBuf, bool)>,
+    /// Each checked crate writes its report into this folder.
+    dir: Option<PathBuf>,
+}
+
+impl Reports {
+    pub fn prepare() -> Self {
+        let format = output_format();
+        let policy = match config().policy(
+            args::get_arg_flag_value("-deny"),
+            args::get_arg_flag_value("-fail-on"),
+        ) {
+            Ok(policy) => policy.is_active(),
+            Err(err) => rap_error_and_exit(err),
+        };
+        let baseline = baseline_path().map(|path| {
+            let recording = !path.exists();
+            (path, recording)
+        });
+        let heap_item = args::rap_and_cargo_args()[0]
+            .iter()
+            .any(|arg| arg == "-heap-item" || arg == "-adt");
+        let dir = if format.extension().is_some() || baseline.is_some() || policy || heap_item {
+            let dir = env::temp_dir().join(format!("rapx-report-{}", process::id()));
+            if let Err(err) = fs::create_dir_all(&dir) {
+                rap_error_and_exit(format!("Failed to create {}: {err}", dir.display()));
+            }
+            env::set_var(RAP_REPORT_DIR, &dir);
+            Some(dir)
+        } else {
+            None
+        };
+        Reports {
+            format,
+            baseline,
+            dir,
+        }
+    }
+
+    pub fn finish(self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        if let Some(ext) = self.format.extension() {
+            let reports = read_reports(dir, ext);
+            let content = match self.format {
+                OutputFormat::Json => json::merge(&reports),
+                OutputFormat::Sarif => sarif::merge(&reports),
+                OutputFormat::Text => unreachable!(),
+            };
+            let output = self.format.default_file().unwrap();
+            write(Path::new(&output), content);
+            rap_info!(
+                "Report of {} crate(s) written into {output}.",
+                reports.len()
+            );
+        }
+        // the JSON arrays written besides the findings, e.g., the outcomes of the solver
+        for ext in [outcome::EXTENSION, export::EXTENSION] {
+            let reports = read_reports(dir, ext);
+            if !reports.is_empty() {
+                let output = format!("rapx.{ext}.json");
+                write(Path::new(&output), json::merge(&reports));
+                rap_info!(
+                    "Results of {} crate(s) written into {output}.",
+                    reports.len()
+                );
+            }
+        }
+        if let Some((path, true)) = &self.baseline {
+            let reports = read_reports(dir, baseline::EXTENSION);
+            let content = json::merge(&reports);
+            let count = baseline::count(&content);
+            write(path, content);
+            rap_info!(
+                "{count} finding(s) recorded into the baseline {}.",
+                path.display()
+            );
+        }
+        let denied: usize = read_reports(dir, policy::EXTENSION)
+            .iter()
+            .filter_map(|report| report.trim().parse::<usize>().ok())
+            .sum();
+        _ = fs::remove_dir_all(dir);
+        if denied > 0 {
+            if let Some((_, false)) = &self.baseline {
+                rap_error!("{denied} new finding(s) not in the baseline.");
+            } else {
+                rap_error!("{denied} finding(s) denied.");
+            }
+            process::exit(DENIED_EXIT_CODE);
+        }
+    }
+}
+
+fn output_format() -> OutputFormat {
+    match args::get_arg_flag_value("-format") {
+        Some(name) => OutputFormat::from_name(name)
+            .unwrap_or_else(|| rap_error_and_exit(format!("Unknown output format: {name}"))),
+        None => config().format.unwrap_or(OutputFormat::Text),
+    }
+}
+
+fn baseline_path() -> Option<PathBuf> {
+    let path = args::get_arg_flag_value("-baseline")?;
+    match path::absolute(path) {
+        Ok(path) => Some(path),
+        Err(err) => rap_error_and_exit(format!("Invalid baseline path {path}: {err}")),
+    }
+}
+
+/// Arguments forwarded to rapx for each crate.
+/// The baseline is passed as an absolute path since crates may be checked in other folders.
+pub fn rap_args(rap_args: &[String]) -> Vec<String> {
+    rap_args
+        .iter()
+        .map(|arg| match arg.strip_prefix("-baseline=") {
+            Some(_) => format!("-baseline={}", baseline_path().unwrap().display()),
+            None => arg.clone(),
+        })
+        .collect()
+}
+
+fn read_reports(dir: &Path, ext: &str) -> Vec<String> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|e| e == ext))
+                .collect()
+        })
+        .unwrap_or_default();
+    paths.sort();
+    paths
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .collect()
+}
+
+fn write(path: &Path, content: String) {
+    if let Err(err) = fs::write(path, content) {
+        rap_error_and_exit(format!("Failed to write {}: {err}", path.display()));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::args;
 use cargo_metadata::{
     camino::{Utf8Path, Utf8PathBuf},
     Metadata,
@@ -24,11 +25,11 @@ pub fn deep_run() {
 }
 
 fn check_members(ws_metadata: &Metadata) {
-    // Force clean even if `RAP_CLEAN` is false, because rapx is in control of
-    // caches for all packages and there should be no cache.
+    // Members are analyzed again in each run, and their dependencies are reused,
+    // so the workspace is only cleaned if requested.
     let ws_root = &ws_metadata.workspace_root;
     rap_trace!("cargo clean in workspace root {ws_root}");
-    super::cargo_clean(ws_root, true);
+    super::cargo_clean(ws_root, args::rap_clean());
 
     for pkg_folder in get_member_folders(ws_metadata) {
         super::cargo_check(pkg_folder);
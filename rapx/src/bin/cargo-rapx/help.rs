@@ -10,8 +10,30 @@ Use-After-Free/double free detection.
 Memory leakage detection.
     -M or -mleak     command: "cargo rapx -mleak"
 
+Exit code options (exit with code 2 if any finding is denied):
+    -deny=<names>    deny findings of the comma-separated detectors or kinds of bugs,
+                     e.g., -deny=uaf,leak; the names are the same as those of suppressions
+    -fail-on=<level> deny findings at or above the severity: error, warning, note
+
+Configuration:
+    -config=<file>   read the configuration from <file> instead of rapx.toml
+                     or [package.metadata.rapx] in Cargo.toml
+
+Report options:
+    -format=sarif    also write the findings into rapx.sarif (SARIF 2.1)
+    -format=json     also write the findings into rapx.json, one object per finding
+    -baseline=<file> record the findings into <file> if it does not exist; otherwise, only
+                     report findings not in <file> and fail if there are any
+    Findings can be suppressed by `#[rapx::allow(uaf, reason = "...")]` or `// rapx-allow: leak -- reason`
+    on a function, an impl block or a module.
+    With -format=json or -format=sarif, how each function is checked by the solver of rCanary
+    is written into rapx.outcomes.json.
+
 Debugging options:
     -mir             print the MIR of each function
+    -heap-item       print whether each ADT owns heap memory and why, and write the ownership
+                     of the ADTs into rapx.heap_items.json; -adt is an alias
+    -alias=mop       print the places that the return value of each function may alias
 
 General command: 
     -H or -help:     show help information
@@ -42,14 +64,16 @@ e.g.
 3. detect use-after-free and memory leak for all members:
    cargo rapx -F -M -- --workspace
 
-Environment Variables (Values are case insensitive):
+Environment Variables (Values are case insensitive; they override the configuration):
     RAP_LOG          verbosity of logging: trace, debug, info, warn
                      trace: print all the detailed RAP execution traces.
                      debug: display intermidiate analysis results.
                      warn: show bugs detected only.
 
     RAP_CLEAN        run cargo clean before check: true, false
-                     * true is the default value except that false is set
+                     * false is the default value; local crates are always analyzed
+                       again, and the analysis results are cached under target/rapx
+                     * any value other than false cleans the target folder
 
     RAP_RECURSIVE    scope of packages to check: none, shallow, deep
                      * none or the variable not set: check for current folder
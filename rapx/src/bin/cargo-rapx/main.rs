@@ -7,7 +7,9 @@
 #[macro_use]
 extern crate rapx;
 
-use rapx::utils::log::{init_log, rap_error_and_exit};
+use rapx::utils::config::{config, set_config, Config};
+use rapx::utils::log::{init_log, rap_error_and_exit, set_log_level};
+use std::path::Path;
 
 mod args;
 mod help;
@@ -21,36 +23,67 @@ fn phase_cargo_rap() {
     rap_trace!("Start cargo-rapx.");
 
     // here we skip two args: cargo rapx
-    let Some(arg) = args::get_arg(2) else {
-        rap_error!("Expect command: e.g., `cargo rapx -help`.");
-        return;
-    };
-    match arg {
-        "-V" | "-version" => {
+    match args::get_arg(2) {
+        Some("-V" | "-version") => {
             rap_info!("{}", help::RAPX_VERSION);
             return;
         }
-        "-H" | "-help" | "--help" => {
+        Some("-H" | "-help" | "--help") => {
             rap_info!("{}", help::RAPX_HELP);
             return;
         }
         _ => {}
     }
 
+    init_config();
+    // detectors can also be enabled in the configuration
+    if args::get_arg(2).is_none() && config().detectors.is_empty() {
+        rap_error!("Expect command: e.g., `cargo rapx -help`.");
+        return;
+    }
     cargo_check::run();
 }
 
+/// Read and validate the configuration given by `-config=<file>`, or found from the current folder
+/// up to the workspace root, and export it to the rapx processes spawned by cargo.
+fn init_config() {
+    let config = match args::get_arg_flag_value("-config") {
+        Some(path) => Config::load(Path::new(path)).map(Some),
+        None => cargo_check::find_config(".".into()),
+    };
+    let config = match config {
+        Ok(config) => config.unwrap_or_default().with_env(),
+        Err(err) => rap_error_and_exit(err),
+    };
+    set_log_level(config.log_level());
+    config.export();
+    set_config(config).unwrap_or_else(|err| rap_error_and_exit(err));
+}
+
 fn phase_rustc_wrapper() {
     rap_trace!("Launch cargo-rapx again triggered by cargo check.");
 
+    match Config::import() {
+        Ok(Some(config)) => set_config(config).unwrap_or_else(|err| rap_error_and_exit(err)),
+        Ok(None) => {}
+        Err(err) => rap_error_and_exit(err),
+    }
     let is_direct = args::is_current_compile_crate();
+    let in_scope = args::get_arg_flag_value("--crate-name")
+        .map_or(true, |name| config().scope.contains_crate(name));
     // rapx only checks local crates
-    if is_direct && args::filter_crate_type() {
+    if is_direct && args::filter_crate_type() && in_scope {
         run_rap();
         return;
     }
 
-    // for dependencies and some special crate types, run rustc as usual
+    // dependencies are summarized for the analysis of local crates
+    if !is_direct && args::filter_crate_type() && args::is_summarized_dependency() {
+        run_rap_summary();
+        return;
+    }
+
+    // for other dependencies and some special crate types, run rustc as usual
     run_rustc();
 }
 
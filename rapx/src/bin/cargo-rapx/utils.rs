@@ -38,3 +38,11 @@ pub fn run_rap() {
     cmd.args(rap_args);
     run_cmd(cmd);
 }
+
+/// Compile a dependency with rapx, which only writes its summaries.
+pub fn run_rap_summary() {
+    let mut cmd = Command::new(find_rap());
+    cmd.args(args::skip2());
+    cmd.arg("-summary");
+    run_cmd(cmd);
+}
@@ -4,11 +4,32 @@ extern crate rustc_driver;
 extern crate rustc_session;
 
 use rapx::{
-    compile_time_sysroot, rap_info, rap_trace, utils::log::init_log, RapCallback, RAP_DEFAULT_ARGS,
+    compile_time_sysroot, rap_crate_args, rap_debug, rap_info, rap_trace,
+    utils::config::{config, set_config, Config, DetectorName},
+    utils::log::{init_log, rap_error_and_exit, set_log_level},
+    utils::report::{self, policy::DENIED_EXIT_CODE, OutputFormat},
+    RapCallback, RAP_DEFAULT_ARGS,
 };
 use rustc_session::config::ErrorOutputType;
 use rustc_session::EarlyDiagCtxt;
 use std::env;
+use std::path::Path;
+
+/// Use the configuration exported by cargo-rapx, or read the one given by `-config=<file>`.
+fn init_config() {
+    let config = Config::import().unwrap_or_else(|err| rap_error_and_exit(err));
+    let config = config.unwrap_or_else(|| {
+        match env::args().find_map(|arg| arg.strip_prefix("-config=").map(str::to_owned)) {
+            Some(path) => {
+                Config::load(Path::new(&path)).unwrap_or_else(|err| rap_error_and_exit(err))
+            }
+            None => Config::default(),
+        }
+    });
+    let config = config.with_env();
+    set_log_level(config.log_level());
+    set_config(config).unwrap_or_else(|err| rap_error_and_exit(err));
+}
 
 fn run_complier(args: &mut Vec<String>, callback: &mut RapCallback) -> i32 {
     if let Some(sysroot) = compile_time_sysroot() {
@@ -21,6 +42,10 @@ fn run_complier(args: &mut Vec<String>, callback: &mut RapCallback) -> i32 {
     }
     // Finally, add the default flags all the way in the beginning, but after the binary name.
     args.splice(1..1, RAP_DEFAULT_ARGS.iter().map(ToString::to_string));
+    if !callback.is_summary_enabled() {
+        let crate_root = args.iter().find(|arg| arg.ends_with(".rs")).cloned();
+        args.splice(1..1, rap_crate_args(crate_root.as_deref()));
+    }
 
     let handler = EarlyDiagCtxt::new(ErrorOutputType::default());
     rustc_driver::init_rustc_env_logger(&handler);
@@ -35,8 +60,13 @@ fn run_complier(args: &mut Vec<String>, callback: &mut RapCallback) -> i32 {
 
 fn main() {
     // Parse the arguments from env.
+    _ = init_log().inspect_err(|err| eprintln!("Failed to init log: {err}"));
+    init_config();
     let mut args = vec![];
     let mut compiler = RapCallback::default();
+    let mut format = None;
+    let mut deny = None;
+    let mut fail_on = None;
     for arg in env::args() {
         match arg.as_str() {
             "-F" | "-uaf" => compiler.enable_safedrop(),
@@ -52,17 +82,50 @@ fn main() {
             "-callgraph" => compiler.enable_callgraph(),
             "-O" | "-opt" => compiler.enable_opt(),
             "-mir" => compiler.enable_show_mir(),
-            "-adt" => {}
+            "-summary" => compiler.enable_summary(),
+            "-heap-item" | "-adt" => compiler.enable_heap_item(),
             "-z3" => {}
             "-meta" => {}
+            s if s.starts_with("-format=") => match OutputFormat::from_name(&s[8..]) {
+                Some(name) => format = Some(name),
+                None => rap_error_and_exit(format!("Unknown output format: {}", &s[8..])),
+            },
+            s if s.starts_with("-config=") => {}
+            s if s.starts_with("-deny=") => deny = Some(s[6..].to_owned()),
+            s if s.starts_with("-fail-on=") => fail_on = Some(s[9..].to_owned()),
+            s if s.starts_with("-baseline=") => report::set_baseline(&s[10..]),
             _ => args.push(arg),
         }
     }
-    _ = init_log().inspect_err(|err| eprintln!("Failed to init log: {err}"));
-    rap_info!("Start analysis with RAP.");
+    compiler.set_output_format(format.or(config().format).unwrap_or(OutputFormat::Text));
+    match config().policy(deny.as_deref(), fail_on.as_deref()) {
+        Ok(policy) => report::set_policy(policy),
+        Err(err) => rap_error_and_exit(err),
+    }
+    // Detectors in the configuration are only used if no analysis is given on the command line.
+    if !compiler.has_analysis() {
+        for detector in &config().detectors {
+            match detector {
+                DetectorName::SafeDrop => compiler.enable_safedrop(),
+                DetectorName::RCanary => compiler.enable_rcanary(),
+                DetectorName::Senryx => compiler.enable_senryx(),
+                DetectorName::Opt => compiler.enable_opt(),
+            }
+        }
+    }
+    if compiler.is_summary_enabled() {
+        rap_debug!("Summarize the dependency with RAP.");
+    } else {
+        rap_info!("Start analysis with RAP.");
+    }
     rap_trace!("rap received arguments{:#?}", env::args());
     rap_trace!("arguments to rustc: {:?}", &args);
 
-    let exit_code = run_complier(&mut args, &mut compiler);
+    let mut exit_code = run_complier(&mut args, &mut compiler);
+    // Under cargo-rapx, denied findings are counted by cargo-rapx itself after all crates are checked.
+    if exit_code == 0 && report::denied_findings() > 0 && env::var(report::RAP_REPORT_DIR).is_err()
+    {
+        exit_code = DENIED_EXIT_CODE;
+    }
     std::process::exit(exit_code)
 }
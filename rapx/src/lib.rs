@@ -11,34 +11,112 @@ extern crate rustc_errors;
 extern crate rustc_hir;
 extern crate rustc_index;
 extern crate rustc_interface;
+extern crate rustc_lexer;
 extern crate rustc_metadata;
 extern crate rustc_middle;
 extern crate rustc_session;
 extern crate rustc_span;
 extern crate rustc_target;
 
+use analysis::core::alias::container;
 use analysis::core::alias::mop::MopAlias;
 use analysis::core::call_graph::CallGraph;
 use analysis::core::dataflow::DataFlow;
+use analysis::core::heap_item::export::HeapItemTable;
 use analysis::opt::Opt;
 use analysis::rcanary::rCanary;
 use analysis::safedrop::SafeDrop;
 use analysis::senryx::SenryxCheck;
 use analysis::unsafety_isolation::{UigInstruction, UnsafetyIsolationCheck};
+use analysis::utils::intrinsic_id;
 use analysis::utils::show_mir::ShowMir;
 use rustc_data_structures::sync::Lrc;
 use rustc_driver::{Callbacks, Compilation};
 use rustc_interface::interface::Compiler;
 use rustc_interface::{Config, Queries};
+use rustc_lexer::TokenKind;
 use rustc_middle::ty::TyCtxt;
 use rustc_middle::util::Providers;
+use rustc_session::config::CrateType;
 use rustc_session::search_paths::PathKind;
+use rustc_span::Symbol;
 use std::path::PathBuf;
+use utils::cache;
+use utils::config::config;
+use utils::report::{self, OutputFormat};
+use utils::summary;
 
 // Insert rustc arguments at the beginning of the argument list that RAP wants to be
 // set per default, for maximal validation power.
 pub static RAP_DEFAULT_ARGS: &[&str] = &["-Zalways-encode-mir", "-Zmir-opt-level=0"];
 
+/// Arguments only for the crates being analyzed, not for the dependencies being summarized.
+///
+/// `rapx` is registered as a tool so that `#[rapx::allow(..)]` can be used to suppress findings,
+/// unless the crate root already registers it.
+pub fn rap_crate_args(crate_root: Option<&str>) -> Vec<String> {
+    let source = crate_root
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .unwrap_or_default();
+    let attrs = inner_attrs(&source);
+    let lists = |attr| attrs.iter().flat_map(move |text| attr_lists(text, attr));
+    let mut args = Vec::new();
+    if !lists("feature(").any(|name| name == "register_tool") {
+        args.push("-Zcrate-attr=feature(register_tool)".to_owned());
+    }
+    if !lists("register_tool(").any(|name| name == "rapx") {
+        args.push("-Zcrate-attr=register_tool(rapx)".to_owned());
+    }
+    args.push("--cfg=rapx".to_owned());
+    args.push("--check-cfg=cfg(rapx)".to_owned());
+    args
+}
+
+/// The inner attributes `#![...]` of the source code without whitespace, where the comments are
+/// removed and the literals are blanked, so that the attributes mentioned in them are not taken.
+fn inner_attrs(source: &str) -> Vec<String> {
+    let mut tokens = String::new();
+    let mut start = 0;
+    for token in rustc_lexer::tokenize(source) {
+        let text = &source[start..start + token.len as usize];
+        start += token.len as usize;
+        match token.kind {
+            TokenKind::Whitespace
+            | TokenKind::LineComment { .. }
+            | TokenKind::BlockComment { .. } => {}
+            TokenKind::Literal { .. } => tokens.push('_'),
+            _ => tokens.push_str(text),
+        }
+    }
+    let mut attrs = Vec::new();
+    let mut rest = tokens.as_str();
+    while let Some(start) = rest.find("#![") {
+        rest = &rest[start + 3..];
+        let mut depth = 1;
+        let end = rest
+            .find(|c| {
+                match c {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            })
+            .unwrap_or(rest.len());
+        attrs.push(rest[..end].to_owned());
+        rest = &rest[end..];
+    }
+    attrs
+}
+
+/// The names listed in each `<attr>(...)` of an attribute, e.g., the features enabled.
+fn attr_lists<'a>(source: &'a str, attr: &'a str) -> impl Iterator<Item = &'a str> {
+    source.match_indices(attr).flat_map(move |(start, _)| {
+        let list = &source[start + attr.len()..];
+        list[..list.find(')').unwrap_or(0)].split(',')
+    })
+}
+
 pub type Elapsed = (i64, i64);
 
 #[derive(Debug, Copy, Clone, Hash)]
@@ -49,9 +127,12 @@ pub struct RapCallback {
     unsafety_isolation: usize,
     mop: bool,
     callgraph: bool,
+    heap_item: bool,
     show_mir: bool,
     dataflow: usize,
     opt: bool,
+    summary: bool,
+    output_format: OutputFormat,
 }
 
 impl Default for RapCallback {
@@ -63,15 +144,27 @@ impl Default for RapCallback {
             unsafety_isolation: 0,
             mop: false,
             callgraph: false,
+            heap_item: false,
             show_mir: false,
             dataflow: 0,
             opt: false,
+            summary: false,
+            output_format: OutputFormat::Text,
         }
     }
 }
 
 impl Callbacks for RapCallback {
     fn config(&mut self, config: &mut Config) {
+        // Dependencies are only summarized when cargo compiles them, so they are not checked again.
+        if let (Ok(run_id), false) = (std::env::var(cache::RAP_RUN_ID), self.summary) {
+            config.psess_created = Some(Box::new(move |psess| {
+                psess.env_depinfo.get_mut().insert((
+                    Symbol::intern(cache::RAP_RUN_ID),
+                    Some(Symbol::intern(&run_id)),
+                ));
+            }));
+        }
         config.override_queries = Some(|_, providers| {
             providers.extern_queries.used_crate_source = |tcx, cnum| {
                 let mut providers = Providers::default();
@@ -134,6 +227,21 @@ impl RapCallback {
         self.unsafety_isolation
     }
 
+    /// Whether any analysis is enabled on the command line.
+    pub fn has_analysis(&self) -> bool {
+        self.rcanary
+            || self.safedrop
+            || self.senryx
+            || self.unsafety_isolation > 0
+            || self.mop
+            || self.callgraph
+            || self.heap_item
+            || self.show_mir
+            || self.dataflow > 0
+            || self.opt
+            || self.summary
+    }
+
     pub fn enable_senryx(&mut self) {
         self.senryx = true;
     }
@@ -150,6 +258,15 @@ impl RapCallback {
         self.callgraph
     }
 
+    /// Write the ownership layouts of the ADTs, see `heap_item::export`.
+    pub fn enable_heap_item(&mut self) {
+        self.heap_item = true;
+    }
+
+    pub fn is_heap_item_enabled(&self) -> bool {
+        self.heap_item
+    }
+
     pub fn enable_show_mir(&mut self) {
         self.show_mir = true;
     }
@@ -173,6 +290,23 @@ impl RapCallback {
     pub fn is_opt_enabled(self) -> bool {
         self.opt
     }
+
+    /// Only summarize the crate for the crates depending on it, see `utils::summary`.
+    pub fn enable_summary(&mut self) {
+        self.summary = true;
+    }
+
+    pub fn is_summary_enabled(&self) -> bool {
+        self.summary
+    }
+
+    pub fn set_output_format(&mut self, format: OutputFormat) {
+        self.output_format = format;
+    }
+
+    pub fn output_format(&self) -> OutputFormat {
+        self.output_format
+    }
 }
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
@@ -209,6 +343,16 @@ pub fn compile_time_sysroot() -> Option<String> {
 }
 
 pub fn start_analyzer(tcx: TyCtxt, callback: RapCallback) {
+    intrinsic_id::init(tcx);
+    container::init(tcx);
+    if callback.is_summary_enabled() {
+        summary::dump(tcx);
+        return;
+    }
+    report::set_output_format(callback.output_format());
+    report::collect_suppressions(tcx);
+    cache::init(tcx);
+
     let _rcanary: Option<rCanary> = if callback.is_rcanary_enabled() {
         let mut rcx = rCanary::new(tcx);
         rcx.start();
@@ -218,7 +362,9 @@ pub fn start_analyzer(tcx: TyCtxt, callback: RapCallback) {
     };
 
     if callback.is_mop_enabled() {
-        MopAlias::new(tcx).start();
+        let mut mop = MopAlias::new(tcx);
+        mop.start();
+        mop.show_return_aliases();
     }
 
     if callback.is_safedrop_enabled() {
@@ -235,7 +381,7 @@ pub fn start_analyzer(tcx: TyCtxt, callback: RapCallback) {
     }
 
     if callback.is_senryx_enabled() {
-        SenryxCheck::new(tcx, 2).start();
+        SenryxCheck::new(tcx, config().limits.senryx_threshold).start();
     }
 
     if callback.is_show_mir_enabled() {
@@ -252,7 +398,22 @@ pub fn start_analyzer(tcx: TyCtxt, callback: RapCallback) {
         CallGraph::new(tcx).start();
     }
 
+    if callback.is_heap_item_enabled() {
+        HeapItemTable::new(tcx).start();
+    }
+
     if callback.is_opt_enabled() {
         Opt::new(tcx).start();
     }
+
+    report::flush(tcx);
+    // Libraries in the workspace are also dependencies of the crates checked after them.
+    if tcx
+        .crate_types()
+        .iter()
+        .any(|crate_type| *crate_type != CrateType::Executable)
+    {
+        summary::dump(tcx);
+    }
+    cache::save();
 }
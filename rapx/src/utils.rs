@@ -1,3 +1,7 @@
+pub mod cache;
+pub mod config;
 pub mod fs;
 pub mod log;
+pub mod report;
 pub mod source;
+pub mod summary;
@@ -0,0 +1,459 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use rustc_data_structures::fingerprint::Fingerprint;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_data_structures::stable_hasher::Hash64;
+use rustc_hir::def_id::{DefId, DefPathHash, LOCAL_CRATE};
+use rustc_hir::ConstContext;
+use rustc_middle::mir::Body;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::source_map::get_source_map;
+use rustc_span::{BytePos, Span};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::core::alias::{FnMap, FnRetAlias};
+use crate::analysis::core::heap_item::{AdtOwner, OwnerUnit};
+use crate::analysis::safedrop::dangling::DanglingMap;
+use crate::rap_debug;
+use crate::utils::config::config;
+use crate::utils::report::{self, stable_hash, Detector, Finding, FindingKind, Label, Trace};
+
+/// Environment variable set by cargo-rapx: the folder of the analysis cache, i.e., `target/rapx`.
+pub const RAP_CACHE_DIR: &str = "RAP_CACHE_DIR";
+/// Environment variable set by cargo-rapx to a different value in each run.
+///
+/// It is recorded as a dependency of the analyzed crates, so that cargo checks them again
+/// without `cargo clean`, while the dependencies compiled by plain rustc are still reused.
+pub const RAP_RUN_ID: &str = "RAP_RUN_ID";
+
+lazy_static! {
+    static ref CACHE: Mutex<Option<Cache>> = Mutex::new(None);
+}
+
+/// A `DefPathHash`, which identifies a definition across compilation sessions.
+pub(crate) type DefHash = (u64, u64);
+
+pub(crate) fn def_hash(tcx: TyCtxt<'_>, def_id: DefId) -> DefHash {
+    let (hi, lo) = tcx.def_path_hash(def_id).0.split();
+    (hi.as_u64(), lo.as_u64())
+}
+
+pub(crate) fn def_id(tcx: TyCtxt<'_>, hash: DefHash) -> Option<DefId> {
+    let fingerprint = Fingerprint::new(Hash64::new(hash.0), Hash64::new(hash.1));
+    tcx.def_path_hash_to_def_id(DefPathHash(fingerprint))
+}
+
+/// The cached results of a crate, written into `<crate>-<stable crate id>.json`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CrateCache {
+    /// The summaries are reused only if the crate is unchanged.
+    crate_key: String,
+    fn_map: Option<Vec<(DefHash, FnRetAlias)>>,
+    adt_owner: Option<Vec<(DefHash, Vec<OwnerUnit>)>>,
+    /// The findings of each detector in each function, keyed by `<detector>:<function>`, and the
+    /// summaries of the functions, keyed by `<detector>-summary:<function>`.
+    functions: HashMap<String, FunctionCache>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FunctionCache {
+    key: String,
+    findings: Vec<CachedFinding>,
+    /// What the detector returns besides the findings, e.g., the outcome of the solver.
+    #[serde(default)]
+    outcome: serde_json::Value,
+}
+
+/// A label whose range is relative to the function: `(lo, hi, message, primary)`.
+type CachedLabel = (u32, u32, String, bool);
+
+/// A finding whose spans are relative to the function, so that it can be replayed
+/// even if the function is moved within the file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedFinding {
+    kind: String,
+    summary: Option<String>,
+    message: String,
+    context: (u32, u32),
+    fold: bool,
+    labels: Vec<CachedLabel>,
+    #[serde(default)]
+    traces: Vec<(Vec<CachedLabel>, Vec<usize>)>,
+    help: Option<String>,
+}
+
+fn cache_labels(labels: &[Label], anchor: Span) -> Option<Vec<CachedLabel>> {
+    labels
+        .iter()
+        .map(|label| {
+            let (lo, hi) = relative_range(label.span, anchor)?;
+            Some((lo, hi, label.message.clone(), label.primary))
+        })
+        .collect()
+}
+
+fn restore_labels(labels: &[CachedLabel], anchor: Span) -> Vec<Label> {
+    labels
+        .iter()
+        .map(|(lo, hi, message, primary)| Label {
+            span: absolute_span((*lo, *hi), anchor),
+            message: message.clone(),
+            primary: *primary,
+        })
+        .collect()
+}
+
+impl CachedFinding {
+    fn new(finding: &Finding, anchor: Span) -> Option<Self> {
+        let labels = cache_labels(&finding.labels, anchor)?;
+        let traces = finding
+            .traces
+            .iter()
+            .map(|trace| Some((cache_labels(&trace.steps, anchor)?, trace.blocks.clone())))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self {
+            kind: finding.kind.rule_id().to_string(),
+            summary: finding.summary.clone(),
+            message: finding.message.clone(),
+            context: relative_range(finding.context, anchor)?,
+            fold: finding.fold,
+            labels,
+            traces,
+            help: finding.help.clone(),
+        })
+    }
+
+    fn restore(&self, def_id: DefId, anchor: Span) -> Option<Finding> {
+        let kind = FindingKind::from_rule_id(&self.kind)?;
+        let mut finding = Finding::new(kind, def_id, absolute_span(self.context, anchor));
+        finding.summary = self.summary.clone();
+        finding.message = self.message.clone();
+        finding.fold = self.fold;
+        finding.help = self.help.clone();
+        finding.labels = restore_labels(&self.labels, anchor);
+        finding.traces = self
+            .traces
+            .iter()
+            .map(|(steps, blocks)| Trace {
+                steps: restore_labels(steps, anchor),
+                blocks: blocks.clone(),
+            })
+            .collect();
+        Some(finding)
+    }
+}
+
+/// The range of `span` relative to `anchor`, if `span` is inside `anchor`.
+fn relative_range(span: Span, anchor: Span) -> Option<(u32, u32)> {
+    if span.from_expansion() || !anchor.contains(span) {
+        return None;
+    }
+    Some(((span.lo() - anchor.lo()).0, (span.hi() - anchor.lo()).0))
+}
+
+fn absolute_span(range: (u32, u32), anchor: Span) -> Span {
+    anchor
+        .with_lo(anchor.lo() + BytePos(range.0))
+        .with_hi(anchor.lo() + BytePos(range.1))
+}
+
+/// The analysis cache of the current crate.
+///
+/// Alias summaries and ownership layouts are reused if the crate hash is unchanged.
+/// Findings are cached for each function, and reused if the source code and the MIR of the
+/// function, as well as the summaries it depends on, are unchanged.
+struct Cache {
+    path: PathBuf,
+    crate_key: String,
+    previous: CrateCache,
+    next: CrateCache,
+}
+
+/// Load the cache of the current crate if cargo-rapx gives a cache folder.
+pub fn init(tcx: TyCtxt<'_>) {
+    let Ok(dir) = std::env::var(RAP_CACHE_DIR) else {
+        return;
+    };
+    let crate_name = tcx.crate_name(LOCAL_CRATE);
+    let crate_id = tcx.stable_crate_id(LOCAL_CRATE).as_u64();
+    let path = PathBuf::from(dir).join(format!("{crate_name}-{crate_id:016x}.json"));
+    let previous: CrateCache = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let crate_key = format!(
+        "{:016x}",
+        stable_hash(&[
+            env!("CARGO_PKG_VERSION"),
+            &format!("{:?}", config().limits),
+            &tcx.crate_hash(LOCAL_CRATE).to_hex(),
+        ])
+    );
+    rap_debug!(
+        "Cache of {crate_name} loaded from {}: {} function(s).",
+        path.display(),
+        previous.functions.len()
+    );
+    let next = CrateCache {
+        crate_key: crate_key.clone(),
+        ..Default::default()
+    };
+    *CACHE.lock().unwrap() = Some(Cache {
+        path,
+        crate_key,
+        previous,
+        next,
+    });
+}
+
+/// Write the cache of the current crate. Functions not analyzed in this run are dropped.
+pub fn save() {
+    let Some(cache) = CACHE.lock().unwrap().take() else {
+        return;
+    };
+    if let Some(dir) = cache.path.parent() {
+        _ = fs::create_dir_all(dir);
+    }
+    let content = serde_json::to_string(&cache.next).unwrap();
+    if let Err(err) = fs::write(&cache.path, content) {
+        rap_debug!("Failed to write cache {}: {err}", cache.path.display());
+    }
+}
+
+/// The cached alias summaries of the crate.
+pub fn fn_map(tcx: TyCtxt<'_>) -> Option<FnMap> {
+    let cache = CACHE.lock().unwrap();
+    let cache = cache.as_ref()?;
+    let entries = match &cache.next.fn_map {
+        Some(entries) => entries,
+        None if cache.previous.crate_key == cache.crate_key => cache.previous.fn_map.as_ref()?,
+        None => return None,
+    };
+    let mut fn_map = FxHashMap::default();
+    for (hash, fn_alias) in entries {
+        fn_map.insert(def_id(tcx, *hash)?, fn_alias.clone());
+    }
+    rap_debug!("Alias summaries loaded from the cache.");
+    Some(fn_map)
+}
+
+pub fn store_fn_map(tcx: TyCtxt<'_>, fn_map: &FnMap) {
+    if let Some(cache) = CACHE.lock().unwrap().as_mut() {
+        let mut entries: Vec<_> = fn_map
+            .iter()
+            .map(|(def_id, fn_alias)| (def_hash(tcx, *def_id), fn_alias.clone()))
+            .collect();
+        entries.sort_by_key(|(hash, _)| *hash);
+        cache.next.fn_map = Some(entries);
+    }
+}
+
+/// The cached ownership layouts of ADTs.
+pub fn adt_owner(tcx: TyCtxt<'_>) -> Option<AdtOwner> {
+    let cache = CACHE.lock().unwrap();
+    let cache = cache.as_ref()?;
+    let entries = match &cache.next.adt_owner {
+        Some(entries) => entries,
+        None if cache.previous.crate_key == cache.crate_key => cache.previous.adt_owner.as_ref()?,
+        None => return None,
+    };
+    let mut adt_owner = AdtOwner::default();
+    for (hash, units) in entries {
+        adt_owner.insert(def_id(tcx, *hash)?, units.clone());
+    }
+    rap_debug!("Ownership layouts loaded from the cache.");
+    Some(adt_owner)
+}
+
+pub fn store_adt_owner(tcx: TyCtxt<'_>, adt_owner: &AdtOwner) {
+    if let Some(cache) = CACHE.lock().unwrap().as_mut() {
+        let mut entries: Vec<_> = adt_owner
+            .iter()
+            .map(|(def_id, units)| (def_hash(tcx, *def_id), units.clone()))
+            .collect();
+        entries.sort_by_key(|(hash, _)| *hash);
+        cache.next.adt_owner = Some(entries);
+    }
+}
+
+/// A key of the summaries that the findings of a function depend on.
+pub fn summary_key(
+    tcx: TyCtxt<'_>,
+    fn_map: Option<&FnMap>,
+    adt_owner: Option<&AdtOwner>,
+    dangling_map: Option<&DanglingMap>,
+) -> String {
+    let mut fn_map: Vec<_> = fn_map
+        .into_iter()
+        .flatten()
+        .map(|(def_id, fn_alias)| {
+            let mut aliases: Vec<_> = fn_alias
+                .aliases()
+                .iter()
+                .map(|alias| alias.to_string())
+                .collect();
+            aliases.sort();
+            format!(
+                "{:?}{}{:?}",
+                def_hash(tcx, *def_id),
+                fn_alias.arg_size(),
+                aliases
+            )
+        })
+        .collect();
+    fn_map.sort();
+    let mut adt_owner: Vec<_> = adt_owner
+        .into_iter()
+        .flatten()
+        .map(|(def_id, units)| format!("{:?}{:?}", def_hash(tcx, *def_id), units))
+        .collect();
+    adt_owner.sort();
+    let mut dangling_map: Vec<_> = dangling_map
+        .into_iter()
+        .flatten()
+        .map(|(def_id, dangling)| format!("{:?}{:?}", def_hash(tcx, *def_id), dangling))
+        .collect();
+    dangling_map.sort();
+    format!(
+        "{:016x}",
+        stable_hash(&[
+            &fn_map.join(";"),
+            &adt_owner.join(";"),
+            &dangling_map.join(";")
+        ])
+    )
+}
+
+// constants, e.g., the lengths of arrays, only have the MIR for const evaluation.
+fn function_body(tcx: TyCtxt<'_>, def_id: DefId) -> &Body<'_> {
+    match def_id
+        .as_local()
+        .and_then(|local| tcx.hir().body_const_context(local))
+    {
+        Some(ConstContext::ConstFn) | None => tcx.optimized_mir(def_id),
+        Some(_) => tcx.mir_for_ctfe(def_id),
+    }
+}
+
+/// The key of a function: its source code and MIR without spans, together with the summaries
+/// it depends on. Moving a function within the file does not change the key.
+fn function_key(tcx: TyCtxt<'_>, def_id: DefId, summary_key: &str) -> String {
+    let body = function_body(tcx, def_id);
+    let source = get_source_map()
+        .unwrap()
+        .span_to_snippet(body.span)
+        .unwrap_or_default();
+    let mut mir = format!("{}", body.arg_count);
+    for decl in body.local_decls.iter() {
+        mir.push_str(&format!("{:?};", decl.ty));
+    }
+    for data in body.basic_blocks.iter() {
+        for statement in &data.statements {
+            mir.push_str(&format!("{:?};", statement.kind));
+        }
+        if let Some(terminator) = &data.terminator {
+            mir.push_str(&format!("{:?};", terminator.kind));
+        }
+    }
+    format!(
+        "{:016x}",
+        stable_hash(&[
+            env!("CARGO_PKG_VERSION"),
+            &format!("{:?}", config().limits),
+            summary_key,
+            &source,
+            &mir,
+        ])
+    )
+}
+
+/// Run `analyze` on the function, or replay its cached findings if the function is unchanged.
+/// The value returned by `analyze` is cached along with the findings.
+pub fn with_findings<T: Serialize + DeserializeOwned>(
+    tcx: TyCtxt<'_>,
+    detector: Detector,
+    def_id: DefId,
+    summary_key: &str,
+    analyze: impl FnOnce() -> T,
+) -> T {
+    with_entry(tcx, detector.name(), def_id, summary_key, analyze)
+}
+
+/// Run `summarize` on the function, or reuse its cached summary if the function is unchanged.
+/// The summaries are cached apart from the findings of the detector.
+pub fn with_summary<T: Serialize + DeserializeOwned>(
+    tcx: TyCtxt<'_>,
+    detector: Detector,
+    def_id: DefId,
+    summary_key: &str,
+    summarize: impl FnOnce() -> T,
+) -> T {
+    let pass = format!("{}-summary", detector.name());
+    with_entry(tcx, &pass, def_id, summary_key, summarize)
+}
+
+fn with_entry<T: Serialize + DeserializeOwned>(
+    tcx: TyCtxt<'_>,
+    pass: &str,
+    def_id: DefId,
+    summary_key: &str,
+    analyze: impl FnOnce() -> T,
+) -> T {
+    if CACHE.lock().unwrap().is_none() || !tcx.is_mir_available(def_id) {
+        return analyze();
+    }
+    let name = format!("{pass}:{:?}", def_hash(tcx, def_id));
+    let key = function_key(tcx, def_id, summary_key);
+    let anchor = function_body(tcx, def_id).span;
+    let cached = CACHE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|cache| cache.previous.functions.get(&name).cloned())
+        .filter(|entry| entry.key == key);
+    if let Some(entry) = cached {
+        let findings: Option<Vec<Finding>> = entry
+            .findings
+            .iter()
+            .map(|finding| finding.restore(def_id, anchor))
+            .collect();
+        let outcome = serde_json::from_value::<T>(entry.outcome.clone()).ok();
+        if let (Some(findings), Some(outcome)) = (findings, outcome) {
+            rap_debug!(
+                "Results of {pass} in {} replayed from the cache.",
+                tcx.def_path_str(def_id)
+            );
+            findings.into_iter().for_each(report::emit);
+            store_function(name, entry);
+            return outcome;
+        }
+    }
+    let (findings, outcome) = report::capture(analyze);
+    // Findings outside of the function cannot be replayed, so the function is not cached.
+    let findings: Option<Vec<CachedFinding>> = findings
+        .iter()
+        .map(|finding| CachedFinding::new(finding, anchor))
+        .collect();
+    if let (Some(findings), Ok(value)) = (findings, serde_json::to_value(&outcome)) {
+        store_function(
+            name,
+            FunctionCache {
+                key,
+                findings,
+                outcome: value,
+            },
+        );
+    }
+    outcome
+}
+
+fn store_function(name: String, entry: FunctionCache) {
+    if let Some(cache) = CACHE.lock().unwrap().as_mut() {
+        cache.next.functions.insert(name, entry);
+    }
+}
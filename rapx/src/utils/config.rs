@@ -0,0 +1,412 @@
+use log::LevelFilter;
+use once_cell::sync::OnceCell;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, fs, path::Path};
+
+use crate::utils::log::rap_error_and_exit;
+use crate::utils::report::policy::Policy;
+use crate::utils::report::OutputFormat;
+
+/// The configuration file read from the package folder, or from a folder above it up to the workspace root.
+pub const CONFIG_FILE: &str = "rapx.toml";
+/// The validated configuration passed from cargo-rapx to each rapx process.
+pub const RAP_CONFIG: &str = "RAP_CONFIG";
+
+static CONFIG: OnceCell<Config> = OnceCell::new();
+
+/// The configuration of rapx, written in `rapx.toml` or `[package.metadata.rapx]` of `Cargo.toml`:
+/// ```toml
+/// detectors = ["safedrop", "rcanary"]
+/// format = "sarif"
+/// log = "info"
+/// deny = ["uaf", "double_free"]
+/// fail-on = "error"
+/// clean = false
+/// cache = true
+/// recursive = "none"
+/// summaries = "local"
+///
+/// [scope]
+/// include-crates = []
+/// exclude-crates = ["generated"]
+/// include-paths = ["src/**"]
+/// exclude-paths = ["src/bindings.rs"]
+///
+/// [limits]
+/// safedrop-visit = 1000
+/// safedrop-widening = 3
+/// mop-visit = 100
+/// mop-field-depth = 4
+/// rcanary-unroll = 2
+/// rcanary-timeout = 1000
+/// senryx-threshold = 2
+///
+/// [limits.rcanary-timeouts]
+/// "crate_name::module::function" = 5000
+///
+/// [display]
+/// z3-goal = false
+/// icx-slice = false
+/// adt = false
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Detectors to run if none is given on the command line.
+    pub detectors: Vec<DetectorName>,
+    /// The report format if `-format` is not given on the command line.
+    pub format: Option<OutputFormat>,
+    /// Verbosity of logging: error, warn, info, debug or trace.
+    pub log: Option<String>,
+    /// Findings that fail the run if `-deny` is not given, e.g., `["uaf", "leak"]`.
+    pub deny: Vec<String>,
+    /// The lowest severity that fails the run if `-fail-on` is not given.
+    pub fail_on: Option<String>,
+    /// Whether to run `cargo clean` before checking.
+    pub clean: Option<bool>,
+    /// Whether to cache the analysis results under `target/rapx`.
+    pub cache: Option<bool>,
+    pub recursive: Option<Recursive>,
+    /// Dependencies whose alias summaries and ownership layouts are computed when they are compiled.
+    pub summaries: Option<Summaries>,
+    pub scope: Scope,
+    pub limits: Limits,
+    pub display: Display,
+}
+
+/// Detectors that can be enabled in the configuration.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DetectorName {
+    SafeDrop,
+    RCanary,
+    Senryx,
+    Opt,
+}
+
+/// Scope of packages to check.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Recursive {
+    /// Check the current folder.
+    #[default]
+    None,
+    /// Check each member of the current workspace.
+    Shallow,
+    /// Check each workspace found from the current folder.
+    Deep,
+}
+
+impl Recursive {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "none" => Some(Recursive::None),
+            "shallow" => Some(Recursive::Shallow),
+            "deep" => Some(Recursive::Deep),
+            _ => None,
+        }
+    }
+}
+
+/// Dependencies to summarize for the analysis of the local crates.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Summaries {
+    /// Do not summarize dependencies.
+    None,
+    /// Summarize path dependencies, e.g., the utility crates in the same workspace.
+    #[default]
+    Local,
+    /// Summarize all dependencies, including those from registries and git.
+    All,
+}
+
+impl Summaries {
+    pub fn name(self) -> &'static str {
+        match self {
+            Summaries::None => "none",
+            Summaries::Local => "local",
+            Summaries::All => "all",
+        }
+    }
+}
+
+/// Crates and source files to analyze. Empty include lists mean everything is included,
+/// and excludes take priority over includes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Scope {
+    pub include_crates: Vec<String>,
+    pub exclude_crates: Vec<String>,
+    /// Glob patterns of source files relative to the package, where `*` matches within a folder
+    /// and `**` matches any number of folders.
+    pub include_paths: Vec<String>,
+    pub exclude_paths: Vec<String>,
+}
+
+impl Scope {
+    pub fn contains_crate(&self, name: &str) -> bool {
+        // Cargo passes crate names with `-` replaced by `_`.
+        let matches = |names: &Vec<String>| names.iter().any(|n| n.replace('-', "_") == name);
+        (self.include_crates.is_empty() || matches(&self.include_crates))
+            && !matches(&self.exclude_crates)
+    }
+
+    pub fn contains_path(&self, path: &str) -> bool {
+        let matches = |globs: &Vec<String>| globs.iter().any(|glob| glob_matches(glob, path));
+        (self.include_paths.is_empty() || matches(&self.include_paths))
+            && !matches(&self.exclude_paths)
+    }
+}
+
+fn glob_matches(glob: &str, path: &str) -> bool {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                pattern.push_str(".*");
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    // A folder matches everything inside it.
+    pattern.push_str("(/.*)?$");
+    Regex::new(&pattern).is_ok_and(|re| re.is_match(path.trim_start_matches("./")))
+}
+
+/// Bounds of the path-sensitive analyses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Limits {
+    /// Maximum number of visited paths per function in SafeDrop.
+    pub safedrop_visit: usize,
+    /// Rounds of analyzing a loop in SafeDrop before widening, once the paths are merged.
+    pub safedrop_widening: usize,
+    /// Maximum number of visited paths per function in the MoP alias analysis.
+    pub mop_visit: usize,
+    /// Maximum depth of the fields distinguished by the MoP alias analysis; deeper fields are merged into
+    /// their ancestor at this depth.
+    pub mop_field_depth: usize,
+    /// Times that the loops are unrolled in rCanary; the ownership after more iterations is approximated.
+    pub rcanary_unroll: usize,
+    /// Time limit in milliseconds of the z3 solver for each function in rCanary.
+    pub rcanary_timeout: u64,
+    /// Time limits in milliseconds for the functions named by their paths, e.g., `my_crate::parse`,
+    /// which override `rcanary-timeout`.
+    pub rcanary_timeouts: BTreeMap<String, u64>,
+    /// Threshold of the Senryx contract checking.
+    pub senryx_threshold: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            safedrop_visit: 1000,
+            safedrop_widening: 3,
+            mop_visit: 100,
+            mop_field_depth: 4,
+            rcanary_unroll: 2,
+            rcanary_timeout: 1000,
+            rcanary_timeouts: BTreeMap::new(),
+            senryx_threshold: 2,
+        }
+    }
+}
+
+impl Limits {
+    /// The time limit of the z3 solver for the function with the path.
+    pub fn rcanary_timeout_of(&self, path: &str) -> u64 {
+        self.rcanary_timeouts
+            .get(path)
+            .copied()
+            .unwrap_or(self.rcanary_timeout)
+    }
+}
+
+/// Verbose output of the intermediate results.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Display {
+    /// Print the z3 goals of rCanary.
+    pub z3_goal: bool,
+    /// Print the intra-procedural context slices of rCanary.
+    pub icx_slice: bool,
+    /// Print the ownership layout of ADTs.
+    pub adt: bool,
+}
+
+impl Config {
+    /// Parse and validate the configuration, e.g., unknown keys are rejected.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let config: Config = toml::from_str(content).map_err(|err| err.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read the configuration of the package in `dir` from `rapx.toml`,
+    /// or from `[package.metadata.rapx]` of `Cargo.toml` if there is no `rapx.toml`.
+    pub fn find(dir: &Path) -> Result<Option<Self>, String> {
+        let path = dir.join(CONFIG_FILE);
+        if path.exists() {
+            return Self::load(&path).map(Some);
+        }
+        let path = dir.join("Cargo.toml");
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Ok(None);
+        };
+        let manifest: toml::Table = toml::from_str(&content)
+            .map_err(|err| format!("Failed to parse {}: {err}", path.display()))?;
+        let Some(metadata) = manifest
+            .get("package")
+            .and_then(|package| package.get("metadata"))
+            .and_then(|metadata| metadata.get("rapx"))
+        else {
+            return Ok(None);
+        };
+        let config: Config = metadata.clone().try_into().map_err(|err| {
+            format!(
+                "Invalid [package.metadata.rapx] in {}: {err}",
+                path.display()
+            )
+        })?;
+        config.validate().map_err(|err| {
+            format!(
+                "Invalid [package.metadata.rapx] in {}: {err}",
+                path.display()
+            )
+        })?;
+        Ok(Some(config))
+    }
+
+    /// Read the configuration of the package in `dir`, or of the first folder above it up to `root`,
+    /// e.g., a `rapx.toml` in the workspace root shared by its members.
+    pub fn resolve(dir: &Path, root: &Path) -> Result<Option<Self>, String> {
+        let dir = std::path::absolute(dir)
+            .map_err(|err| format!("Invalid folder {}: {err}", dir.display()))?;
+        for dir in dir.ancestors() {
+            if let Some(config) = Self::find(dir)? {
+                return Ok(Some(config));
+            }
+            if dir == root {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+        Self::parse(&content).map_err(|err| format!("Invalid {}: {err}", path.display()))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some(level) = &self.log {
+            level
+                .parse::<LevelFilter>()
+                .map_err(|_| format!("invalid `log` value `{level}`, expected one of error, warn, info, debug, trace"))?;
+        }
+        self.policy(None, None)?;
+        if self.limits.safedrop_visit == 0 || self.limits.mop_visit == 0 {
+            return Err("visit limits should be positive".to_string());
+        }
+        if self.limits.mop_field_depth == 0 {
+            return Err("`mop-field-depth` should be positive".to_string());
+        }
+        if self.limits.safedrop_widening == 0 {
+            return Err("`safedrop-widening` should be positive".to_string());
+        }
+        if self.limits.rcanary_unroll == 0 {
+            return Err("`rcanary-unroll` should be positive".to_string());
+        }
+        if self.limits.rcanary_timeout == 0
+            || self.limits.rcanary_timeouts.values().any(|t| *t == 0)
+        {
+            return Err("rCanary timeouts should be positive".to_string());
+        }
+        Ok(())
+    }
+
+    /// The environment variables of earlier versions override the configuration.
+    pub fn with_env(mut self) -> Self {
+        if let Ok(level) = env::var("RAP_LOG") {
+            self.log = Some(level);
+        }
+        if let Ok(clean) = env::var("RAP_CLEAN") {
+            self.clean = Some(clean.trim().to_ascii_lowercase() != "false");
+        }
+        if let Ok(recursive) = env::var("RAP_RECURSIVE") {
+            self.recursive = Recursive::from_name(&recursive);
+            if self.recursive.is_none() {
+                rap_error_and_exit(
+                    "`RAP_RECURSIVE` should only accept one of the values: none, shallow or deep.",
+                );
+            }
+        }
+        self.display.z3_goal |= env::var_os("Z3").is_some();
+        self.display.icx_slice |= env::var_os("ICX_SLICE").is_some();
+        self.display.adt |= env::var_os("ADT_DISPLAY").is_some();
+        self
+    }
+
+    /// The exit-code policy; the options on the command line take priority over the configuration.
+    pub fn policy(&self, deny: Option<&str>, fail_on: Option<&str>) -> Result<Policy, String> {
+        let config_deny = self.deny.join(",");
+        Policy::new(
+            deny.or(Some(config_deny.as_str())),
+            fail_on.or(self.fail_on.as_deref()),
+        )
+    }
+
+    pub fn log_level(&self) -> LevelFilter {
+        self.log
+            .as_deref()
+            .and_then(|level| level.parse().ok())
+            .unwrap_or(LevelFilter::Info)
+    }
+
+    pub fn is_clean(&self) -> bool {
+        // local crates are checked again in each run without cleaning, see `cache::RAP_RUN_ID`
+        self.clean.unwrap_or(false)
+    }
+
+    pub fn is_cached(&self) -> bool {
+        self.cache.unwrap_or(true)
+    }
+
+    /// Serialize the configuration into `RAP_CONFIG` for the rapx processes spawned by cargo.
+    pub fn export(&self) {
+        env::set_var(RAP_CONFIG, self.to_json());
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Failed to serialize the configuration.")
+    }
+
+    /// Read the configuration exported by cargo-rapx, if any.
+    pub fn import() -> Result<Option<Self>, String> {
+        let Ok(content) = env::var(RAP_CONFIG) else {
+            return Ok(None);
+        };
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|err| format!("Invalid {RAP_CONFIG}: {err}"))
+    }
+}
+
+/// Set the configuration of the current process; it can only be set once.
+pub fn set_config(config: Config) -> Result<(), String> {
+    CONFIG
+        .set(config)
+        .map_err(|_| "The configuration is set twice.".to_string())
+}
+
+/// The configuration of the current process, or the default one if it has not been set.
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(|| Config::default().with_env())
+}
@@ -17,9 +17,10 @@ fn log_level() -> LevelFilter {
 }
 
 /// Detect `RAP_LOG` environment variable first; if it's not set,
-/// default to INFO level.
+/// default to INFO level. The level can be changed later by `set_log_level`,
+/// e.g., once the configuration is read.
 pub fn init_log() -> Result<(), fern::InitError> {
-    let dispatch = Dispatch::new().level(log_level());
+    let dispatch = Dispatch::new().level(LevelFilter::Trace);
 
     let color_line = ColoredLevelConfig::new()
         .error(Color::Red)
@@ -51,9 +52,14 @@ pub fn init_log() -> Result<(), fern::InitError> {
 
     /* Note that we cannot dispatch to stdout due to some bugs */
     dispatch.chain(stderr_dispatch).apply()?;
+    set_log_level(log_level());
     Ok(())
 }
 
+pub fn set_log_level(level: LevelFilter) {
+    log::set_max_level(level);
+}
+
 #[macro_export]
 macro_rules! rap_trace {
     ($($arg:tt)+) => (
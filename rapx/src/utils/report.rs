@@ -0,0 +1,703 @@
+pub mod baseline;
+pub mod json;
+pub mod outcome;
+pub mod policy;
+pub mod sarif;
+pub mod suppression;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use annotate_snippets::{Level, Renderer, Snippet};
+use lazy_static::lazy_static;
+use rustc_hir::def_id::{DefId, LOCAL_CRATE};
+use rustc_middle::ty::TyCtxt;
+use rustc_span::source_map::get_source_map;
+use rustc_span::Span;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::config::config;
+use crate::utils::log::{
+    are_spans_in_same_file, rap_error_and_exit, relative_pos_range, span_to_filename,
+    span_to_line_number, span_to_source_code,
+};
+use crate::{rap_debug, rap_info, rap_warn};
+use baseline::Baseline;
+use outcome::Outcome;
+use policy::Policy;
+use suppression::{Suppression, Suppressions};
+
+/// Environment variable set by cargo-rapx: each analyzed crate writes its structured
+/// report into this folder, and cargo-rapx merges them once `cargo check` finishes.
+pub const RAP_REPORT_DIR: &str = "RAP_REPORT_DIR";
+
+lazy_static! {
+    static ref FINDINGS: Mutex<Vec<Finding>> = Mutex::new(Vec::new());
+    static ref OUTPUT_FORMAT: Mutex<OutputFormat> = Mutex::new(OutputFormat::Text);
+    static ref BASELINE: Mutex<Option<Baseline>> = Mutex::new(None);
+    static ref POLICY: Mutex<Policy> = Mutex::new(Policy::default());
+    static ref DENIED_FINDINGS: Mutex<usize> = Mutex::new(0);
+    static ref SUPPRESSIONS: Mutex<Suppressions> = Mutex::new(Suppressions::default());
+    static ref CAPTURED: Mutex<Option<Vec<Finding>>> = Mutex::new(None);
+    static ref OUTCOMES: Mutex<Vec<Outcome>> = Mutex::new(Vec::new());
+}
+
+/// The format of the analysis report.
+/// Bugs are always rendered as text on stdout; other formats are written to a file in addition.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Sarif,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            "sarif" => Some(OutputFormat::Sarif),
+            _ => None,
+        }
+    }
+
+    /// The extension of the report file, or `None` if nothing is written to disk.
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            OutputFormat::Text => None,
+            OutputFormat::Json => Some("json"),
+            OutputFormat::Sarif => Some("sarif"),
+        }
+    }
+
+    /// The default report file written by rapx.
+    pub fn default_file(&self) -> Option<String> {
+        self.extension().map(|ext| format!("rapx.{ext}"))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum Detector {
+    SafeDrop,
+    RCanary,
+    Senryx,
+    Opt,
+}
+
+impl Detector {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Detector::SafeDrop => "safedrop",
+            Detector::RCanary => "rcanary",
+            Detector::Senryx => "senryx",
+            Detector::Opt => "opt",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "error" => Some(Severity::Error),
+            "warning" => Some(Severity::Warning),
+            "note" => Some(Severity::Note),
+            _ => None,
+        }
+    }
+
+    /// Higher ranks are more severe.
+    pub fn rank(&self) -> u8 {
+        match self {
+            Severity::Error => 2,
+            Severity::Warning => 1,
+            Severity::Note => 0,
+        }
+    }
+
+    fn level(&self) -> Level {
+        match self {
+            Severity::Error => Level::Error,
+            Severity::Warning => Level::Warning,
+            Severity::Note => Level::Note,
+        }
+    }
+}
+
+/// The kind of a finding, which is used as the rule of structured reports.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum FindingKind {
+    DoubleFree,
+    UseAfterFree,
+    DanglingPointer,
+    MemoryLeak,
+    ContractFailure,
+    BoundsChecking,
+    MemoryCloning,
+}
+
+impl FindingKind {
+    pub const ALL: [FindingKind; 7] = [
+        FindingKind::DoubleFree,
+        FindingKind::UseAfterFree,
+        FindingKind::DanglingPointer,
+        FindingKind::MemoryLeak,
+        FindingKind::ContractFailure,
+        FindingKind::BoundsChecking,
+        FindingKind::MemoryCloning,
+    ];
+
+    pub fn rule_id(&self) -> &'static str {
+        match self {
+            FindingKind::DoubleFree => "double-free",
+            FindingKind::UseAfterFree => "use-after-free",
+            FindingKind::DanglingPointer => "dangling-pointer",
+            FindingKind::MemoryLeak => "memory-leak",
+            FindingKind::ContractFailure => "contract-failure",
+            FindingKind::BoundsChecking => "bounds-checking",
+            FindingKind::MemoryCloning => "memory-cloning",
+        }
+    }
+
+    pub fn from_rule_id(rule_id: &str) -> Option<Self> {
+        FindingKind::ALL
+            .into_iter()
+            .find(|kind| kind.rule_id() == rule_id)
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            FindingKind::DoubleFree => "A heap item may be dropped twice.",
+            FindingKind::UseAfterFree => "A heap item may be used after it has been dropped.",
+            FindingKind::DanglingPointer => "A dangling pointer may escape from the function.",
+            FindingKind::MemoryLeak => "A heap item may never be dropped.",
+            FindingKind::ContractFailure => "The safety contract of an unsafe API may not hold.",
+            FindingKind::BoundsChecking => "Bounds checkings that can be avoided.",
+            FindingKind::MemoryCloning => "Memory cloning that can be avoided.",
+        }
+    }
+
+    pub fn detector(&self) -> Detector {
+        match self {
+            FindingKind::DoubleFree | FindingKind::UseAfterFree | FindingKind::DanglingPointer => {
+                Detector::SafeDrop
+            }
+            FindingKind::MemoryLeak => Detector::RCanary,
+            FindingKind::ContractFailure => Detector::Senryx,
+            FindingKind::BoundsChecking | FindingKind::MemoryCloning => Detector::Opt,
+        }
+    }
+
+    /// Memory-safety bugs are errors, possible leaks and contract failures are warnings,
+    /// and optimization chances are notes.
+    pub fn severity(&self) -> Severity {
+        match self {
+            FindingKind::DoubleFree | FindingKind::UseAfterFree | FindingKind::DanglingPointer => {
+                Severity::Error
+            }
+            FindingKind::MemoryLeak | FindingKind::ContractFailure => Severity::Warning,
+            FindingKind::BoundsChecking | FindingKind::MemoryCloning => Severity::Note,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    /// Primary labels mark the bug itself; the others are related locations.
+    pub primary: bool,
+}
+
+/// The steps leading to a bug, e.g., where the heap item is created, aliased and dropped for the
+/// first time, which end with the primary label of the bug.
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    pub steps: Vec<Label>,
+    /// The basic blocks visited on the path to the bug.
+    pub blocks: Vec<usize>,
+}
+
+impl Trace {
+    pub fn step(&mut self, span: Span, message: impl Into<String>) {
+        self.steps.push(Label {
+            span,
+            message: message.into(),
+            primary: false,
+        });
+    }
+
+    /// The basic blocks visited on the path, e.g., "bb0 -> bb2 -> bb5".
+    pub fn path(&self) -> String {
+        self.blocks
+            .iter()
+            .map(|block| format!("bb{block}"))
+            .collect::<Vec<_>>()
+            .join(" -> ")
+    }
+}
+
+/// A bug or an optimization chance found by one of the detectors.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub kind: FindingKind,
+    /// The one-line summary logged as a warning, e.g., "Double free detected in function foo".
+    pub summary: Option<String>,
+    pub message: String,
+    /// The function where the finding is located.
+    pub def_id: DefId,
+    /// The span of the function body, which is the snippet shown in text reports.
+    pub context: Span,
+    pub fold: bool,
+    pub labels: Vec<Label>,
+    /// The traces of the primary labels, if the detector records them.
+    pub traces: Vec<Trace>,
+    pub help: Option<String>,
+    /// The suppression in the source code that silences this finding.
+    pub suppression: Option<Suppression>,
+}
+
+impl Finding {
+    pub fn new(kind: FindingKind, def_id: DefId, context: Span) -> Self {
+        Self {
+            kind,
+            summary: None,
+            message: String::new(),
+            def_id,
+            context,
+            fold: true,
+            labels: Vec::new(),
+            traces: Vec::new(),
+            help: None,
+            suppression: None,
+        }
+    }
+
+    pub fn summary(mut self, summary: impl Into<String>) -> Self {
+        self.summary = Some(summary.into());
+        self
+    }
+
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+        self
+    }
+
+    pub fn fold(mut self, fold: bool) -> Self {
+        self.fold = fold;
+        self
+    }
+
+    pub fn primary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: true,
+        });
+        self
+    }
+
+    pub fn related(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+
+    /// Add a primary label with the trace leading to it.
+    pub fn traced(mut self, span: Span, message: impl Into<String>, mut trace: Trace) -> Self {
+        let message = message.into();
+        trace.steps.push(Label {
+            span,
+            message: message.clone(),
+            primary: true,
+        });
+        self.traces.push(trace);
+        self.primary(span, message)
+    }
+
+    pub fn help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn primary_spans(&self) -> impl Iterator<Item = Span> + '_ {
+        self.labels
+            .iter()
+            .filter(|label| label.primary)
+            .map(|label| label.span)
+    }
+
+    /// A stable identifier of the finding.
+    /// It is computed from the kind, the function path and the normalized code of the first
+    /// primary label instead of byte offsets, so it does not change when unrelated lines move.
+    /// Detectors report one finding per bug site, so new bugs in the same function do not
+    /// change the fingerprints of the findings triaged before. Bug sites with the same code in a
+    /// function share the fingerprint, and the baseline counts them.
+    pub fn fingerprint(&self, tcx: TyCtxt<'_>) -> String {
+        let crate_name = tcx.crate_name(self.def_id.krate).to_string();
+        let fn_path = tcx.def_path_str(self.def_id);
+        let code = self
+            .primary_spans()
+            .next()
+            .map(normalized_code)
+            .unwrap_or_default();
+        let parts = [
+            crate_name.as_str(),
+            fn_path.as_str(),
+            self.kind.rule_id(),
+            code.as_str(),
+        ];
+        format!("{:016x}", stable_hash(&parts))
+    }
+
+    /// Log the summary and print the rendered snippet.
+    pub fn print(&self) {
+        if let Some(summary) = &self.summary {
+            rap_warn!("{}", summary);
+        }
+        println!("{}", self.render_text());
+    }
+
+    pub fn render_text(&self) -> String {
+        let level = self.kind.severity().level();
+        let code_source = span_to_source_code(self.context);
+        let filename = span_to_filename(self.context);
+        let mut snippet = Snippet::source(&code_source)
+            .line_start(span_to_line_number(self.context))
+            .origin(&filename)
+            .fold(self.fold);
+        // The steps of the traces are shown as secondary labels, once for each location.
+        let mut steps: Vec<&Label> = Vec::new();
+        for step in self.traces.iter().flat_map(|trace| trace.steps.iter()) {
+            if !step.primary
+                && !steps
+                    .iter()
+                    .copied()
+                    .chain(self.labels.iter())
+                    .any(|label| label.span == step.span)
+            {
+                steps.push(step);
+            }
+        }
+        for label in self.labels.iter().chain(steps) {
+            //todo: remove this condition
+            if !are_spans_in_same_file(self.context, label.span) {
+                continue;
+            }
+            let label_level = if label.primary { level } else { Level::Info };
+            snippet = snippet.annotation(
+                label_level
+                    .span(unsafe { relative_pos_range(self.context, label.span) })
+                    .label(&label.message),
+            );
+        }
+        let mut message = level.title(&self.message).snippet(snippet);
+        // The paths are long, so only the first one is shown and the others are left to
+        // the structured reports.
+        let mut paths = self.traces.iter().filter(|trace| !trace.blocks.is_empty());
+        let path = paths.next().map(|trace| match paths.count() {
+            0 => format!("path: {}", trace.path()),
+            others => format!("path: {}, and {others} other path(s)", trace.path()),
+        });
+        if let Some(path) = &path {
+            message = message.footer(Level::Note.title(path));
+        }
+        if let Some(help) = &self.help {
+            message = message.footer(Level::Help.title(help));
+        }
+        let renderer = Renderer::styled();
+        format!("{}", renderer.render(message))
+    }
+}
+
+/// The source code of the span with all whitespace sequences collapsed into a single space.
+fn normalized_code(span: Span) -> String {
+    get_source_map()
+        .unwrap()
+        .span_to_snippet(span)
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// FNV-1a hash, which is stable across runs, platforms and toolchains.
+pub fn stable_hash(parts: &[&str]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for byte in part.bytes().chain(std::iter::once(0)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+/// A resolved position in the source code.
+#[derive(Debug, Clone)]
+pub struct Region {
+    pub file: String,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Region {
+    pub fn from_span(span: Span) -> Self {
+        let source_map = get_source_map().unwrap();
+        let lo = source_map.lookup_char_pos(span.lo());
+        let hi = source_map.lookup_char_pos(span.hi());
+        Self {
+            file: span_to_filename(span),
+            start_line: lo.line,
+            start_column: lo.col.0 + 1,
+            end_line: hi.line,
+            end_column: hi.col.0 + 1,
+        }
+    }
+}
+
+/// Collect the suppressions written in the local crate.
+pub fn collect_suppressions(tcx: TyCtxt<'_>) {
+    *SUPPRESSIONS.lock().unwrap() = Suppressions::collect(tcx);
+}
+
+pub fn set_output_format(format: OutputFormat) {
+    *OUTPUT_FORMAT.lock().unwrap() = format;
+}
+
+pub fn output_format() -> OutputFormat {
+    *OUTPUT_FORMAT.lock().unwrap()
+}
+
+pub fn set_baseline(path: &str) {
+    *BASELINE.lock().unwrap() = Some(Baseline::load(path));
+}
+
+/// Findings are checked against the baseline once the analysis is done,
+/// so they are only rendered at that time.
+fn is_checking_baseline() -> bool {
+    BASELINE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|baseline| !baseline.is_recording())
+}
+
+pub fn set_policy(policy: Policy) {
+    *POLICY.lock().unwrap() = policy;
+}
+
+/// The number of findings that fail the run: those denied by the policy,
+/// and those not in the baseline being checked.
+pub fn denied_findings() -> usize {
+    *DENIED_FINDINGS.lock().unwrap()
+}
+
+/// Report a finding: it is rendered immediately and recorded for the structured report.
+///
+/// Findings in source files out of the configured scope are dropped.
+/// Suppressed findings are only kept for SARIF, which can carry the suppressions.
+pub fn emit(mut finding: Finding) {
+    if let Some(captured) = CAPTURED.lock().unwrap().as_mut() {
+        captured.push(finding.clone());
+    }
+    let file = Region::from_span(finding.context).file;
+    if !config().scope.contains_path(&file) {
+        rap_debug!("{:?} in {} is out of the scope.", finding.kind, file);
+        return;
+    }
+    finding.suppression = SUPPRESSIONS.lock().unwrap().check(&finding);
+    if finding.suppression.is_some() {
+        rap_debug!("{:?} suppressed in {:?}.", finding.kind, finding.def_id);
+        if output_format() == OutputFormat::Sarif {
+            FINDINGS.lock().unwrap().push(finding);
+        }
+        return;
+    }
+    let baseline = BASELINE.lock().unwrap().is_some();
+    if !is_checking_baseline() {
+        finding.print();
+    }
+    if output_format() != OutputFormat::Text || baseline || POLICY.lock().unwrap().is_active() {
+        FINDINGS.lock().unwrap().push(finding);
+    }
+}
+
+/// Whether the function is defined in a source file in the configured scope.
+/// Detectors skip the functions out of the scope, while their summaries are still computed for the callers.
+pub fn in_scope(tcx: TyCtxt<'_>, def_id: DefId) -> bool {
+    let file = Region::from_span(tcx.def_span(def_id)).file;
+    config().scope.contains_path(&file)
+}
+
+/// Record how a function is checked by a detector; functions in source files out of the configured scope are dropped.
+pub fn record_outcome(outcome: Outcome) {
+    if config().scope.contains_path(&outcome.file) {
+        OUTCOMES.lock().unwrap().push(outcome);
+    }
+}
+
+/// Run `f` and return the findings emitted by it along with its result, e.g., to cache the findings of a function.
+pub fn capture<T>(f: impl FnOnce() -> T) -> (Vec<Finding>, T) {
+    *CAPTURED.lock().unwrap() = Some(Vec::new());
+    let result = f();
+    (CAPTURED.lock().unwrap().take().unwrap_or_default(), result)
+}
+
+/// The file to write a report of the current crate.
+/// Under cargo-rapx, it is a fragment to be merged; otherwise, it is the final report.
+fn report_path(tcx: TyCtxt<'_>, ext: &str, default: PathBuf) -> PathBuf {
+    match std::env::var(RAP_REPORT_DIR) {
+        Ok(dir) => {
+            let crate_name = tcx.crate_name(LOCAL_CRATE);
+            let crate_id = tcx.stable_crate_id(LOCAL_CRATE).as_u64();
+            PathBuf::from(dir).join(format!("{crate_name}-{crate_id:016x}.{ext}"))
+        }
+        Err(_) => default,
+    }
+}
+
+fn write_report(path: &Path, content: String) {
+    if let Err(err) = fs::write(path, content) {
+        rap_error_and_exit(format!("Failed to write report {}: {err}", path.display()));
+    }
+}
+
+/// Record the findings into the baseline, or drop the findings that are known by it.
+fn apply_baseline(tcx: TyCtxt<'_>, findings: &mut Vec<Finding>) {
+    let Some(mut baseline) = BASELINE.lock().unwrap().clone() else {
+        return;
+    };
+    if !baseline.is_recording() {
+        let total = findings.len();
+        findings.retain(|finding| !baseline.take_known(&finding.fingerprint(tcx)));
+        for finding in findings.iter() {
+            finding.print();
+        }
+        rap_info!(
+            "{} finding(s) suppressed by the baseline {}.",
+            total - findings.len(),
+            baseline.path.display()
+        );
+        if !findings.is_empty() {
+            rap_warn!("{} new finding(s) not in the baseline.", findings.len());
+        }
+        return;
+    }
+    // Under cargo-rapx, the fragment is merged into the baseline.
+    let path = report_path(tcx, baseline::EXTENSION, baseline.path.clone());
+    write_report(&path, json::render(tcx, findings));
+    if path == baseline.path {
+        rap_info!(
+            "{} finding(s) recorded into the baseline {}.",
+            findings.len(),
+            path.display()
+        );
+    }
+}
+
+/// Count the findings that fail the run: those denied by the policy, and those not in the baseline being checked,
+/// which are the only findings left by `apply_baseline` and fail the run whether the policy denies them or not.
+/// Under cargo-rapx, the number is written into a fragment and summed up once all crates are checked.
+fn count_denied(tcx: TyCtxt<'_>, findings: &[Finding]) {
+    let policy = POLICY.lock().unwrap().clone();
+    let checking_baseline = is_checking_baseline();
+    let denied = findings
+        .iter()
+        .filter(|finding| checking_baseline || (policy.is_active() && policy.denies(finding)))
+        .count();
+    if denied == 0 {
+        return;
+    }
+    if policy.is_active() {
+        rap_warn!("{denied} finding(s) denied.");
+    }
+    *DENIED_FINDINGS.lock().unwrap() += denied;
+    if std::env::var(RAP_REPORT_DIR).is_ok() {
+        let path = report_path(tcx, policy::EXTENSION, PathBuf::new());
+        write_report(&path, denied.to_string());
+    }
+}
+
+fn show_used_suppressions() {
+    let suppressions = SUPPRESSIONS.lock().unwrap();
+    let used = suppressions.used();
+    if used.is_empty() {
+        return;
+    }
+    rap_info!("{} suppression(s) used:", used.len());
+    for (suppression, count) in used {
+        rap_info!(
+            "  {} `{}` silenced {} finding(s), reason: {}",
+            suppression.location(),
+            suppression.source,
+            count,
+            suppression.reason()
+        );
+    }
+}
+
+/// Show the summary table of the outcomes, and write them along with a structured report.
+fn flush_outcomes(tcx: TyCtxt<'_>) {
+    let outcomes = std::mem::take(&mut *OUTCOMES.lock().unwrap());
+    if outcomes.is_empty() {
+        return;
+    }
+    for line in outcome::table(&outcomes) {
+        rap_info!("{}", line);
+    }
+    if output_format().extension().is_none() {
+        return;
+    }
+    write_structured(tcx, outcome::EXTENSION, outcome::render(&outcomes));
+}
+
+/// Write a JSON array besides the findings into `rapx.<ext>.json`, or into a fragment under cargo-rapx,
+/// which is merged into `rapx.<ext>.json` once all crates are checked.
+pub fn write_structured(tcx: TyCtxt<'_>, ext: &str, content: String) -> PathBuf {
+    let path = report_path(tcx, ext, PathBuf::from(format!("rapx.{ext}.json")));
+    write_report(&path, content);
+    path
+}
+
+/// Write the recorded findings of the current crate in the selected format.
+pub fn flush(tcx: TyCtxt<'_>) {
+    show_used_suppressions();
+    flush_outcomes(tcx);
+    let (suppressed, mut findings): (Vec<Finding>, Vec<Finding>) =
+        std::mem::take(&mut *FINDINGS.lock().unwrap())
+            .into_iter()
+            .partition(|finding| finding.suppression.is_some());
+    apply_baseline(tcx, &mut findings);
+    count_denied(tcx, &findings);
+
+    let format = output_format();
+    let Some(ext) = format.extension() else {
+        return;
+    };
+    let content = match format {
+        OutputFormat::Json => json::render(tcx, &findings),
+        OutputFormat::Sarif => sarif::render(tcx, &[findings.as_slice(), &suppressed].concat()),
+        OutputFormat::Text => unreachable!(),
+    };
+    let path = report_path(tcx, ext, PathBuf::from(format.default_file().unwrap()));
+    write_report(&path, content);
+    rap_info!(
+        "{} finding(s) written into {}.",
+        findings.len(),
+        path.display()
+    );
+}
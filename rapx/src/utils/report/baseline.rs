@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::utils::log::rap_error_and_exit;
+
+/// The extension of the baseline fragments written by each crate under cargo-rapx.
+pub const EXTENSION: &str = "baseline";
+
+/// Known findings recorded in a previous run.
+///
+/// The baseline file has the same layout as the report of `-format=json`.
+/// If the file does not exist yet, rapx records the current findings into it;
+/// otherwise, only findings whose fingerprints are not in the file are reported.
+/// Findings on identical code in a function share a fingerprint, so the file records how many
+/// times each fingerprint is known, and the findings beyond that number are new.
+#[derive(Debug, Clone)]
+pub struct Baseline {
+    pub path: PathBuf,
+    /// `None` if the baseline is being recorded in this run.
+    known: Option<HashMap<String, usize>>,
+}
+
+impl Baseline {
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let known = if path.exists() {
+            let content = fs::read_to_string(&path).unwrap_or_else(|err| {
+                rap_error_and_exit(format!("Failed to read baseline {}: {err}", path.display()))
+            });
+            match fingerprints(&content) {
+                Ok(known) => Some(known),
+                Err(err) => {
+                    rap_error_and_exit(format!("Invalid baseline {}: {err}", path.display()))
+                }
+            }
+        } else {
+            None
+        };
+        Self { path, known }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.known.is_none()
+    }
+
+    /// Whether the finding is known, which uses up one of the known findings of the fingerprint.
+    pub fn take_known(&mut self, fingerprint: &str) -> bool {
+        match self
+            .known
+            .as_mut()
+            .and_then(|known| known.get_mut(fingerprint))
+        {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Count the findings of each fingerprint in a JSON report.
+pub fn fingerprints(content: &str) -> Result<HashMap<String, usize>, String> {
+    let Value::Array(findings) = serde_json::from_str(content).map_err(|err| err.to_string())?
+    else {
+        return Err("expect an array of findings".to_string());
+    };
+    let mut counts = HashMap::new();
+    for finding in findings {
+        match finding.get("fingerprint") {
+            Some(Value::String(fingerprint)) => {
+                *counts.entry(fingerprint.clone()).or_insert(0) += 1
+            }
+            _ => return Err("every finding should have a fingerprint".to_string()),
+        }
+    }
+    Ok(counts)
+}
+
+/// Count the findings of a JSON report.
+pub fn count(content: &str) -> usize {
+    match serde_json::from_str(content) {
+        Ok(Value::Array(findings)) => findings.len(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,69 @@
+use rustc_middle::ty::TyCtxt;
+use serde_json::{json, Value};
+
+use super::{Finding, Region, Trace};
+
+fn label(region: Region, message: &str, primary: bool) -> Value {
+    json!({
+        "message": message,
+        "primary": primary,
+        "file": region.file,
+        "start_line": region.start_line,
+        "start_column": region.start_column,
+        "end_line": region.end_line,
+        "end_column": region.end_column,
+    })
+}
+
+fn trace(trace: &Trace) -> Value {
+    let steps: Vec<Value> = trace
+        .steps
+        .iter()
+        .map(|l| label(Region::from_span(l.span), &l.message, l.primary))
+        .collect();
+    json!({
+        "steps": steps,
+        "blocks": trace.blocks,
+    })
+}
+
+/// Convert a finding into a flat JSON object.
+pub fn finding(tcx: TyCtxt<'_>, finding: &Finding) -> Value {
+    let context = Region::from_span(finding.context);
+    let labels: Vec<Value> = finding
+        .labels
+        .iter()
+        .map(|l| label(Region::from_span(l.span), &l.message, l.primary))
+        .collect();
+    json!({
+        "detector": finding.kind.detector().name(),
+        "kind": finding.kind.rule_id(),
+        "message": finding.message,
+        "crate": tcx.crate_name(finding.def_id.krate).to_string(),
+        "function": tcx.def_path_str(finding.def_id),
+        "file": context.file,
+        "start_line": context.start_line,
+        "end_line": context.end_line,
+        "labels": labels,
+        "traces": finding.traces.iter().map(trace).collect::<Vec<_>>(),
+        "help": finding.help,
+        "fingerprint": finding.fingerprint(tcx),
+    })
+}
+
+/// The report is a JSON array with one object per finding.
+pub fn render(tcx: TyCtxt<'_>, findings: &[Finding]) -> String {
+    let findings: Vec<Value> = findings.iter().map(|f| finding(tcx, f)).collect();
+    serde_json::to_string_pretty(&findings).unwrap()
+}
+
+/// Merge several reports, e.g., one for each crate, into a single array.
+pub fn merge(reports: &[String]) -> String {
+    let mut findings = Vec::new();
+    for content in reports {
+        if let Ok(Value::Array(report)) = serde_json::from_str::<Value>(content) {
+            findings.extend(report);
+        }
+    }
+    serde_json::to_string_pretty(&findings).unwrap()
+}
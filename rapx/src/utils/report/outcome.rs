@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::TyCtxt;
+use serde::{Deserialize, Serialize};
+
+use super::{Detector, Region};
+
+/// The extension of the outcome fragments written by each crate under cargo-rapx,
+/// and of the merged file `rapx.outcomes.json`.
+pub const EXTENSION: &str = "outcomes";
+
+/// The outcome of checking a function, e.g., whether the solver of rCanary verifies it or times out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Outcome {
+    pub detector: String,
+    pub outcome: String,
+    /// Whether the function is fully verified by the detector.
+    pub verified: bool,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub function: String,
+    pub file: String,
+    pub start_line: usize,
+    /// The lines of the loops whose iterations are only checked up to a limit, e.g., `limits.rcanary-unroll`,
+    /// so the function is verified up to that number of iterations.
+    pub unrolled_loops: Vec<usize>,
+}
+
+impl Outcome {
+    pub fn new(
+        tcx: TyCtxt<'_>,
+        detector: Detector,
+        def_id: DefId,
+        outcome: &str,
+        verified: bool,
+    ) -> Self {
+        let region = Region::from_span(tcx.def_span(def_id));
+        Self {
+            detector: detector.name().to_string(),
+            outcome: outcome.to_string(),
+            verified,
+            krate: tcx.crate_name(def_id.krate).to_string(),
+            function: tcx.def_path_str(def_id),
+            file: region.file,
+            start_line: region.start_line,
+            unrolled_loops: Vec::new(),
+        }
+    }
+
+    pub fn unrolled_loops(mut self, lines: Vec<usize>) -> Self {
+        self.unrolled_loops = lines;
+        self
+    }
+}
+
+/// The lines of the summary table: the number of functions with each outcome for each detector,
+/// followed by the functions that are not verified or whose loops are unrolled.
+pub fn table(outcomes: &[Outcome]) -> Vec<String> {
+    let mut counts: BTreeMap<&str, BTreeMap<&str, usize>> = BTreeMap::new();
+    for outcome in outcomes {
+        *counts
+            .entry(&outcome.detector)
+            .or_default()
+            .entry(&outcome.outcome)
+            .or_default() += 1;
+    }
+    let mut lines = Vec::new();
+    for (detector, counts) in counts {
+        let total: usize = counts.values().sum();
+        let verified = outcomes
+            .iter()
+            .filter(|outcome| outcome.detector == detector && outcome.verified)
+            .count();
+        let counts: Vec<String> = counts
+            .iter()
+            .map(|(outcome, count)| format!("{outcome} {count}"))
+            .collect();
+        lines.push(format!(
+            "{detector}: {verified} of {total} function(s) verified ({}).",
+            counts.join(", ")
+        ));
+    }
+    for outcome in outcomes
+        .iter()
+        .filter(|outcome| !outcome.verified || !outcome.unrolled_loops.is_empty())
+    {
+        let mut line = format!(
+            "  {:<12} {} ({}:{})",
+            outcome.outcome, outcome.function, outcome.file, outcome.start_line
+        );
+        if !outcome.unrolled_loops.is_empty() {
+            let loops: Vec<String> = outcome
+                .unrolled_loops
+                .iter()
+                .map(|line| line.to_string())
+                .collect();
+            line.push_str(&format!(", loops unrolled at lines {}", loops.join(", ")));
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// The outcomes are a JSON array with one object per function.
+pub fn render(outcomes: &[Outcome]) -> String {
+    serde_json::to_string_pretty(outcomes).unwrap()
+}
@@ -0,0 +1,54 @@
+use super::suppression::Target;
+use super::{Finding, Severity};
+
+/// The extension of the fragments written by each crate under cargo-rapx,
+/// which contain the number of denied findings.
+pub const EXTENSION: &str = "denied";
+
+/// The exit code of rapx and cargo-rapx when some findings are denied,
+/// which is different from the exit codes of failed compilations.
+pub const DENIED_EXIT_CODE: i32 = 2;
+
+/// Which findings fail the run, e.g., `-deny=uaf,leak` or `-fail-on=warning`.
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    deny: Vec<Target>,
+    fail_on: Option<Severity>,
+}
+
+impl Policy {
+    /// `deny` is a comma-separated list of the names accepted by suppressions,
+    /// and `fail_on` is the lowest severity that fails the run.
+    pub fn new(deny: Option<&str>, fail_on: Option<&str>) -> Result<Self, String> {
+        let mut policy = Policy::default();
+        if let Some(names) = deny {
+            for name in names.split(',').filter(|name| !name.trim().is_empty()) {
+                let target = Target::from_name(name).ok_or_else(|| {
+                    format!(
+                        "Unknown name `{name}` to deny; expect a detector, a kind of bug or `all`."
+                    )
+                })?;
+                policy.deny.push(target);
+            }
+        }
+        if let Some(name) = fail_on {
+            let severity = Severity::from_name(name).ok_or_else(|| {
+                format!("Unknown severity `{name}` to fail on; expect error, warning or note.")
+            })?;
+            policy.fail_on = Some(severity);
+        }
+        Ok(policy)
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.deny.is_empty() || self.fail_on.is_some()
+    }
+
+    pub fn denies(&self, finding: &Finding) -> bool {
+        let severity = finding.kind.severity();
+        self.deny.iter().any(|target| target.matches(finding.kind))
+            || self
+                .fail_on
+                .is_some_and(|fail_on| severity.rank() >= fail_on.rank())
+    }
+}
@@ -0,0 +1,186 @@
+use rustc_hir::def_id::LOCAL_CRATE;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::Span;
+use serde_json::{json, Value};
+
+use super::{Finding, FindingKind, Region, Severity, Trace};
+
+pub const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+pub const SARIF_VERSION: &str = "2.1.0";
+const SRCROOT: &str = "%SRCROOT%";
+
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note => "note",
+    }
+}
+
+fn rules() -> Vec<Value> {
+    FindingKind::ALL
+        .iter()
+        .map(|kind| {
+            json!({
+                "id": kind.rule_id(),
+                "name": kind.rule_id(),
+                "shortDescription": { "text": kind.description() },
+                "defaultConfiguration": { "level": level(kind.severity()) },
+                "properties": { "detector": kind.detector().name() },
+            })
+        })
+        .collect()
+}
+
+fn physical_location(span: Span) -> Value {
+    let region = Region::from_span(span);
+    json!({
+        "artifactLocation": { "uri": region.file, "uriBaseId": SRCROOT },
+        "region": {
+            "startLine": region.start_line,
+            "startColumn": region.start_column,
+            "endLine": region.end_line,
+            "endColumn": region.end_column,
+        },
+    })
+}
+
+/// A trace is a code flow with a single thread, whose last location is the bug.
+fn code_flow(trace: &Trace) -> Value {
+    let locations: Vec<Value> = trace
+        .steps
+        .iter()
+        .map(|step| {
+            json!({
+                "location": {
+                    "physicalLocation": physical_location(step.span),
+                    "message": { "text": step.message },
+                },
+                "importance": if step.primary { "essential" } else { "important" },
+            })
+        })
+        .collect();
+    json!({
+        "message": { "text": format!("path: {}", trace.path()) },
+        "threadFlows": [{ "locations": locations }],
+    })
+}
+
+fn result(tcx: TyCtxt<'_>, finding: &Finding) -> Value {
+    let logical_locations = json!([{
+        "fullyQualifiedName": tcx.def_path_str(finding.def_id),
+        "kind": "function",
+    }]);
+    let mut locations: Vec<Value> = finding
+        .labels
+        .iter()
+        .filter(|label| label.primary)
+        .map(|label| {
+            json!({
+                "physicalLocation": physical_location(label.span),
+                "logicalLocations": logical_locations,
+                "message": { "text": label.message },
+            })
+        })
+        .collect();
+    // Some findings, e.g., memory leaks, do not have a precise location.
+    if locations.is_empty() {
+        locations.push(json!({
+            "physicalLocation": physical_location(finding.context),
+            "logicalLocations": logical_locations,
+        }));
+    }
+    let related_locations: Vec<Value> = finding
+        .labels
+        .iter()
+        .filter(|label| !label.primary)
+        .enumerate()
+        .map(|(id, label)| {
+            json!({
+                "id": id,
+                "physicalLocation": physical_location(label.span),
+                "message": { "text": label.message },
+            })
+        })
+        .collect();
+    let rule_index = FindingKind::ALL
+        .iter()
+        .position(|kind| *kind == finding.kind)
+        .unwrap();
+    let mut message = finding.message.clone();
+    if let Some(help) = &finding.help {
+        message = format!("{message} {help}");
+    }
+    let suppressions: Vec<Value> = finding
+        .suppression
+        .iter()
+        .map(|suppression| {
+            json!({
+                "kind": "inSource",
+                "justification": suppression.reason(),
+                "location": { "physicalLocation": physical_location(suppression.span) },
+            })
+        })
+        .collect();
+    json!({
+        "ruleId": finding.kind.rule_id(),
+        "ruleIndex": rule_index,
+        "level": level(finding.kind.severity()),
+        "message": { "text": message },
+        "locations": locations,
+        "relatedLocations": related_locations,
+        "codeFlows": finding.traces.iter().map(code_flow).collect::<Vec<_>>(),
+        "suppressions": suppressions,
+        "partialFingerprints": { "rapx/v1": finding.fingerprint(tcx) },
+        "properties": { "detector": finding.kind.detector().name() },
+    })
+}
+
+/// Build a SARIF run for the findings of the current crate.
+pub fn run(tcx: TyCtxt<'_>, findings: &[Finding]) -> Value {
+    let src_root = std::env::current_dir()
+        .map(|dir| format!("file://{}/", dir.display()))
+        .unwrap_or_default();
+    json!({
+        "tool": {
+            "driver": {
+                "name": "rapx",
+                "version": env!("CARGO_PKG_VERSION"),
+                "informationUri": env!("CARGO_PKG_HOMEPAGE"),
+                "rules": rules(),
+            }
+        },
+        "originalUriBaseIds": { SRCROOT: { "uri": src_root } },
+        "automationDetails": { "id": format!("rapx/{}", tcx.crate_name(LOCAL_CRATE)) },
+        "results": findings
+            .iter()
+            .map(|finding| result(tcx, finding))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Wrap SARIF runs into a SARIF log.
+pub fn log(runs: Vec<Value>) -> Value {
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": runs,
+    })
+}
+
+pub fn render(tcx: TyCtxt<'_>, findings: &[Finding]) -> String {
+    serde_json::to_string_pretty(&log(vec![run(tcx, findings)])).unwrap()
+}
+
+/// Merge several SARIF logs, e.g., one for each crate, into a single log.
+pub fn merge(logs: &[String]) -> String {
+    let mut runs = Vec::new();
+    for content in logs {
+        if let Ok(Value::Object(mut log)) = serde_json::from_str::<Value>(content) {
+            if let Some(Value::Array(log_runs)) = log.remove("runs") {
+                runs.extend(log_runs);
+            }
+        }
+    }
+    serde_json::to_string_pretty(&log(runs)).unwrap()
+}
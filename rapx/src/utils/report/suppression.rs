@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::{DefId, LocalDefId, LocalModDefId, CRATE_DEF_ID};
+use rustc_middle::ty::TyCtxt;
+use rustc_span::source_map::get_source_map;
+use rustc_span::{Span, Symbol};
+
+use super::{Detector, Finding, FindingKind, Region};
+use crate::rap_warn;
+use crate::utils::log::are_spans_in_same_file;
+
+/// The marker of a suppression comment, e.g., `// rapx-allow: leak -- freed by the C side`.
+pub const ALLOW_COMMENT: &str = "rapx-allow:";
+
+/// What a suppression silences.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    Kind(FindingKind),
+    Detector(Detector),
+    All,
+}
+
+impl Target {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let target = match name.trim() {
+            "all" => Target::All,
+            "safedrop" => Target::Detector(Detector::SafeDrop),
+            "rcanary" => Target::Detector(Detector::RCanary),
+            "senryx" => Target::Detector(Detector::Senryx),
+            "opt" => Target::Detector(Detector::Opt),
+            "double_free" => Target::Kind(FindingKind::DoubleFree),
+            "use_after_free" | "uaf" => Target::Kind(FindingKind::UseAfterFree),
+            "dangling_pointer" | "dangling" => Target::Kind(FindingKind::DanglingPointer),
+            "memory_leak" | "leak" => Target::Kind(FindingKind::MemoryLeak),
+            "contract_failure" => Target::Kind(FindingKind::ContractFailure),
+            "bounds_checking" => Target::Kind(FindingKind::BoundsChecking),
+            "memory_cloning" => Target::Kind(FindingKind::MemoryCloning),
+            _ => return None,
+        };
+        Some(target)
+    }
+
+    pub fn matches(&self, kind: FindingKind) -> bool {
+        match self {
+            Target::Kind(target) => *target == kind,
+            Target::Detector(detector) => *detector == kind.detector(),
+            Target::All => true,
+        }
+    }
+}
+
+/// A `#[rapx::allow(..)]` attribute or a `// rapx-allow: ..` comment in the source code.
+#[derive(Debug, Clone)]
+pub struct Suppression {
+    pub targets: Vec<Target>,
+    pub reason: Option<String>,
+    /// Where the suppression is written.
+    pub span: Span,
+    /// The suppression as written, e.g., `#[rapx::allow(leak)]`.
+    pub source: String,
+}
+
+impl Suppression {
+    pub fn matches(&self, kind: FindingKind) -> bool {
+        self.targets.iter().any(|target| target.matches(kind))
+    }
+
+    pub fn reason(&self) -> &str {
+        self.reason.as_deref().unwrap_or("no reason given")
+    }
+
+    pub fn location(&self) -> String {
+        let region = Region::from_span(self.span);
+        format!("{}:{}", region.file, region.start_line)
+    }
+}
+
+fn parse_targets(names: &[String], span: Span) -> Vec<Target> {
+    names
+        .iter()
+        .filter_map(|name| {
+            let target = Target::from_name(name);
+            if target.is_none() {
+                rap_warn!(
+                    "Unknown suppression `{}` at {}; expect a detector, a kind of bug or `all`.",
+                    name,
+                    Region::from_span(span).file
+                );
+            }
+            target
+        })
+        .collect()
+}
+
+/// Parse `#[rapx::allow(double_free, leak, reason = "...")]`.
+fn from_attrs(tcx: TyCtxt<'_>, def_id: LocalDefId) -> Vec<Suppression> {
+    let rapx = Symbol::intern("rapx");
+    let allow = Symbol::intern("allow");
+    let reason_sym = Symbol::intern("reason");
+    let hir_id = tcx.local_def_id_to_hir_id(def_id);
+    let mut suppressions = Vec::new();
+    for attr in tcx.hir().attrs(hir_id) {
+        if !attr.path_matches(&[rapx, allow]) {
+            continue;
+        }
+        let mut names = Vec::new();
+        let mut reason = None;
+        for item in attr.meta_item_list().unwrap_or_default() {
+            if item.name_or_empty() == reason_sym {
+                reason = item.value_str().map(|s| s.to_string());
+            } else if item.is_word() {
+                names.push(item.name_or_empty().to_string());
+            }
+        }
+        let source = get_source_map()
+            .unwrap()
+            .span_to_snippet(attr.span)
+            .unwrap_or_default();
+        suppressions.push(Suppression {
+            targets: parse_targets(&names, attr.span),
+            reason,
+            span: attr.span,
+            source,
+        });
+    }
+    suppressions
+}
+
+/// Parse `// rapx-allow: double_free, leak -- reason` in the given line.
+fn from_comment(line: &str, span: Span) -> Option<Suppression> {
+    let comment = line.trim_start().strip_prefix("//")?;
+    let (_, body) = comment.split_once(ALLOW_COMMENT)?;
+    let (names, reason) = match body.split_once("--") {
+        Some((names, reason)) => (names, Some(reason.trim().to_string())),
+        None => (body, None),
+    };
+    let names: Vec<String> = names.split(',').map(|s| s.trim().to_string()).collect();
+    Some(Suppression {
+        targets: parse_targets(&names, span),
+        reason: reason.filter(|reason| !reason.is_empty()),
+        span,
+        source: line.trim().to_string(),
+    })
+}
+
+/// Suppression comments in lines `[start, end)` (0-based) of the file containing `anchor`.
+fn from_lines(anchor: Span, start: usize, end: usize) -> Vec<Suppression> {
+    let source_map = get_source_map().unwrap();
+    let file = source_map.lookup_source_file(anchor.lo());
+    (start..end)
+        .filter_map(|idx| {
+            let line = file.get_line(idx)?;
+            let lo = file.line_bounds(idx).start;
+            from_comment(&line, anchor.with_lo(lo).with_hi(lo))
+        })
+        .collect()
+}
+
+/// Suppression comments right above an item (i.e., only comments are allowed in between)
+/// or among its attributes.
+fn from_comments_above(tcx: TyCtxt<'_>, def_id: LocalDefId) -> Vec<Suppression> {
+    let source_map = get_source_map().unwrap();
+    let def_span = tcx.def_span(def_id);
+    if def_span.is_dummy() || def_span.from_expansion() {
+        return Vec::new();
+    }
+    let hir_id = tcx.local_def_id_to_hir_id(def_id);
+    let item_start = tcx
+        .hir()
+        .attrs(hir_id)
+        .iter()
+        .filter(|attr| {
+            !attr.span.from_expansion()
+                && attr.span.lo() < def_span.lo()
+                && are_spans_in_same_file(attr.span, def_span)
+        })
+        .map(|attr| attr.span.lo())
+        .chain(std::iter::once(def_span.lo()))
+        .min()
+        .unwrap();
+    let file = source_map.lookup_source_file(item_start);
+    let first_line = file
+        .lookup_line(file.relative_position(item_start))
+        .unwrap_or(0);
+    let def_line = file
+        .lookup_line(file.relative_position(def_span.lo()))
+        .unwrap_or(0);
+    let mut start = first_line;
+    while start > 0 {
+        match file.get_line(start - 1) {
+            Some(line) if line.trim_start().starts_with("//") => start -= 1,
+            _ => break,
+        }
+    }
+    from_lines(def_span, start, def_line)
+}
+
+/// Suppression comments at the beginning of a module, before its first item.
+fn from_module_head(tcx: TyCtxt<'_>, def_id: LocalDefId) -> Vec<Suppression> {
+    let source_map = get_source_map().unwrap();
+    let (_, inner_span, _) = tcx.hir().get_module(LocalModDefId::new_unchecked(def_id));
+    if inner_span.is_dummy() {
+        return Vec::new();
+    }
+    let file = source_map.lookup_source_file(inner_span.lo());
+    let inner_line = file
+        .lookup_line(file.relative_position(inner_span.lo()))
+        .unwrap_or(0);
+    // The module is either the crate root, declared by `mod foo;` in another file,
+    // or declared inline by `mod foo { .. }`.
+    let def_span = tcx.def_span(def_id);
+    let start = if def_id == CRATE_DEF_ID || !are_spans_in_same_file(def_span, inner_span) {
+        0
+    } else {
+        file.lookup_line(file.relative_position(def_span.lo()))
+            .map_or(0, |line| line + 1)
+    };
+    from_lines(inner_span, start.min(inner_line), inner_line)
+}
+
+fn own_suppressions(tcx: TyCtxt<'_>, def_id: LocalDefId) -> Vec<Suppression> {
+    let mut suppressions = from_attrs(tcx, def_id);
+    match tcx.def_kind(def_id) {
+        DefKind::Mod => {
+            if def_id != CRATE_DEF_ID {
+                suppressions.extend(from_comments_above(tcx, def_id));
+            }
+            suppressions.extend(from_module_head(tcx, def_id));
+        }
+        DefKind::Fn
+        | DefKind::AssocFn
+        | DefKind::Impl { .. }
+        | DefKind::Trait
+        | DefKind::Struct
+        | DefKind::Enum
+        | DefKind::Union
+        | DefKind::Const
+        | DefKind::Static { .. } => suppressions.extend(from_comments_above(tcx, def_id)),
+        _ => {}
+    }
+    suppressions
+}
+
+/// All suppressions of the local crate, and the suppressions in effect for each definition,
+/// including those inherited from the enclosing functions, impls and modules.
+#[derive(Debug, Default)]
+pub struct Suppressions {
+    pub all: Vec<Suppression>,
+    pub used: Vec<usize>,
+    scopes: HashMap<DefId, Vec<usize>>,
+}
+
+impl Suppressions {
+    pub fn collect(tcx: TyCtxt<'_>) -> Self {
+        let mut suppressions = Suppressions::default();
+        let mut local_scopes: HashMap<LocalDefId, Vec<usize>> = HashMap::new();
+        for def_id in tcx.iter_local_def_id() {
+            suppressions.scope_of(tcx, def_id, &mut local_scopes);
+        }
+        suppressions.used = vec![0; suppressions.all.len()];
+        suppressions.scopes = local_scopes
+            .into_iter()
+            .filter(|(_, scope)| !scope.is_empty())
+            .map(|(def_id, scope)| (def_id.to_def_id(), scope))
+            .collect();
+        suppressions
+    }
+
+    fn scope_of(
+        &mut self,
+        tcx: TyCtxt<'_>,
+        def_id: LocalDefId,
+        scopes: &mut HashMap<LocalDefId, Vec<usize>>,
+    ) -> Vec<usize> {
+        if let Some(scope) = scopes.get(&def_id) {
+            return scope.clone();
+        }
+        let mut scope = match tcx.opt_local_parent(def_id) {
+            Some(parent) => self.scope_of(tcx, parent, scopes),
+            None => Vec::new(),
+        };
+        for suppression in own_suppressions(tcx, def_id) {
+            scope.push(self.all.len());
+            self.all.push(suppression);
+        }
+        scopes.insert(def_id, scope.clone());
+        scope
+    }
+
+    /// Find the innermost suppression of the finding, and count it as used.
+    pub fn check(&mut self, finding: &Finding) -> Option<Suppression> {
+        let scope = self.scopes.get(&finding.def_id)?;
+        let idx = *scope
+            .iter()
+            .rev()
+            .find(|idx| self.all[**idx].matches(finding.kind))?;
+        self.used[idx] += 1;
+        Some(self.all[idx].clone())
+    }
+
+    /// Suppressions that have silenced at least one finding, with the numbers of findings.
+    pub fn used(&self) -> Vec<(&Suppression, usize)> {
+        self.all
+            .iter()
+            .zip(self.used.iter())
+            .filter(|(_, count)| **count > 0)
+            .map(|(suppression, count)| (suppression, *count))
+            .collect()
+    }
+}
@@ -0,0 +1,177 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Once;
+
+use rustc_hir::def_id::{CrateNum, LOCAL_CRATE};
+use rustc_middle::ty::TyCtxt;
+use rustc_session::cstore::CrateDepKind;
+use rustc_span::{FileName, RealFileName};
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::core::alias::mop::MopAlias;
+use crate::analysis::core::alias::{FnMap, FnRetAlias};
+use crate::analysis::core::heap_item::{AdtOwner, OwnerUnit, TypeAnalysis};
+use crate::analysis::rcanary::rCanary;
+use crate::utils::cache::{def_hash, def_id, DefHash};
+use crate::utils::config::{config, Summaries};
+use crate::{rap_debug, rap_warn};
+
+/// Environment variable set by cargo-rapx: the folder of the summaries of dependencies,
+/// i.e., `target/rapx/summaries`.
+pub const RAP_SUMMARY_DIR: &str = "RAP_SUMMARY_DIR";
+
+/// The alias summaries of the functions and the ownership layouts of the ADTs defined in a crate,
+/// written into `<crate>-<stable crate id>.json` when cargo-rapx compiles a dependency.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CrateSummary {
+    fn_map: Vec<(DefHash, FnRetAlias)>,
+    adt_owner: Vec<(DefHash, Vec<OwnerUnit>)>,
+}
+
+fn summary_path(tcx: TyCtxt<'_>, cnum: CrateNum) -> Option<PathBuf> {
+    let dir = std::env::var(RAP_SUMMARY_DIR).ok()?;
+    let crate_name = tcx.crate_name(cnum);
+    let crate_id = tcx.stable_crate_id(cnum).as_u64();
+    Some(PathBuf::from(dir).join(format!("{crate_name}-{crate_id:016x}.json")))
+}
+
+/// Compute the summaries of the current crate and write them for the crates depending on it.
+pub fn dump(tcx: TyCtxt<'_>) {
+    let Some(path) = summary_path(tcx, LOCAL_CRATE) else {
+        return;
+    };
+    let mut mop = MopAlias::new(tcx);
+    let fn_map = mop.start();
+    let rcx = Box::leak(Box::new(rCanary::new(tcx)));
+    TypeAnalysis::new(rcx).start();
+
+    // Summaries of other crates are loaded from their own files.
+    let mut summary = CrateSummary::default();
+    for (def_id, fn_alias) in fn_map.iter().filter(|(def_id, _)| def_id.is_local()) {
+        summary
+            .fn_map
+            .push((def_hash(tcx, *def_id), fn_alias.clone()));
+    }
+    for (def_id, units) in rcx
+        .adt_owner()
+        .iter()
+        .filter(|(def_id, _)| def_id.is_local())
+    {
+        summary
+            .adt_owner
+            .push((def_hash(tcx, *def_id), units.clone()));
+    }
+    summary.fn_map.sort_by_key(|(hash, _)| *hash);
+    summary.adt_owner.sort_by_key(|(hash, _)| *hash);
+
+    if let Some(dir) = path.parent() {
+        _ = fs::create_dir_all(dir);
+    }
+    let content = serde_json::to_string(&summary).unwrap();
+    match fs::write(&path, content) {
+        Ok(()) => rap_debug!(
+            "Summaries of {} written into {}: {} function(s), {} ADT(s).",
+            tcx.crate_name(LOCAL_CRATE),
+            path.display(),
+            summary.fn_map.len(),
+            summary.adt_owner.len()
+        ),
+        Err(err) => rap_debug!("Failed to write summaries {}: {err}", path.display()),
+    }
+}
+
+/// Whether cargo-rapx is expected to summarize the dependency: proc macros and the crates of
+/// the standard library are never summarized, nor are registry and git crates unless all
+/// dependencies are summarized.
+fn is_summarized(tcx: TyCtxt<'_>, cnum: CrateNum) -> bool {
+    if tcx.dep_kind(cnum) == CrateDepKind::MacrosOnly {
+        return false;
+    }
+    let span = tcx.def_span(cnum.as_def_id());
+    let FileName::Real(RealFileName::LocalPath(path)) =
+        tcx.sess.source_map().span_to_filename(span)
+    else {
+        return false;
+    };
+    if path.starts_with(&tcx.sess.sysroot) {
+        return false;
+    }
+    if config().summaries.unwrap_or_default() == Summaries::All {
+        return true;
+    }
+    let components: Vec<_> = path.components().map(|c| c.as_os_str()).collect();
+    !components
+        .windows(2)
+        .any(|pair| pair == ["registry", "src"] || pair == ["git", "checkouts"])
+}
+
+/// The summaries written by the dependencies of the current crate, if any.
+/// Missing summaries are reported once, since the calls into those crates are not modeled.
+fn load(tcx: TyCtxt<'_>) -> Vec<CrateSummary> {
+    static WARN_MISSING: Once = Once::new();
+    let mut missing = Vec::new();
+    let summaries = tcx
+        .crates(())
+        .iter()
+        .filter_map(|cnum| {
+            let path = summary_path(tcx, *cnum)?;
+            let Ok(content) = fs::read_to_string(&path) else {
+                if is_summarized(tcx, *cnum) {
+                    missing.push(tcx.crate_name(*cnum));
+                }
+                return None;
+            };
+            let summary = serde_json::from_str(&content).ok();
+            if summary.is_none() {
+                rap_debug!("Failed to read summaries {}.", path.display());
+            }
+            summary
+        })
+        .collect();
+    WARN_MISSING.call_once(|| {
+        for crate_name in missing {
+            rap_warn!(
+                "Summaries of the dependency {crate_name} are missing; run with RAP_CLEAN=true to compile it again."
+            );
+        }
+    });
+    summaries
+}
+
+/// The alias summaries of the functions defined in the dependencies.
+pub fn fn_map(tcx: TyCtxt<'_>) -> FnMap {
+    let mut fn_map = FnMap::default();
+    for summary in load(tcx) {
+        for (hash, fn_alias) in summary.fn_map {
+            if let Some(def_id) = def_id(tcx, hash) {
+                fn_map.insert(def_id, fn_alias);
+            }
+        }
+    }
+    if !fn_map.is_empty() {
+        rap_debug!(
+            "Alias summaries of {} dependent function(s) loaded.",
+            fn_map.len()
+        );
+    }
+    fn_map
+}
+
+/// The ownership layouts of the ADTs defined in the dependencies.
+pub fn adt_owner(tcx: TyCtxt<'_>) -> AdtOwner {
+    let mut adt_owner = AdtOwner::default();
+    for summary in load(tcx) {
+        for (hash, units) in summary.adt_owner {
+            if let Some(def_id) = def_id(tcx, hash) {
+                adt_owner.insert(def_id, units);
+            }
+        }
+    }
+    if !adt_owner.is_empty() {
+        rap_debug!(
+            "Ownership layouts of {} dependent ADT(s) loaded.",
+            adt_owner.len()
+        );
+    }
+    adt_owner
+}
@@ -0,0 +1,28 @@
+#!/bin/bash
+# Check the aliases of the return values queried from MoP by cargo rapx -alias=mop.
+
+log=$(mktemp)
+failed=0
+
+# expect <project> <expected output line>
+expect() {
+  project_dir=$1
+  expected=$2
+  pushd "$project_dir" >/dev/null
+  cargo clean 2>/dev/null
+  cargo rapx -alias=mop >$log 2>&1
+  popd >/dev/null
+  if ansi2txt <$log | grep -qF "|: $expected"; then
+    echo -e "\033[32m$project_dir $expected pass\033[0m"
+  else
+    echo -e "\033[31mError: 'cargo rapx -alias=mop' doesn't output '$expected' in $project_dir \033[0m\nRAP output:"
+    cat $log
+    failed=1
+  fi
+}
+
+expect todo/alias_mop "Return value of ::foo at bb7 may alias: 1, 2, 4"
+expect todo/alias_mop_field "Return value of ::foo at bb0 may alias: 1.1, 2"
+
+rm -f $log
+exit $failed
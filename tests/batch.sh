@@ -43,5 +43,31 @@ find support -type f -name "Cargo.toml" | while read -r cargo_file; do
   fi
 
   # 返回原始目录
+  popd >/dev/null
+done || exit 1
+
+# clean 目录下的项目没有缺陷，不应输出 WARN
+find clean -type f -name "Cargo.toml" | while read -r cargo_file; do
+  project_dir=$(dirname "$cargo_file")
+
+  echo "Processing project in: $project_dir"
+
+  pushd "$project_dir" >/dev/null
+
+  if [ $# -eq 0 ]; then
+    cargo clean
+    popd >/dev/null
+    continue
+  fi
+
+  cmd="cargo rapx $@"
+  $cmd 2>&1 | tee $cur/rapx.txt | ansi2txt | grep -E 'RAP\|(WARN|ERROR)\|'
+  if [ $? -eq 0 ]; then
+    echo -e "\033[31mError: '$cmd' emits diagnostics in the clean project $project_dir \033[0m\nRAP output:"
+    cat $cur/rapx.txt
+    exit 1
+  fi
+  echo -e "\033[32m$project_dir pass\033[0m"
+
   popd >/dev/null
 done
@@ -0,0 +1,12 @@
+// constants, e.g., the lengths of arrays, only have the MIR for const evaluation.
+const N: usize = 3;
+static S: [u8; N] = [0; N];
+struct A;
+impl A {
+    const M: usize = N + 1;
+}
+fn main() {
+    let a = [0u8; N];
+    let b = [0u8; A::M];
+    println!("{} {} {}", a.len(), b.len(), S.len());
+}
@@ -0,0 +1,27 @@
+/*
+ * Copying values that own no heap item does not make their buffers alias.
+ */
+use std::ptr;
+
+struct Pair {
+    a: String,
+    n: usize,
+}
+
+fn copy_bytes() {
+    let s = String::from("abc");
+    let mut v = vec![0u8; 3];
+    unsafe { ptr::copy_nonoverlapping(s.as_ptr(), v.as_mut_ptr(), 3) };
+}
+
+fn copy_len() {
+    let p = Pair { a: String::from("a"), n: 1 };
+    let mut q = Pair { a: String::from("b"), n: 2 };
+    unsafe { ptr::copy_nonoverlapping(&p.n, &mut q.n, 1) };
+    assert_eq!(q.n, 1);
+}
+
+fn main() {
+    copy_bytes();
+    copy_len();
+}
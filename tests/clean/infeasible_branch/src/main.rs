@@ -0,0 +1,18 @@
+// The string is only used if it is not dropped, and written again if it is.
+fn reset(s: &mut String, dropped: bool) -> usize {
+    let p = s as *mut String;
+    if dropped {
+        unsafe { std::ptr::drop_in_place(p) };
+    }
+    let len = if !dropped { s.len() } else { 0 };
+    if dropped {
+        unsafe { std::ptr::write(p, String::new()) };
+    }
+    len
+}
+
+fn main() {
+    let mut s = String::from("hello");
+    assert_eq!(reset(&mut s, false), 5);
+    assert_eq!(reset(&mut s, true), 0);
+}
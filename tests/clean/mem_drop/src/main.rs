@@ -0,0 +1,28 @@
+/*
+ * The values given to mem::drop are dropped, so nothing is leaked.
+ */
+
+struct Owner {
+    b: Box<i32>,
+}
+
+fn drop_owner() {
+    let o = Owner { b: Box::new(1) };
+    drop(o);
+}
+
+fn drop_box() {
+    let b = Box::new(1);
+    drop(b);
+}
+
+fn drop_vec() {
+    let v = vec![1, 2];
+    drop(v);
+}
+
+fn main() {
+    drop_owner();
+    drop_box();
+    drop_vec();
+}
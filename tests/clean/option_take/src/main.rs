@@ -0,0 +1,22 @@
+/*
+ * This is a correct case: `take` and `replace` move the payload out of the option,
+ * so dropping the payload does not free the option.
+ */
+fn take() {
+    let mut o = Some(Box::new(5));
+    let t = o.take();
+    drop(t);
+    println!("{:?}", o);
+}
+
+fn replace() {
+    let mut o = Some(Box::new(5));
+    let t = o.replace(Box::new(6));
+    drop(t);
+    println!("{:?}", o);
+}
+
+fn main() {
+    take();
+    replace();
+}
@@ -0,0 +1,12 @@
+/*
+ * The crate registers the rapx tool itself, and the double free is suppressed.
+ */
+#![feature(register_tool)]
+#![register_tool(rapx)]
+
+#[rapx::allow(double_free, reason = "the string is leaked by the vector")]
+fn main() {
+    let mut s = String::from("a tmp string");
+    let ptr = s.as_mut_ptr();
+    let _v = unsafe { Vec::from_raw_parts(ptr, s.len(), s.len()) };
+}
@@ -0,0 +1,17 @@
+pub fn free_twice(n: usize) {
+    let mut s = String::from("a tmp string");
+    if n > 1 {
+        s.push('a');
+    }
+    if n > 2 {
+        s.push('b');
+    }
+    if n > 3 {
+        s.push('c');
+    }
+    if n > 4 {
+        s.push('d');
+    }
+    let ptr = s.as_mut_ptr();
+    let _v = unsafe { Vec::from_raw_parts(ptr, s.len(), s.len()) };
+}
@@ -0,0 +1,9 @@
+/*
+ * The double free is in a source file out of the scope, which is not analyzed at all:
+ * neither the bug nor the paths over the visit limit are reported.
+ */
+mod ffi;
+
+fn main() {
+    ffi::free_twice(std::env::args().count());
+}
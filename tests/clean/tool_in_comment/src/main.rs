@@ -0,0 +1,11 @@
+//! The crate does not register the rapx tool, although it mentions `register_tool(rapx)` here,
+//! so the tool is still registered for the leak to be suppressed.
+
+// we could use feature(register_tool) here one day
+const NOTE: &str = "#![register_tool(rapx)]";
+
+#[rapx::allow(memory_leak, reason = "the buffer lives until the end of the program")]
+fn main() {
+    let buf = Box::new(NOTE);
+    let _ptr = Box::into_raw(buf);
+}
@@ -0,0 +1,26 @@
+/*
+ * The pointer upgraded from a weak one shares the payload, which is dropped with the last strong pointer.
+ */
+use std::rc::Rc;
+use std::sync::Arc;
+
+fn upgrade_rc() -> i32 {
+    let rc = Rc::new(Box::new(1));
+    let weak = Rc::downgrade(&rc);
+    let strong = weak.upgrade().unwrap();
+    drop(rc);
+    **strong
+}
+
+fn upgrade_arc() -> i32 {
+    let arc = Arc::new(Box::new(1));
+    let weak = Arc::downgrade(&arc);
+    let strong = weak.upgrade().unwrap();
+    drop(arc);
+    **strong
+}
+
+fn main() {
+    upgrade_rc();
+    upgrade_arc();
+}
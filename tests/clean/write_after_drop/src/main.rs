@@ -0,0 +1,19 @@
+/*
+ * ptr::write initializes the place dropped in place again, so it is dropped only once at the end.
+ */
+use std::ptr;
+
+fn write_again() -> i32 {
+    let mut b = Box::new(1);
+    let p = &mut b as *mut Box<i32>;
+    let c = Box::new(2);
+    unsafe {
+        ptr::drop_in_place(p);
+        ptr::write(p, c);
+    }
+    *b
+}
+
+fn main() {
+    write_again();
+}
@@ -0,0 +1,45 @@
+#!/bin/bash
+# Check the output of cargo rapx -F on the test crates whose paths are joined.
+
+log=$(mktemp)
+failed=0
+
+# run <project> <rapx option>
+run() {
+  pushd "$1" >/dev/null
+  cargo clean 2>/dev/null
+  cargo rapx $2 >$log 2>&1
+  popd >/dev/null
+}
+
+# expect <project> <rapx option> <expected output line>
+expect() {
+  run "$1" "$2"
+  if ansi2txt <$log | grep -qF "|: $3"; then
+    echo -e "\033[32m$1 $3 pass\033[0m"
+  else
+    echo -e "\033[31mError: 'cargo rapx $2' doesn't output '$3' in $1 \033[0m\nRAP output:"
+    cat $log
+    failed=1
+  fi
+}
+
+# reject <project> <rapx option> <unexpected output line>
+reject() {
+  run "$1" "$2"
+  if ansi2txt <$log | grep -qF "|: $3"; then
+    echo -e "\033[31mError: 'cargo rapx $2' outputs '$3' in $1 \033[0m\nRAP output:"
+    cat $log
+    failed=1
+  else
+    echo -e "\033[32m$1 no $3 pass\033[0m"
+  fi
+}
+
+expect support/uaf/uaf_joined -F "Double free detected in function use_after_join"
+expect support/uaf/uaf_joined -F "Paths of use_after_join exceed the visit limit: they are joined 5 times and 0 loops are widened, so bugs may be missed."
+expect support/uaf/uaf_joined -F "Paths of walk exceed the visit limit: they are joined 5 times and 1 loops are widened, so bugs may be missed."
+reject support/uaf/uaf_joined -F 'Use after free detected in function "walk"'
+
+rm -f $log
+exit $failed
@@ -0,0 +1,34 @@
+#!/bin/bash
+# Check the spans of the labels reported by cargo rapx -M, read from rapx.json.
+
+failed=0
+
+# expect <project> <function> <label message> <line>
+expect() {
+  project_dir=$1
+  function=$2
+  message=$3
+  line=$4
+  pushd "$project_dir" >/dev/null
+  if [ ! -f rapx.json ]; then
+    cargo clean 2>/dev/null
+    cargo rapx -M -format=json >/dev/null 2>&1
+  fi
+  found=$(jq --arg f "$function" --arg m "$message" --argjson l "$line" \
+    '[.[] | select(.function == $f) | .labels[] | select(.message == $m and .start_line == $l)] | length' rapx.json 2>/dev/null)
+  popd >/dev/null
+  if [ "${found:-0}" -gt 0 ]; then
+    echo -e "\033[32m$project_dir $function:$line pass\033[0m"
+  else
+    echo -e "\033[31mError: 'cargo rapx -M' doesn't label line $line of $function in $project_dir with '$message'\033[0m"
+    failed=1
+  fi
+}
+
+rm -f support/leak/leak_lost_span/rapx.json
+expect support/leak/leak_lost_span overwrite 'The ownership of `p` is lost here.' 3
+expect support/leak/leak_lost_span pass_ptr 'The ownership is lost here.' 13
+expect support/leak/leak_lost_span lost_at_return 'The ownership is lost here.' 19
+rm -f support/leak/leak_lost_span/rapx.json support/leak/leak_lost_span/rapx.outcomes.json
+
+exit $failed
@@ -0,0 +1,56 @@
+#!/bin/bash
+# Check the exit codes of cargo rapx under each -fail-on level and with a baseline.
+# Double frees are errors, memory leaks are warnings and bounds checkings are notes,
+# and a run exits with code 2 when some findings are denied.
+
+log=$(mktemp)
+failed=0
+
+# expect <project> <expected exit code> <arguments of cargo rapx...>
+expect() {
+  project_dir=$1
+  expected=$2
+  shift 2
+  pushd "$project_dir" >/dev/null
+  cargo clean 2>/dev/null
+  cargo rapx "$@" >$log 2>&1
+  code=$?
+  popd >/dev/null
+  if [ $code -eq $expected ]; then
+    echo -e "\033[32m$project_dir $* pass\033[0m"
+  else
+    echo -e "\033[31mError: 'cargo rapx $*' exits with $code instead of $expected in $project_dir \033[0m\nRAP output:"
+    cat $log
+    failed=1
+  fi
+}
+
+expect support/uaf/df_min 2 -F -fail-on=error
+expect support/uaf/df_min 2 -F -fail-on=warning
+expect support/uaf/df_min 2 -F -fail-on=note
+
+expect support/leak/leak_min 0 -M -fail-on=error
+expect support/leak/leak_min 2 -M -fail-on=warning
+expect support/leak/leak_min 2 -M -fail-on=note
+
+expect todo/bounds_len 0 -O -fail-on=error
+expect todo/bounds_len 0 -O -fail-on=warning
+expect todo/bounds_len 2 -O -fail-on=note
+
+# new findings not in the baseline fail the run even if the policy does not deny them,
+# and findings in the baseline are never denied
+baseline=$(mktemp -d)
+echo '[]' >$baseline/empty.json
+expect support/uaf/df_min 2 -F -deny=leak -baseline=$baseline/empty.json
+expect support/uaf/df_min 0 -F -baseline=$baseline/recorded.json
+expect support/uaf/df_min 0 -F -fail-on=error -baseline=$baseline/recorded.json
+
+# a fingerprint is known as many times as it is in the baseline, so the second of two double
+# frees on the same code is new if the baseline only has the first one
+expect support/uaf/df_same_code 0 -F -baseline=$baseline/same_code.json
+jq '.[:1]' $baseline/same_code.json >$baseline/first.json
+expect support/uaf/df_same_code 0 -F -baseline=$baseline/same_code.json
+expect support/uaf/df_same_code 2 -F -baseline=$baseline/first.json
+
+rm -rf $log $baseline
+exit $failed
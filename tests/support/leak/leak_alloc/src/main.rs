@@ -0,0 +1,10 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+
+fn main() {
+    let layout = Layout::new::<u64>();
+    unsafe {
+        let ptr1 = System.alloc(layout);
+        let ptr2 = System.realloc(ptr1, layout, 16);
+        ptr2.write(1);
+    }
+}
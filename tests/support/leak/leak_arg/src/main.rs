@@ -0,0 +1,51 @@
+/*
+ * This is a buggy case: the helpers leak the boxes passed to them,
+ * so the leaks are reported both in the helpers and at their call sites.
+ */
+
+// The pointer is dropped without freeing the box.
+fn forget_box(b: Box<i32>) {
+    let _p = Box::into_raw(b);
+}
+
+// The address of the box is returned as an integer, which does not own it.
+fn box_address(b: Box<i32>) -> usize {
+    Box::into_raw(b) as usize
+}
+
+// The box is freed, so the callers do not leak it.
+fn free_box(b: Box<i32>) {
+    let p = Box::into_raw(b);
+    unsafe {
+        drop(Box::from_raw(p));
+    }
+}
+
+// The returned pointer owns the box, which is freed by the caller.
+fn into_handle(b: Box<i32>) -> *mut i32 {
+    Box::into_raw(b)
+}
+
+fn leak_by_forget() {
+    let b = Box::new(1);
+    forget_box(b);
+}
+
+fn leak_by_address() -> usize {
+    let b = Box::new(2);
+    box_address(b)
+}
+
+fn no_leak() {
+    free_box(Box::new(3));
+    let handle = into_handle(Box::new(4));
+    unsafe {
+        drop(Box::from_raw(handle));
+    }
+}
+
+fn main() {
+    leak_by_forget();
+    leak_by_address();
+    no_leak();
+}
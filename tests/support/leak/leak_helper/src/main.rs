@@ -0,0 +1,31 @@
+struct Node {
+    value: usize,
+}
+
+// The returned pointer owns the node.
+fn make_handle(value: usize) -> *mut Node {
+    Box::into_raw(Box::new(Node { value }))
+}
+
+// Takes the ownership of the node back and frees it.
+fn release(handle: *mut Node) {
+    unsafe {
+        drop(Box::from_raw(handle));
+    }
+}
+
+// The node owned by the handle is never freed.
+fn leaked(value: usize) -> bool {
+    let handle = make_handle(value);
+    handle.is_null()
+}
+
+fn released(value: usize) {
+    let handle = make_handle(value);
+    release(handle);
+}
+
+fn main() {
+    leaked(1);
+    released(2);
+}
@@ -0,0 +1,12 @@
+// The buffers taken from the pool are leaked in every iteration of the loop.
+fn fill(pool: &mut Vec<Box<[u8; 64]>>, n: usize) {
+    for _ in 0..n {
+        let buf = pool.pop().unwrap_or_else(|| Box::new([0; 64]));
+        let _raw = Box::into_raw(buf);
+    }
+}
+
+fn main() {
+    let mut pool = Vec::new();
+    fill(&mut pool, 4);
+}
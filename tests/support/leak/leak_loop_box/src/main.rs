@@ -0,0 +1,24 @@
+// The box is dereferenced in the loop and leaked after it.
+fn sum(b: Box<[i32; 4]>) -> i32 {
+    let mut s = 0;
+    for x in b.iter() {
+        s += x;
+    }
+    let _raw = Box::into_raw(b);
+    s
+}
+
+// The box is dereferenced in the loop and dropped after it.
+fn sum_range(n: i32) -> i32 {
+    let b = Box::new(n);
+    let mut s = 0;
+    for i in 0..*b {
+        s += i;
+    }
+    s
+}
+
+fn main() {
+    println!("{}", sum(Box::new([1, 2, 3, 4])));
+    println!("{}", sum_range(3));
+}
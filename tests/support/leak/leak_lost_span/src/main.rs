@@ -0,0 +1,25 @@
+fn overwrite() {
+    let mut p = Box::into_raw(Box::new(1));
+    p = std::ptr::null_mut();
+    println!("{:?}", p);
+}
+
+fn show(p: *mut i32) {
+    println!("{:?}", p);
+}
+
+fn pass_ptr() {
+    let p = Box::into_raw(Box::new(2));
+    show(p);
+}
+
+fn lost_at_return() {
+    let p = Box::into_raw(Box::new(3));
+    println!("{:?}", p);
+}
+
+fn main() {
+    overwrite();
+    pass_ptr();
+    lost_at_return();
+}
@@ -0,0 +1,15 @@
+/*
+ * This is a buggy case: dropping the holder by mem::drop does not free the heap item
+ * owned by its raw pointer.
+ */
+
+struct Holder {
+    p: *mut i32,
+}
+
+fn main() {
+    let h = Holder {
+        p: Box::into_raw(Box::new(1)),
+    };
+    drop(h);
+}
@@ -0,0 +1,7 @@
+fn main() {
+    let a = Box::new(1);
+    let b = unsafe { std::ptr::read(&a) };
+    // the arguments of a closure are passed in a tuple, whose fields alias them one by one.
+    let consume = |x: Box<i32>| drop(x);
+    consume(b);
+}
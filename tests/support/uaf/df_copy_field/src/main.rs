@@ -0,0 +1,16 @@
+/*
+ * Copying a field that owns a heap item into another struct frees the item twice.
+ */
+use std::ptr;
+
+struct Pair {
+    a: String,
+    n: usize,
+}
+
+fn main() {
+    let p = Pair { a: String::from("a"), n: 1 };
+    let mut q = Pair { a: String::from("b"), n: 2 };
+    unsafe { ptr::copy_nonoverlapping(&p.a, &mut q.a, 1) };
+    assert_eq!(p.n + q.n, 3);
+}
@@ -0,0 +1,11 @@
+// The two double frees have the same code, and so the same fingerprint.
+fn main() {
+    {
+        let mut s = String::from("s");
+        unsafe { std::ptr::drop_in_place(&mut s) };
+    }
+    {
+        let mut s = String::from("s");
+        unsafe { std::ptr::drop_in_place(&mut s) };
+    }
+}
@@ -0,0 +1,12 @@
+/*
+ * This is a buggy case: the string is dropped by the method `<*mut T>::drop_in_place`,
+ * and then used and dropped again by the box.
+ */
+
+fn main() {
+    let p = Box::into_raw(Box::new(String::from("a tmp string")));
+    unsafe {
+        p.drop_in_place();
+    }
+    let _b = unsafe { Box::from_raw(p) };
+}
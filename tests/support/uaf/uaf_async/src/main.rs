@@ -0,0 +1,14 @@
+async fn ready() {}
+
+async fn task(v: Vec<String>) {
+    let ptr = v.as_ptr();
+    ready().await;
+    drop(v);
+    // the pointer is kept in the state machine across the await
+    ready().await;
+    println!("{}", unsafe { &*ptr });
+}
+
+fn main() {
+    let _ = task(vec![String::from("hello")]);
+}
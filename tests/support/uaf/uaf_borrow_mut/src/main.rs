@@ -0,0 +1,15 @@
+/*
+ * This is a buggy case: use-after-free through a mutable borrow of a dropped place.
+ * Only the destination of `ptr::write`, which initializes the place again, may point to dropped memory.
+ */
+use std::ptr;
+
+fn main() {
+    let mut x = Box::new(1);
+    let p = &mut x as *mut Box<i32>;
+    unsafe {
+        ptr::drop_in_place(p);
+        let r = &mut *p;
+        **r = 2;
+    }
+}
@@ -0,0 +1,8 @@
+fn main() {
+    let v = vec![String::from("hello")];
+    let ptr = v.as_ptr();
+    // the vector is captured by the closure and dropped when it is called
+    let consume = move || drop(v);
+    consume();
+    println!("{}", unsafe { &*ptr });
+}
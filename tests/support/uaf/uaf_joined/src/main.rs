@@ -0,0 +1,34 @@
+use std::ptr;
+
+struct Node {
+    next: Option<Box<Node>>,
+}
+
+// The box is dropped on both branches, so it is still dropped after the paths are joined.
+fn use_after_join(flag: bool, other: bool) -> i32 {
+    let mut b = Box::new(1);
+    let p = &mut b as *mut Box<i32>;
+    if flag {
+        unsafe { ptr::drop_in_place(p) };
+    } else {
+        unsafe { ptr::drop_in_place(p) };
+    }
+    let n = if other { 1 } else { 2 };
+    *b + n
+}
+
+// Each node is dropped when the list moves on to the next one, which is no bug although the loop
+// is analyzed in rounds and widened.
+fn walk(mut cur: Box<Node>, flag: bool) -> usize {
+    let mut n = if flag { 1 } else { 0 };
+    while let Some(next) = cur.next.take() {
+        cur = next;
+        n += 1;
+    }
+    n
+}
+
+fn main() {
+    use_after_join(true, false);
+    walk(Box::new(Node { next: Some(Box::new(Node { next: None })) }), true);
+}
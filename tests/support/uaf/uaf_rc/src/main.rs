@@ -0,0 +1,13 @@
+use std::rc::Rc;
+
+fn main() {
+    let rc = Rc::new(String::from("hello"));
+    let shared = Rc::clone(&rc);
+    let ptr = Rc::into_raw(shared);
+    // the raw pointer holds the count taken by `shared`
+    let owner = unsafe { Rc::from_raw(ptr) };
+    drop(owner);
+    drop(rc);
+    // the last `Rc` is gone, so the payload has been freed
+    println!("{}", unsafe { &*ptr });
+}